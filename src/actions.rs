@@ -1,110 +1,3270 @@
+use crate::cache::{lookup_remote_url, store_remote_url};
+use crate::config::{Config, RepoConfig};
+use crate::error::GitOpenError;
+use crate::git::{GitBackend, GitOutput};
 use crate::match_logic::{
-    get_commit_link, get_line_number_link, parse_path_and_line_arg, parse_url_from_git,
+    branch_choices, diagnostic_location, explain_push_failure, find_change_id, find_pr_url,
+    get_blame_link, get_commit_link, get_history_link, get_host, get_line_number_link,
+    get_raw_link, get_ssh_host, get_tree_link, is_number, parse_compare_range, parse_file_status,
+    parse_path_and_line_arg, parse_url_from_git, replace_host, repo_owner, resolve_repo_path,
+    resolve_submodule_url, split_commit_message, split_repo_url, FileAtLine, FileStatus, Submodule,
+};
+use crate::output::{progress, Link, Output};
+use crate::picker::{ask, pick};
+use crate::providers::{
+    provider_for_config, ArchiveFormat, CodeSearch, GitRef, NewPullRequest, ProjectPage, Provider,
+    SettingsSection, Sourcegraph,
 };
 use anyhow::anyhow;
 use anyhow::Result as AnyhowResult;
-use regex::Regex;
-use std::io::{Error, ErrorKind};
-use std::process::{Command, Stdio};
-
-fn get_local_branch_name() -> AnyhowResult<String> {
-    let git_repo = Command::new("git")
-        .args(["symbolic-ref", "HEAD"])
-        .stdout(Stdio::piped())
-        .output()?;
-
-    let stdout = String::from_utf8(git_repo.stdout)?;
-    if stdout.starts_with("refs/heads/") {
-        Ok(stdout[11..].trim().to_string())
-    } else {
-        Err(Error::new(ErrorKind::Other, "oh no!").into())
+use std::collections::{BTreeSet, HashSet};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::debug;
+
+/// Which remote to open: `name` when one was given, and whether gitopen may
+/// ask on the terminal when it can't tell.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RemoteSelection<'a> {
+    pub name: Option<&'a str>,
+    pub interactive: bool,
+}
+
+/// How file links are made: `permalink` pins them to the HEAD commit,
+/// `strict` refuses links the remote may not match, and `push` pushes the
+/// current branch first so it does. `raw` links the files' bare content.
+/// `sourcegraph` links the repository, files and commits on Sourcegraph.
+/// `file_action` is what files given without a line open.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LinkOptions {
+    pub permalink: bool,
+    pub strict: bool,
+    pub push: bool,
+    pub raw: bool,
+    pub sourcegraph: bool,
+    pub file_action: FileAction,
+}
+
+/// What bare `gitopen` opens, set with `default-action` in the config.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultAction {
+    /// The repository's home page.
+    #[default]
+    Repo,
+    /// The tree of the current branch.
+    Branch,
+    /// The pull request of the current branch.
+    Pr,
+    /// The HEAD commit.
+    Commit,
+}
+
+impl DefaultAction {
+    pub const NAMES: &'static [&'static str] = &["repo", "branch", "pr", "commit"];
+
+    pub fn from_name(name: &str) -> AnyhowResult<DefaultAction> {
+        match name {
+            "repo" => Ok(DefaultAction::Repo),
+            "branch" => Ok(DefaultAction::Branch),
+            "pr" => Ok(DefaultAction::Pr),
+            "commit" => Ok(DefaultAction::Commit),
+            _ => Err(anyhow!(
+                "Unknown default action '{}', the actions are: {}",
+                name,
+                DefaultAction::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// What `gitopen <path>` opens for a file without a line, set with
+/// `file-action` in the config.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FileAction {
+    /// The file's page.
+    #[default]
+    Blob,
+    Blame,
+    History,
+}
+
+impl FileAction {
+    pub const NAMES: &'static [&'static str] = &["blob", "blame", "history"];
+
+    pub fn from_name(name: &str) -> AnyhowResult<FileAction> {
+        match name {
+            "blob" => Ok(FileAction::Blob),
+            "blame" => Ok(FileAction::Blame),
+            "history" => Ok(FileAction::History),
+            _ => Err(anyhow!(
+                "Unknown file action '{}', the actions are: {}",
+                name,
+                FileAction::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// The instance `--sourcegraph` links to unless the config names another.
+const SOURCEGRAPH_URL: &str = "https://sourcegraph.com";
+
+/// What `gitopen -p` passes on to `git push`: `--force-with-lease`, for
+/// branches that were rebased, and `--push-option`s for the remote.
+#[derive(Debug, Default, Clone)]
+pub struct PushOptions<'a> {
+    pub force_with_lease: bool,
+    pub push_options: Vec<&'a str>,
+}
+
+/// What `gitopen pr new` prefills. `fill` takes the title and body missing
+/// here from the last commit's message, and `base` defaults to the remote's
+/// default branch. `draft` opens it as a work in progress.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NewPrOptions<'a> {
+    pub title: Option<&'a str>,
+    pub body: Option<&'a str>,
+    pub base: Option<&'a str>,
+    pub fill: bool,
+    pub draft: bool,
+}
+
+pub(crate) fn get_local_branch_name(git: &dyn GitBackend) -> AnyhowResult<String> {
+    match git.symbolic_ref("HEAD")? {
+        Some(head) => match head.strip_prefix("refs/heads/") {
+            Some(branch) => Ok(branch.to_string()),
+            None => Err(GitOpenError::DetachedHead.into()),
+        },
+        None => Err(GitOpenError::DetachedHead.into()),
+    }
+}
+
+fn get_remote_branch_name(git: &dyn GitBackend, local_branch_name: String) -> AnyhowResult<String> {
+    Ok(git
+        .config_get(&format!("branch.{}.remote", local_branch_name))?
+        .unwrap_or_default())
+}
+
+/// Resolves any revision git understands (`HEAD~2`, `v1.4.0`, a short SHA)
+/// to the full SHA of the commit it points at.
+pub(crate) fn resolve_commit_sha(git: &dyn GitBackend, rev: &str) -> AnyhowResult<String> {
+    git.rev_parse(&format!("{}^{{commit}}", rev))?
+        .ok_or_else(|| anyhow!("'{}' does not name a commit in this repository", rev))
+}
+
+fn get_head_sha(git: &dyn GitBackend) -> AnyhowResult<String> {
+    resolve_commit_sha(git, "HEAD")
+}
+
+/// Checks that a ref exists, either locally or as a remote-tracking ref.
+fn verify_ref(git: &dyn GitBackend, rev: &str, remote_name: &str) -> AnyhowResult<()> {
+    resolve_commit_sha(git, rev)
+        .or_else(|_| resolve_commit_sha(git, &format!("{}/{}", remote_name, rev)))
+        .map_err(|_| {
+            anyhow!(
+                "'{}' does not name a commit locally or on '{}'",
+                rev,
+                remote_name
+            )
+        })?;
+    Ok(())
+}
+
+/// Reads the default branch of a remote from `refs/remotes/<remote>/HEAD`,
+/// which `git clone` (or `git remote set-head <remote> --auto`) sets up.
+fn get_default_branch(git: &dyn GitBackend, remote_name: &str) -> AnyhowResult<String> {
+    if let Some(default_branch) = get_repo_config(git)?.default_branch {
+        return Ok(default_branch);
+    }
+    let remote_head = git
+        .symbolic_ref(&format!("refs/remotes/{}/HEAD", remote_name))?
+        .unwrap_or_default();
+    match remote_head.strip_prefix(&format!("refs/remotes/{}/", remote_name)) {
+        Some(branch) => Ok(branch.to_string()),
+        None => Err(anyhow!(
+            "Default branch of '{}' is unknown, run `git remote set-head {} --auto`",
+            remote_name,
+            remote_name
+        )),
+    }
+}
+
+/// What file links point at: a branch, or a commit for permalinks and when
+/// there is no branch to link to.
+pub(crate) enum LinkTarget {
+    Branch(String),
+    Commit(String),
+}
+
+impl LinkTarget {
+    pub(crate) fn as_git_ref(&self) -> GitRef<'_> {
+        match self {
+            LinkTarget::Branch(branch) => GitRef::Branch(branch),
+            LinkTarget::Commit(sha) => GitRef::Commit(sha),
+        }
     }
 }
 
-fn get_remote_branch_name(local_branch_name: String) -> AnyhowResult<String> {
-    let git_repo = Command::new("git")
-        .args([
-            "config",
-            "--get",
-            &format!("branch.{}.remote", local_branch_name),
-        ])
-        .stdout(Stdio::piped())
-        .output()?;
+fn is_on_remote(
+    git: &dyn GitBackend,
+    local_branch_name: &str,
+    remote_name: &str,
+) -> AnyhowResult<bool> {
+    let has_upstream = git
+        .rev_parse(&format!("{}@{{upstream}}", local_branch_name))?
+        .is_some();
+    Ok(has_upstream
+        || resolve_commit_sha(
+            git,
+            &format!("refs/remotes/{}/{}", remote_name, local_branch_name),
+        )
+        .is_ok())
+}
+
+/// The checked-out branch, unless the remote doesn't know it yet, in which
+/// case the links go to its default branch. On a detached HEAD, as during a
+/// rebase or bisect and in CI checkouts, there is no branch and the links
+/// point at the checked-out commit. Without a default branch either, the
+/// links go to the unpushed branch, with a warning, and `--strict` refuses.
+pub(crate) fn get_link_target(
+    git: &dyn GitBackend,
+    remote_name: &str,
+    options: LinkOptions,
+) -> AnyhowResult<LinkTarget> {
+    if options.permalink {
+        return Ok(LinkTarget::Commit(get_head_sha(git)?));
+    }
+    let local_branch_name = match get_local_branch_name(git) {
+        Ok(local_branch_name) => local_branch_name,
+        Err(_) => return Ok(LinkTarget::Commit(get_head_sha(git)?)),
+    };
+    if is_on_remote(git, &local_branch_name, remote_name)? {
+        return Ok(LinkTarget::Branch(local_branch_name));
+    }
+    match get_default_branch(git, remote_name) {
+        Ok(default_branch) => {
+            warning!(
+                "'{}' is not pushed to '{}', linking to '{}' instead",
+                local_branch_name,
+                remote_name,
+                default_branch
+            );
+            Ok(LinkTarget::Branch(default_branch))
+        }
+        Err(_) => {
+            check_remote_mismatches(
+                vec![format!(
+                    "'{}' is not pushed to '{}', whose default branch is unknown, the link won't work until it is",
+                    local_branch_name, remote_name
+                )],
+                options.strict,
+            )?;
+            Ok(LinkTarget::Branch(local_branch_name))
+        }
+    }
+}
+
+fn is_commit_on_remote(
+    git: &dyn GitBackend,
+    commit_sha: &str,
+    remote_name: &str,
+) -> AnyhowResult<bool> {
+    Ok(!git
+        .remote_branches_containing(commit_sha, &format!("{}/*", remote_name))?
+        .is_empty())
+}
+
+/// Lists the reasons the remote may show other lines than the local file:
+/// the file is untracked or modified, or the commits are not pushed.
+fn remote_mismatches(
+    git: &dyn GitBackend,
+    repo_path: &str,
+    remote_name: &str,
+    link_target: &LinkTarget,
+) -> AnyhowResult<Vec<String>> {
+    let mut mismatches = Vec::new();
+    let git_status = git.status(&format!(":(top){}", repo_path))?;
+    match parse_file_status(&git_status) {
+        FileStatus::Untracked => mismatches.push(format!(
+            "'{}' is not tracked by git, the remote doesn't have it",
+            repo_path
+        )),
+        FileStatus::Modified => mismatches.push(format!(
+            "'{}' has uncommitted changes, the remote may show different lines",
+            repo_path
+        )),
+        FileStatus::Clean => {}
+    }
 
-    let stdout = String::from_utf8(git_repo.stdout)?.trim().to_string();
-    Ok(stdout)
+    match link_target {
+        LinkTarget::Branch(branch) => {
+            let range = format!("refs/remotes/{}/{}..HEAD", remote_name, branch);
+            match git.count_commits(&range)? {
+                Some(0) => {}
+                Some(ahead) => mismatches.push(format!(
+                    "HEAD is {} commit(s) ahead of '{}/{}', the remote may show different lines",
+                    ahead, remote_name, branch
+                )),
+                // `get_link_target` already said so about the checked-out
+                // branch when it has no upstream.
+                None if is_reported_unpushed(git, branch)? => {}
+                None => mismatches.push(format!(
+                    "'{}' is not on '{}', the link won't work until it is pushed",
+                    branch, remote_name
+                )),
+            }
+        }
+        LinkTarget::Commit(commit_sha) => {
+            mismatches.extend(unpushed_commit(git, commit_sha, remote_name)?);
+        }
+    }
+    Ok(mismatches)
 }
 
-// TODO: Add caching (`cached` crate)
-fn get_parsed_url(remote_branch_name: String) -> AnyhowResult<String> {
-    let git_repo = Command::new("git")
-        .args([
-            "config",
-            "--get",
-            &format!("remote.{}.url", remote_branch_name),
-        ])
-        .stdout(Stdio::piped())
-        .output()?;
+/// Whether `branch` is the checked-out branch without an upstream, which
+/// `get_link_target` only links to after warning it isn't pushed.
+fn is_reported_unpushed(git: &dyn GitBackend, branch: &str) -> AnyhowResult<bool> {
+    let is_checked_out = get_local_branch_name(git).is_ok_and(|current| current == branch);
+    Ok(is_checked_out
+        && git
+            .rev_parse(&format!("{}@{{upstream}}", branch))?
+            .is_none())
+}
 
-    let stdout = String::from_utf8(git_repo.stdout)?;
-    let parsed_url = parse_url_from_git(&stdout)?;
+/// Says that the remote doesn't have the commit, when no branch of it has.
+fn unpushed_commit(
+    git: &dyn GitBackend,
+    commit_sha: &str,
+    remote_name: &str,
+) -> AnyhowResult<Option<String>> {
+    if is_commit_on_remote(git, commit_sha, remote_name)? {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "{} is not pushed to '{}' yet, the link won't work until it is",
+        commit_sha, remote_name
+    )))
+}
+
+/// Warns about the mismatches, or with `--strict` refuses to link at all.
+fn check_remote_mismatches(mismatches: Vec<String>, strict: bool) -> AnyhowResult<()> {
+    if strict && !mismatches.is_empty() {
+        return Err(anyhow!("{}", mismatches.join("\n")));
+    }
+    for mismatch in mismatches {
+        warning!("{}", mismatch);
+    }
+    Ok(())
+}
+
+/// Pushes `branch` to the remote, setting it as the branch's upstream when
+/// there is none yet, unless `gitopen.setUpstream` is false.
+fn push_branch(
+    git: &dyn GitBackend,
+    remote_name: &str,
+    branch: &str,
+    push_options: &PushOptions,
+) -> AnyhowResult<GitOutput> {
+    let has_upstream = git
+        .config_get(&format!("branch.{}.merge", branch))?
+        .is_some();
+    let mut args = vec![];
+    if !has_upstream && get_repo_config(git)?.set_upstream != Some(false) {
+        args.push("--set-upstream");
+    }
+    if push_options.force_with_lease {
+        args.push("--force-with-lease");
+    }
+    for push_option in &push_options.push_options {
+        args.extend_from_slice(&["--push-option", push_option]);
+    }
+    args.extend_from_slice(&[remote_name, branch]);
+    let git_push = git.push(&args)?;
+    if !git_push.success {
+        let stderr = git_push.stderr.trim_end();
+        return Err(GitOpenError::PushFailed {
+            branch: branch.to_string(),
+            remote: remote_name.to_string(),
+            reason: explain_push_failure(stderr).map(str::to_string),
+            output: stderr.to_string(),
+        }
+        .into());
+    }
+    progress("Pushed", &format!("'{}' to '{}'", branch, remote_name));
+    Ok(git_push)
+}
+
+/// The remote `branch.<name>.pushRemote` or else `remote.pushDefault`
+/// sends the branch to, in triangular workflows that fetch from one remote
+/// and push to another.
+fn get_configured_push_remote(git: &dyn GitBackend, branch: &str) -> AnyhowResult<Option<String>> {
+    if let Some(push_remote) = git.config_get(&format!("branch.{}.pushRemote", branch))? {
+        return Ok(Some(push_remote));
+    }
+    git.config_get("remote.pushDefault")
+}
+
+/// Where pushes of the current branch go: the remote that was asked for,
+/// or else the configured push remote, or else the one links are for.
+fn get_push_remote_name(git: &dyn GitBackend, remote: RemoteSelection) -> AnyhowResult<String> {
+    if remote.name.is_none() {
+        if let Some(push_remote) = get_configured_push_remote(git, &get_local_branch_name(git)?)? {
+            return Ok(push_remote);
+        }
+    }
+    get_remote_name(git, remote)
+}
+
+/// Pushes the current branch so links to it show the local commits.
+fn push_current_branch(git: &dyn GitBackend, remote: RemoteSelection) -> AnyhowResult<()> {
+    push_branch(
+        git,
+        &get_push_remote_name(git, remote)?,
+        &get_local_branch_name(git)?,
+        &PushOptions::default(),
+    )?;
+    Ok(())
+}
+
+pub(crate) fn get_parsed_url(
+    git: &dyn GitBackend,
+    remote_branch_name: String,
+) -> AnyhowResult<String> {
+    let git_dir = git.git_dir();
+    if let Some(git_dir) = &git_dir {
+        if let Some(parsed_url) = lookup_remote_url(git_dir, &remote_branch_name) {
+            debug!(parsed_url, "the URL of {} is cached", remote_branch_name);
+            return Ok(parsed_url);
+        }
+    }
+    // Unlike `git config remote.<name>.url`, this applies `url.<base>.insteadOf`
+    // rewrites, so aliases like `gh:user/repo` come back as the real URL.
+    let stdout = git
+        .remote_url(&remote_branch_name)?
+        .ok_or_else(|| anyhow!("Remote '{}' has no URL", remote_branch_name))?;
+    let parsed_url = web_url_from_git(git, &stdout)?;
+    if let Some(git_dir) = &git_dir {
+        store_remote_url(git_dir, &remote_branch_name, &parsed_url);
+    }
 
     Ok(parsed_url)
 }
 
-pub fn open_repo() -> AnyhowResult<()> {
-    let local_branch_name = get_local_branch_name()?;
-    let remote_branch_name = get_remote_branch_name(local_branch_name)?;
-    let parsed_url = get_parsed_url(remote_branch_name)?;
-    webbrowser::open(&parsed_url)?;
+/// The web URL of a clone URL, with ssh host aliases resolved.
+fn web_url_from_git(git: &dyn GitBackend, remote_url: &str) -> AnyhowResult<String> {
+    let mut parsed_url = parse_url_from_git(remote_url)?;
+    debug!(remote_url, parsed_url, "read the remote URL");
+    // Only the host is renamed, and only when the web URL is still on it.
+    if let Some(alias) = get_ssh_host(remote_url).filter(|alias| get_host(&parsed_url) == *alias) {
+        if let Some(hostname) = git.ssh_hostname(alias) {
+            debug!(hostname, "~/.ssh/config resolves the host alias {}", alias);
+            parsed_url = replace_host(&parsed_url, &hostname);
+        }
+    }
+    Ok(parsed_url)
+}
+
+/// A submodule's repository on the web, at the commit the superproject
+/// pins it at.
+struct SubmoduleRepo {
+    parsed_url: String,
+    provider: Box<dyn Provider>,
+    commit_sha: String,
+}
+
+impl SubmoduleRepo {
+    fn new(
+        git: &dyn GitBackend,
+        submodule: &Submodule,
+        remote_name: &str,
+        options: LinkOptions,
+    ) -> AnyhowResult<SubmoduleRepo> {
+        // Relative submodule URLs are relative to the superproject's remote.
+        let remote_url = git.remote_url(remote_name)?.unwrap_or_default();
+        let parsed_url =
+            web_url_from_git(git, &resolve_submodule_url(&remote_url, &submodule.url))?;
+        // The superproject's `.gitopen.toml` doesn't describe the submodule.
+        let repo_config = RepoConfig::default();
+        let provider = on_sourcegraph(
+            git,
+            provider_for_config(
+                &parsed_url,
+                &load_config(git, &parsed_url, &repo_config)?,
+                &repo_config,
+            )?,
+            options,
+        )?;
+        let commit_sha = git
+            .rev_parse(&format!("HEAD:{}", submodule.path))?
+            .ok_or_else(|| anyhow!("HEAD records no commit for submodule '{}'", submodule.path))?;
+        Ok(SubmoduleRepo {
+            parsed_url,
+            provider,
+            commit_sha,
+        })
+    }
+}
+
+/// The submodule `repo_path` is in, if any, and the path inside it.
+fn find_submodule<'a>(
+    submodules: &'a [Submodule],
+    repo_path: &'a str,
+) -> Option<(&'a Submodule, &'a str)> {
+    submodules
+        .iter()
+        .find_map(|submodule| match repo_path.strip_prefix(&submodule.path)? {
+            "" => Some((submodule, "")),
+            rest => Some((submodule, rest.strip_prefix('/')?)),
+        })
+}
+
+pub(crate) fn get_toplevel(git: &dyn GitBackend) -> AnyhowResult<PathBuf> {
+    git.toplevel()?.ok_or_else(|| GitOpenError::NotARepo.into())
+}
+
+fn to_repo_path(git: &dyn GitBackend, input: &str) -> AnyhowResult<String> {
+    Ok(resolve_repo_path(
+        input,
+        &env::current_dir()?,
+        &get_toplevel(git)?,
+    ))
+}
+
+/// Reads `.gitopen.toml` from the repository root and applies the
+/// `gitopen.*` git config values on top.
+pub(crate) fn get_repo_config(git: &dyn GitBackend) -> AnyhowResult<RepoConfig> {
+    let mut repo_config = match get_toplevel(git) {
+        Ok(toplevel) if toplevel.join(".gitopen.toml").is_file() => {
+            let path = toplevel.join(".gitopen.toml");
+            RepoConfig::parse(&fs::read_to_string(&path)?)
+                .map_err(|err| anyhow!("Invalid config {}: {}", path.display(), err))?
+        }
+        _ => RepoConfig::default(),
+    };
+
+    repo_config.apply_git_config(&git.config_get_regexp(r"^gitopen\.")?)?;
+    warn_unknown_keys(&repo_config.unknown_keys());
+    Ok(repo_config)
+}
+
+/// Warns about each key once, though the config is read for every setting.
+fn warn_unknown_keys(keys: &[String]) {
+    static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+    let mut warned = WARNED.lock().unwrap_or_else(|err| err.into_inner());
+    for key in keys {
+        if warned.insert(key.clone()) {
+            warning!(
+                "Ignoring the config key '{}', which this version of gitopen doesn't know",
+                key
+            );
+        }
+    }
+}
+
+/// Asks which remote to open on the terminal, suggesting `origin`, and
+/// remembers the answer in the repository's git config for the branches
+/// that don't have a remote.
+fn pick_remote(git: &dyn GitBackend, remote_names: &[String]) -> AnyhowResult<String> {
+    let default = remote_names
+        .iter()
+        .position(|name| name == "origin")
+        .unwrap_or(0);
+    eprintln!("This repository has several remotes:");
+    for (index, name) in remote_names.iter().enumerate() {
+        let url = get_parsed_url(git, name.to_string()).unwrap_or_default();
+        eprintln!("  {}) {}\t{}", index + 1, name, url);
+    }
+    eprint!("Which one should gitopen open? [{}] ", default + 1);
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let index = match answer.trim() {
+        "" => default,
+        answer => match answer.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= remote_names.len() => number - 1,
+            _ => return Err(anyhow!("'{}' is not one of the numbers offered", answer)),
+        },
+    };
+
+    let remote_name = remote_names[index].clone();
+    git.config_set("gitopen.pickedRemote", &remote_name)?;
+    note!(
+        "Saved, '{}' is opened on branches without a remote until `git config gitopen.pickedRemote <remote>` changes it",
+        remote_name
+    );
+    Ok(remote_name)
+}
+
+/// Picks the remote to open: the one asked for, the one configured for the
+/// repository, the current branch's remote, the one picked before, and
+/// otherwise asks on the terminal, or takes `origin` and finally whatever
+/// remote there is.
+pub(crate) fn get_remote_name(
+    git: &dyn GitBackend,
+    remote: RemoteSelection,
+) -> AnyhowResult<String> {
+    let remote_names = git.remotes()?;
+    if let Some(remote_name) = remote.name {
+        if !remote_names.iter().any(|name| name == remote_name) {
+            return Err(anyhow!(
+                "No remote named '{}', the remotes are: {}",
+                remote_name,
+                remote_names.join(", ")
+            ));
+        }
+        return Ok(remote_name.to_string());
+    }
+    let repo_config = get_repo_config(git)?;
+    if let Some(remote_name) = repo_config.remote {
+        return Ok(remote_name);
+    }
+    if let Ok(local_branch_name) = get_local_branch_name(git) {
+        let remote_name = get_remote_branch_name(git, local_branch_name)?;
+        if !remote_name.is_empty() {
+            return Ok(remote_name);
+        }
+    }
+    if let Some(remote_name) = repo_config
+        .picked_remote
+        .filter(|remote_name| remote_names.contains(remote_name))
+    {
+        return Ok(remote_name);
+    }
+    if let Some(remote_name) = git
+        .user_config()?
+        .remote
+        .filter(|remote_name| remote_names.contains(remote_name))
+    {
+        return Ok(remote_name);
+    }
+    if remote_names.len() > 1
+        && remote.interactive
+        && io::stdin().is_terminal()
+        && io::stderr().is_terminal()
+    {
+        return pick_remote(git, &remote_names);
+    }
+    if remote_names.iter().any(|name| name == "origin") {
+        return Ok("origin".to_string());
+    }
+    if remote_names.is_empty() && git.toplevel()?.is_none() && git.git_dir().is_none() {
+        return Err(GitOpenError::NotARepo.into());
+    }
+    remote_names
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("This repository has no remotes"))
+}
+
+fn get_repo_url(git: &dyn GitBackend, remote: RemoteSelection) -> AnyhowResult<String> {
+    get_parsed_url(git, get_remote_name(git, remote)?)
+}
+
+pub(crate) fn get_provider(
+    git: &dyn GitBackend,
+    parsed_url: &str,
+) -> AnyhowResult<Box<dyn Provider>> {
+    let repo_config = get_repo_config(git)?;
+    let provider = provider_for_config(
+        parsed_url,
+        &load_config(git, parsed_url, &repo_config)?,
+        &repo_config,
+    )?;
+    debug!(
+        provider = provider.name(),
+        "builds the links of {}", parsed_url
+    );
+    Ok(provider)
+}
+
+/// The config file, with the forge of a host gitopen doesn't recognize
+/// filled in when `probe = true` asks for it to be found out.
+pub(crate) fn load_config(
+    git: &dyn GitBackend,
+    parsed_url: &str,
+    repo_config: &RepoConfig,
+) -> AnyhowResult<Config> {
+    let mut config = git.user_config()?;
+    if repo_config.provider.is_none() {
+        probe_host(&mut config, parsed_url);
+    }
+    Ok(config)
+}
+
+/// Asks the host which forge it runs, once, and takes the answer like a
+/// `[providers]` entry of the config.
+#[cfg(feature = "api")]
+fn probe_host(config: &mut Config, parsed_url: &str) {
+    let host = crate::match_logic::get_host(parsed_url);
+    if !config.probe
+        || config.provider_for_host(host).is_some()
+        || crate::providers::detect_provider(parsed_url).is_some()
+    {
+        return;
+    }
+    let provider_name = match crate::cache::lookup_forge(host) {
+        Some(provider_name) => provider_name,
+        None => match crate::api::probe_forge(split_repo_url(parsed_url).0) {
+            Ok(provider_name) => {
+                crate::cache::store_forge(host, provider_name);
+                provider_name.map(str::to_string)
+            }
+            Err(err) => {
+                warning!("Could not ask {} which forge it runs: {}", host, err);
+                None
+            }
+        },
+    };
+    if let Some(provider_name) = provider_name {
+        config.providers.insert(host.to_string(), provider_name);
+    }
+}
+
+#[cfg(not(feature = "api"))]
+fn probe_host(_config: &mut Config, _parsed_url: &str) {}
+
+/// Whether the host is known to run the provider's forge, from its name or
+/// the config file, rather than getting GitHub links for want of a better
+/// guess. Only then may the forge's API be sent a token for the host; a
+/// checked-in `.gitopen.toml` naming the provider doesn't count.
+#[cfg(feature = "api")]
+fn forge_identified(
+    git: &dyn GitBackend,
+    parsed_url: &str,
+    provider: &dyn Provider,
+) -> AnyhowResult<bool> {
+    let host = crate::match_logic::get_host(parsed_url);
+    let config = load_config(git, parsed_url, &RepoConfig::default())?;
+    Ok(config.provider_for_host(host) == Some(provider.name())
+        || crate::providers::detect_provider(parsed_url)
+            .is_some_and(|detected| detected.name() == provider.name()))
+}
+
+/// Moves code links to Sourcegraph with `--sourcegraph`: the instance the
+/// config file names, or sourcegraph.com.
+fn on_sourcegraph(
+    git: &dyn GitBackend,
+    provider: Box<dyn Provider>,
+    options: LinkOptions,
+) -> AnyhowResult<Box<dyn Provider>> {
+    if !options.sourcegraph {
+        return Ok(provider);
+    }
+    let instance = git
+        .user_config()?
+        .sourcegraph
+        .unwrap_or_else(|| SOURCEGRAPH_URL.to_string());
+    Ok(Box::new(Sourcegraph::new(&instance, provider)))
+}
+
+/// Fails for pages Sourcegraph doesn't have, rather than open them on the
+/// git host as if `--sourcegraph` wasn't given.
+fn refuse_sourcegraph(options: LinkOptions, pages: &str) -> AnyhowResult<()> {
+    if options.sourcegraph {
+        return Err(anyhow!(
+            "Sourcegraph has no {}, leave out --sourcegraph",
+            pages
+        ));
+    }
+    Ok(())
+}
+
+/// The browser command configured with `git config gitopen.browser`, or
+/// else in the config file.
+pub fn get_browser(git: &dyn GitBackend) -> AnyhowResult<Option<String>> {
+    match get_repo_config(git)?.browser {
+        Some(browser) => Ok(Some(browser)),
+        None => Ok(git.user_config()?.browser),
+    }
+}
+
+/// How links are printed without `--format`, from the repository's git
+/// config or else the config file.
+pub fn get_format(git: &dyn GitBackend) -> AnyhowResult<Option<String>> {
+    match get_repo_config(git)?.format {
+        Some(format) => Ok(Some(format)),
+        None => Ok(git.user_config()?.format),
+    }
+}
+
+/// What bare `gitopen` opens, from the repository's config or else the
+/// config file.
+pub fn get_default_action(git: &dyn GitBackend) -> AnyhowResult<DefaultAction> {
+    match get_repo_config(git)?.default_action {
+        Some(action) => DefaultAction::from_name(&action),
+        None => git
+            .user_config()?
+            .default_action
+            .map_or(Ok(DefaultAction::Repo), |action| {
+                DefaultAction::from_name(&action)
+            }),
+    }
+}
+
+/// What files given without a line open, from the repository's config or
+/// else the config file.
+pub fn get_file_action(git: &dyn GitBackend) -> AnyhowResult<FileAction> {
+    match get_repo_config(git)?.file_action {
+        Some(action) => FileAction::from_name(&action),
+        None => git
+            .user_config()?
+            .file_action
+            .map_or(Ok(FileAction::Blob), |action| {
+                FileAction::from_name(&action)
+            }),
+    }
+}
+
+/// Opens what bare `gitopen` is set to open, the repository's home page
+/// unless `default-action` says otherwise.
+pub fn open_default(
+    git: &dyn GitBackend,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let action = get_default_action(git)?;
+    if action != DefaultAction::Repo && options.push {
+        push_current_branch(git, remote)?;
+    }
+    match action {
+        DefaultAction::Repo => open_repo(git, options, remote, output),
+        DefaultAction::Branch => open_branch(git, None, false, options, remote, output),
+        DefaultAction::Pr => open_branch_pr(git, remote, output),
+        DefaultAction::Commit => open_commit(git, "HEAD", options, remote, output),
+    }
+}
+
+pub fn open_repo(
+    git: &dyn GitBackend,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let remote_name = get_remote_name(git, remote)?;
+    if options.push {
+        push_current_branch(git, remote)?;
+    }
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = on_sourcegraph(git, get_provider(git, &parsed_url)?, options)?;
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &provider.repo_url(&parsed_url),
+        split_repo_url(&parsed_url).1,
+    ))?;
     Ok(())
 }
 
-pub fn open_commit(commit_sha: &str) -> AnyhowResult<()> {
-    let local_branch_name = get_local_branch_name()?;
-    let remote_branch_name = get_remote_branch_name(local_branch_name)?;
-    let parsed_url = get_parsed_url(remote_branch_name)?;
-    let commit_link = get_commit_link(parsed_url, commit_sha);
+/// How many of the latest commits `gitopen commit` offers to pick from.
+const PICKED_COMMITS: usize = 500;
 
-    webbrowser::open(&commit_link)?;
+/// Asks on the terminal which of the latest commits to open, for `gitopen
+/// commit` without a revision.
+pub fn pick_commit(git: &dyn GitBackend, interactive: bool) -> AnyhowResult<String> {
+    if !(interactive && io::stdin().is_terminal() && io::stderr().is_terminal()) {
+        return Err(anyhow!(
+            "Must supply a commit when gitopen can't ask on the terminal"
+        ));
+    }
+    let commits = git.recent_commits(PICKED_COMMITS)?;
+    if commits.is_empty() {
+        return Err(anyhow!("The current branch has no commits"));
+    }
+    let picked = pick("Which commit should gitopen open?", &commits, None)?
+        .ok_or_else(|| anyhow!("No commit picked"))?;
+    Ok(picked
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// Asks on the terminal which of the tracked files below the current
+/// directory to open, and at which lines, for `gitopen -i`. Returns the
+/// `<path>[:<line>]` to open.
+pub fn pick_file(git: &dyn GitBackend) -> AnyhowResult<String> {
+    if !(io::stdin().is_terminal() && io::stderr().is_terminal()) {
+        return Err(anyhow!("gitopen -i needs a terminal to ask on"));
+    }
+    let files = git.tracked_files()?;
+    if files.is_empty() {
+        return Err(anyhow!("There are no tracked files here"));
+    }
+    let path = pick(
+        "Which file should gitopen open?",
+        &files,
+        Some("cat -n -- {}"),
+    )?
+    .ok_or_else(|| anyhow!("No file picked"))?;
+    loop {
+        let lines = ask("At which line or lines (e.g. 10 or 10-20)? [the whole file]")?;
+        if lines.is_empty() {
+            return Ok(path.to_string());
+        }
+        let mut numbers = lines.splitn(2, '-');
+        if numbers.all(is_number) {
+            return Ok(format!("{}:{}", path, lines));
+        }
+        warning!("'{}' is not a line number or range", lines);
+    }
+}
+
+/// Opens a commit, or the compare view between two for a `<base>..<head>`
+/// range.
+pub fn open_commit(
+    git: &dyn GitBackend,
+    rev: &str,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    // Ref names can't have "..", so it is always a range.
+    if rev.contains("..") {
+        return open_commit_range(git, rev, options, remote, output);
+    }
+    // The full SHA, which providers find where a short one may be a 404.
+    let commit_sha = resolve_commit_sha(git, rev)?;
+    let remote_name = get_remote_name(git, remote)?;
+    check_remote_mismatches(
+        unpushed_commit(git, &commit_sha, &remote_name)?
+            .into_iter()
+            .collect(),
+        options.strict,
+    )?;
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = on_sourcegraph(git, get_provider(git, &parsed_url)?, options)?;
+    let commit_link = get_commit_link(provider.as_ref(), &parsed_url, &commit_sha);
+
+    output.deliver_link(&Link {
+        commit: Some(commit_sha.clone()),
+        ..repo_link(
+            provider.as_ref(),
+            &parsed_url,
+            &commit_link,
+            &commit_sha[..commit_sha.len().min(7)],
+        )
+    })?;
     Ok(())
 }
 
-pub fn open_at_line_number(input: &str) -> AnyhowResult<()> {
+/// Opens the commit that last changed the line of `<path>:<line>`, or the
+/// first line of a range.
+pub fn open_last_change(
+    git: &dyn GitBackend,
+    input: &str,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
     let file_at_line = parse_path_and_line_arg(input, ':')?;
-    let local_branch_name = get_local_branch_name()?;
-    let remote_branch_name = get_remote_branch_name(local_branch_name)?;
-    let parsed_url = get_parsed_url(remote_branch_name)?;
-    let line_number_link =
-        get_line_number_link(&parsed_url, file_at_line.filepath, file_at_line.line_number)?;
+    let line_number = file_at_line
+        .line_number
+        .ok_or_else(|| anyhow!("Must supply a line, as in '{}:<line-number>'", input))?;
+    let line: u32 = line_number
+        .parse()
+        .map_err(|_| anyhow!("'{}' is not a line number", line_number))?;
+    let commit_sha = git
+        .blame_line(file_at_line.filepath, line)?
+        .ok_or_else(|| {
+            anyhow!(
+                "git can't blame line {} of '{}'",
+                line,
+                file_at_line.filepath
+            )
+        })?;
+    if commit_sha.chars().all(|c| c == '0') {
+        return Err(anyhow!(
+            "Line {} of '{}' has changes that aren't committed yet",
+            line,
+            file_at_line.filepath
+        ));
+    }
+    open_commit(git, &commit_sha, options, remote, output)
+}
+
+/// Opens the Gerrit change of a commit, found by the `Change-Id` trailer of
+/// its message.
+pub fn open_change(
+    git: &dyn GitBackend,
+    rev: &str,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let message = git
+        .commit_message(rev)?
+        .ok_or_else(|| anyhow!("'{}' is not a commit", rev))?;
+    let change_id = find_change_id(&message)
+        .ok_or_else(|| anyhow!("The message of '{}' has no Change-Id trailer", rev))?;
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let change_link = provider.change_url(&parsed_url, change_id)?;
 
-    webbrowser::open(&line_number_link)?;
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &change_link,
+        change_id,
+    ))?;
     Ok(())
 }
 
-pub fn push_and_open_pr() -> AnyhowResult<()> {
-    let current_branch = Command::new("git")
-        .args(["branch", "--show-current"])
-        .stdout(Stdio::piped())
-        .output()?;
-    let current_branch_text = &String::from_utf8(current_branch.stdout)?;
-    let current_branch_text_stripped = current_branch_text.trim();
-    let output_from_push = Command::new("git")
-        .args(["push", "origin", current_branch_text_stripped])
-        .stderr(Stdio::piped())
-        .output()?;
-    let pr_re = Regex::new(r"remote:.*(https\S*)\s*\n")?;
-    let output_from_push_text = String::from_utf8(output_from_push.stderr)?;
-    let captured = pr_re
-        .captures(&output_from_push_text)
-        .ok_or_else(|| anyhow!("Error capturing PR url"))?;
-    webbrowser::open(&captured[1])?;
+/// Opens the pull request that introduced a commit, which the forge's API
+/// finds when it can be asked. Otherwise opens a search for the pull
+/// requests containing it or, where there is none, the commit page, which
+/// names them.
+pub fn open_pr_for_commit(
+    git: &dyn GitBackend,
+    rev: &str,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let commit_sha = resolve_commit_sha(git, rev)?;
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let label = format!("pull request of {}", &commit_sha[..commit_sha.len().min(7)]);
+    let link = |url: &str| Link {
+        commit: Some(commit_sha.clone()),
+        ..repo_link(provider.as_ref(), &parsed_url, url, &label)
+    };
+    #[cfg(feature = "api")]
+    match crate::api::find_commit_pr(
+        provider.name(),
+        &parsed_url,
+        forge_identified(git, &parsed_url, provider.as_ref())?,
+        &commit_sha,
+    ) {
+        Ok(Some(pr_link)) => return output.deliver_link(&link(&pr_link)),
+        Ok(None) => {}
+        Err(err) => warning!(
+            "Could not look up the pull request, searching instead: {}",
+            err
+        ),
+    }
+    let prs_link = provider
+        .commit_prs_url(&parsed_url, &commit_sha)
+        .unwrap_or_else(|_| get_commit_link(provider.as_ref(), &parsed_url, &commit_sha));
+
+    output.deliver_link(&link(&prs_link))?;
+    Ok(())
+}
+
+/// Describes a page of the repository for `--format`.
+fn repo_link(provider: &dyn Provider, parsed_url: &str, url: &str, label: &str) -> Link {
+    Link {
+        provider: Some(provider.name().to_string()),
+        repo: Some(split_repo_url(parsed_url).1.to_string()),
+        ..Link::new(url, label)
+    }
+}
+
+/// Describes a link to a file, its lines or a directory on a ref.
+fn file_link(
+    provider: &dyn Provider,
+    parsed_url: &str,
+    git_ref: &GitRef,
+    file_at_line: &FileAtLine,
+    url: &str,
+    label: &str,
+) -> Link {
+    let (branch, commit) = match git_ref {
+        GitRef::Branch(branch) => (Some(branch.to_string()), None),
+        GitRef::Commit(sha) => (None, Some(sha.to_string())),
+    };
+    Link {
+        branch,
+        commit,
+        path: Some(file_at_line.filepath.to_string()),
+        line: file_at_line.line_number.and_then(|line| line.parse().ok()),
+        end_line: file_at_line
+            .end_line_number
+            .and_then(|line| line.parse().ok()),
+        ..repo_link(provider, parsed_url, url, label)
+    }
+}
+
+/// The page of a file at its lines, or its raw content with `--raw`.
+fn file_url(
+    provider: &dyn Provider,
+    parsed_url: &str,
+    git_ref: &GitRef,
+    file_at_line: &FileAtLine,
+    options: LinkOptions,
+) -> AnyhowResult<String> {
+    if options.raw {
+        return get_raw_link(provider, parsed_url, git_ref, file_at_line);
+    }
+    Ok(get_line_number_link(
+        provider,
+        parsed_url,
+        git_ref,
+        file_at_line,
+    ))
+}
+
+/// Names a tree link by its directory, or the repository for its root.
+fn tree_label<'a>(parsed_url: &'a str, repo_path: &'a str) -> &'a str {
+    match repo_path {
+        "" => split_repo_url(parsed_url).1,
+        _ => repo_path,
+    }
+}
+
+/// Opens each `<path>:<line>`, or the tree view when the input is a
+/// directory. The remote and branch are only looked up once for all of them.
+pub fn open_at_line_number(
+    git: &dyn GitBackend,
+    inputs: &[&str],
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let remote_name = get_remote_name(git, remote)?;
+    if options.push {
+        push_current_branch(git, remote)?;
+    }
+    let link_target = get_link_target(git, &remote_name, options)?;
+    let parsed_url = get_parsed_url(git, remote_name.clone())?;
+    let provider = on_sourcegraph(git, get_provider(git, &parsed_url)?, options)?;
+    let cwd = env::current_dir()?;
+    let toplevel = get_toplevel(git)?;
+    let submodules = git.submodules()?;
+    // Built before any is delivered, so that an input that has no link
+    // fails the batch rather than stop it halfway.
+    let mut links = Vec::new();
+    for input in inputs {
+        let is_dir = Path::new(input).is_dir();
+        let file_at_line = if is_dir {
+            FileAtLine::new(input, None, None)
+        } else {
+            parse_path_and_line_arg(input, ':')?
+        };
+        let repo_path = resolve_repo_path(file_at_line.filepath, &cwd, &toplevel);
+        // Files in a submodule are linked in its own repository.
+        let (submodule_repo, path) = match find_submodule(&submodules, &repo_path) {
+            Some((submodule, path)) => (
+                Some(SubmoduleRepo::new(git, submodule, &remote_name, options)?),
+                path,
+            ),
+            None => (None, repo_path.as_str()),
+        };
+        let (provider, parsed_url, git_ref) = match &submodule_repo {
+            Some(repo) => (
+                repo.provider.as_ref(),
+                &repo.parsed_url,
+                GitRef::Commit(&repo.commit_sha),
+            ),
+            None => (provider.as_ref(), &parsed_url, link_target.as_git_ref()),
+        };
+        let file_at_line = FileAtLine {
+            filepath: path,
+            ..file_at_line
+        };
+        if is_dir {
+            if options.raw {
+                return Err(anyhow!(
+                    "'{}' is a directory, which has no raw content",
+                    input
+                ));
+            }
+            let tree_link = get_tree_link(provider, parsed_url, &git_ref, path);
+            links.push(file_link(
+                provider,
+                parsed_url,
+                &git_ref,
+                &file_at_line,
+                &tree_link,
+                tree_label(parsed_url, path),
+            ));
+            continue;
+        }
+        if submodule_repo.is_none() {
+            check_remote_mismatches(
+                remote_mismatches(git, path, &remote_name, &link_target)?,
+                options.strict,
+            )?;
+        }
+        let is_bare_file = !options.raw && file_at_line.line_number.is_none();
+        let (link, label) = match options.file_action {
+            FileAction::Blame if is_bare_file => (
+                get_blame_link(provider, parsed_url, &git_ref, &file_at_line)?,
+                format!("blame of {}", file_at_line),
+            ),
+            FileAction::History if is_bare_file => (
+                get_history_link(provider, parsed_url, &git_ref, path)?,
+                format!("history of {}", path),
+            ),
+            _ => (
+                file_url(provider, parsed_url, &git_ref, &file_at_line, options)?,
+                file_at_line.to_string(),
+            ),
+        };
+        links.push(file_link(
+            provider,
+            parsed_url,
+            &git_ref,
+            &file_at_line,
+            &link,
+            &label,
+        ));
+    }
+    for link in &links {
+        output.deliver_link(link)?;
+    }
+    Ok(())
+}
+
+/// Opens the repository of the submodule at `path`, at the commit the
+/// superproject records for it.
+pub fn open_submodule(
+    git: &dyn GitBackend,
+    path: &str,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let remote_name = get_remote_name(git, remote)?;
+    let repo_path = to_repo_path(git, path)?;
+    let submodules = git.submodules()?;
+    let submodule = match find_submodule(&submodules, &repo_path) {
+        Some((submodule, "")) => submodule,
+        _ => return Err(anyhow!("'{}' is not a submodule", path)),
+    };
+    let repo = SubmoduleRepo::new(git, submodule, &remote_name, LinkOptions::default())?;
+    let git_ref = GitRef::Commit(&repo.commit_sha);
+    let tree_link = get_tree_link(repo.provider.as_ref(), &repo.parsed_url, &git_ref, "");
+
+    output.deliver_link(&file_link(
+        repo.provider.as_ref(),
+        &repo.parsed_url,
+        &git_ref,
+        &FileAtLine::new("", None, None),
+        &tree_link,
+        tree_label(&repo.parsed_url, ""),
+    ))?;
+    Ok(())
+}
+
+/// Opens the tree of `branch`, or of the current branch. With
+/// `pick_on_terminal`, asks on the terminal which of the local and remote
+/// branches to open instead.
+pub fn open_branch(
+    git: &dyn GitBackend,
+    branch: Option<&str>,
+    pick_on_terminal: bool,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let remote_name = get_remote_name(git, remote)?;
+    let branch = match branch {
+        Some(branch) => branch.to_string(),
+        None if pick_on_terminal => pick_branch(git, &remote_name)?,
+        None => get_local_branch_name(git)?,
+    };
+    if !is_on_remote(git, &branch, &remote_name)? {
+        warning!(
+            "'{}' is not a branch on '{}', the page may not exist",
+            branch,
+            remote_name
+        );
+    }
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = on_sourcegraph(git, get_provider(git, &parsed_url)?, options)?;
+    let git_ref = GitRef::Branch(&branch);
+    let tree_link = get_tree_link(provider.as_ref(), &parsed_url, &git_ref, "");
+
+    output.deliver_link(&file_link(
+        provider.as_ref(),
+        &parsed_url,
+        &git_ref,
+        &FileAtLine::new("", None, None),
+        &tree_link,
+        &format!("{} on {}", branch, split_repo_url(&parsed_url).1),
+    ))?;
+    Ok(())
+}
+
+fn pick_branch(git: &dyn GitBackend, remote_name: &str) -> AnyhowResult<String> {
+    if !(io::stdin().is_terminal() && io::stderr().is_terminal()) {
+        return Err(anyhow!("gitopen branch -i needs a terminal to ask on"));
+    }
+    let branches = branch_choices(&git.branches()?, remote_name);
+    if branches.is_empty() {
+        return Err(anyhow!("This repository has no branches"));
+    }
+    let picked = pick("Which branch should gitopen open?", &branches, None)?
+        .ok_or_else(|| anyhow!("No branch picked"))?;
+    Ok(picked.to_string())
+}
+
+pub fn open_pr(
+    git: &dyn GitBackend,
+    pr_number: &str,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    if !is_number(pr_number) {
+        return Err(anyhow!("'{}' is not a pull request number", pr_number));
+    }
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let pr_link = provider.pr_url(&parsed_url, pr_number)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &pr_link,
+        &format!("#{}", pr_number),
+    ))?;
+    Ok(())
+}
+
+/// Opens the open pull request from the current branch, which the forge's
+/// API finds when it can be asked, or else the list of them.
+pub fn open_branch_pr(
+    git: &dyn GitBackend,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let current_branch = get_local_branch_name(git)?;
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    #[cfg(feature = "api")]
+    match crate::api::find_branch_pr(
+        provider.name(),
+        &parsed_url,
+        forge_identified(git, &parsed_url, provider.as_ref())?,
+        &current_branch,
+    ) {
+        Ok(Some(pr_link)) => {
+            return output.deliver_link(&Link {
+                branch: Some(current_branch.clone()),
+                ..repo_link(provider.as_ref(), &parsed_url, &pr_link, &current_branch)
+            });
+        }
+        Ok(None) => {}
+        Err(err) => warning!(
+            "Could not look up the pull request, searching instead: {}",
+            err
+        ),
+    }
+    let prs_link = provider.branch_prs_url(&parsed_url, &current_branch)?;
+
+    output.deliver_link(&Link {
+        branch: Some(current_branch.clone()),
+        ..repo_link(provider.as_ref(), &parsed_url, &prs_link, &current_branch)
+    })?;
+    Ok(())
+}
+
+/// The namespace of the fork the branch is pushed to when that's not the
+/// remote the pull request is for, as in a triangular workflow with
+/// `origin` a fork of `upstream`.
+fn get_fork_owner(
+    git: &dyn GitBackend,
+    branch: &str,
+    remote_name: &str,
+) -> AnyhowResult<Option<String>> {
+    let mut head_remote = match get_configured_push_remote(git, branch)? {
+        Some(push_remote) => push_remote,
+        None => get_remote_branch_name(git, branch.to_string())?,
+    };
+    if head_remote.is_empty() {
+        head_remote = "origin".to_string();
+    }
+    if head_remote == remote_name || git.remote_url(&head_remote)?.is_none() {
+        return Ok(None);
+    }
+    let fork_url = get_parsed_url(git, head_remote)?;
+    let fork_path = split_repo_url(&fork_url).1;
+    Ok(fork_path
+        .rsplit_once('/')
+        .map(|(owner, _)| owner.to_string()))
+}
+
+/// Opens the page that creates a pull request from the current branch.
+pub fn open_new_pr(
+    git: &dyn GitBackend,
+    options: NewPrOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let current_branch = get_local_branch_name(git)?;
+    let remote_name = get_remote_name(git, remote)?;
+    let base = match options.base {
+        Some(base) => {
+            verify_ref(git, base, &remote_name)?;
+            base.to_string()
+        }
+        None => get_default_branch(git, &remote_name)?,
+    };
+    let message = if options.fill {
+        git.commit_message("HEAD")?.unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let (subject, message_body) = split_commit_message(&message);
+    let head_owner = get_fork_owner(git, &current_branch, &remote_name)?;
+    // A branch pushed to a fork is on a remote this one can't tell about.
+    if head_owner.is_none() && !is_on_remote(git, &current_branch, &remote_name)? {
+        return Err(GitOpenError::NoUpstream {
+            branch: current_branch,
+            remote: remote_name,
+        }
+        .into());
+    }
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let new_pr = NewPullRequest {
+        base: &base,
+        head: &current_branch,
+        head_owner: head_owner.as_deref(),
+        title: options
+            .title
+            .or(Some(subject).filter(|subject| !subject.is_empty())),
+        body: options.body.or(message_body),
+        draft: options.draft,
+    };
+    let new_pr_link = provider.new_pr_url(&parsed_url, &new_pr)?;
+
+    output.deliver_link(&Link {
+        branch: Some(current_branch.clone()),
+        ..repo_link(
+            provider.as_ref(),
+            &parsed_url,
+            &new_pr_link,
+            new_pr.title.unwrap_or(&current_branch),
+        )
+    })?;
+    Ok(())
+}
+
+pub fn open_issues(
+    git: &dyn GitBackend,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let issues_link = provider.issues_url(&parsed_url)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &issues_link,
+        "issues",
+    ))?;
+    Ok(())
+}
+
+pub fn open_issue(
+    git: &dyn GitBackend,
+    issue_number: &str,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    if !is_number(issue_number) {
+        return Err(anyhow!("'{}' is not an issue number", issue_number));
+    }
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let issue_link = provider.issue_url(&parsed_url, issue_number)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &issue_link,
+        &format!("#{}", issue_number),
+    ))?;
+    Ok(())
+}
+
+pub fn open_new_issue(
+    git: &dyn GitBackend,
+    title: Option<&str>,
+    body: Option<&str>,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let new_issue_link = provider.new_issue_url(&parsed_url, title, body)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &new_issue_link,
+        title.unwrap_or("new issue"),
+    ))?;
+    Ok(())
+}
+
+/// Opens the CI results of HEAD. With `failed`, opens the branch's latest
+/// failed run instead, when the forge's API can tell which it is.
+pub fn open_ci(
+    git: &dyn GitBackend,
+    failed: bool,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let commit_sha = get_head_sha(git)?;
+    // A detached HEAD has no branch whose pipelines could be listed.
+    let local_branch_name = get_local_branch_name(git).unwrap_or_else(|_| commit_sha.clone());
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    #[cfg(feature = "api")]
+    if failed {
+        match crate::api::find_failed_run(
+            provider.name(),
+            &parsed_url,
+            forge_identified(git, &parsed_url, provider.as_ref())?,
+            &local_branch_name,
+        ) {
+            Ok(Some(run_link)) => {
+                return output.deliver_link(&Link {
+                    branch: Some(local_branch_name.clone()),
+                    ..repo_link(
+                        provider.as_ref(),
+                        &parsed_url,
+                        &run_link,
+                        &format!("failed CI of {}", local_branch_name),
+                    )
+                });
+            }
+            Ok(None) => {}
+            Err(err) => warning!("Could not look up the failed run: {}", err),
+        }
+    }
+    #[cfg(not(feature = "api"))]
+    if failed {
+        note!("Finding the failed run needs gitopen built with the api feature");
+    }
+    let ci_link = provider.ci_url(&parsed_url, &local_branch_name, &commit_sha)?;
+
+    output.deliver_link(&Link {
+        branch: Some(local_branch_name.clone()),
+        commit: Some(commit_sha.clone()),
+        ..repo_link(
+            provider.as_ref(),
+            &parsed_url,
+            &ci_link,
+            &format!("CI of {}", local_branch_name),
+        )
+    })?;
+    Ok(())
+}
+
+pub fn open_releases(
+    git: &dyn GitBackend,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let releases_link = provider.releases_url(&parsed_url)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &releases_link,
+        "releases",
+    ))?;
+    Ok(())
+}
+
+/// The full name of the ref `rev` names, `refs/tags/...` for tags and
+/// `refs/heads/...` for local and remote branches, or else its commit.
+fn full_ref_name(git: &dyn GitBackend, rev: &str, remote_name: &str) -> AnyhowResult<String> {
+    if rev.starts_with("refs/") {
+        return Ok(rev.to_string());
+    }
+    let tag = format!("refs/tags/{}", rev);
+    if git.rev_parse(&tag)?.is_some() {
+        return Ok(tag);
+    }
+    let branch = format!("refs/heads/{}", rev);
+    let remote_branch = format!("refs/remotes/{}/{}", remote_name, rev);
+    // refs/remotes/<remote>/HEAD isn't a branch of the remote.
+    if rev != "HEAD"
+        && (git.rev_parse(&branch)?.is_some() || git.rev_parse(&remote_branch)?.is_some())
+    {
+        return Ok(branch);
+    }
+    resolve_commit_sha(git, rev)
+}
+
+/// Opens the download of the repository's files at `rev`, by default on the
+/// branch file links point at.
+pub fn open_archive(
+    git: &dyn GitBackend,
+    rev: Option<&str>,
+    format: ArchiveFormat,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    refuse_sourcegraph(options, "archives")?;
+    let remote_name = get_remote_name(git, remote)?;
+    let git_ref = match rev {
+        Some(rev) => full_ref_name(git, rev, &remote_name)?,
+        None => match get_link_target(git, &remote_name, options)? {
+            LinkTarget::Branch(branch) => format!("refs/heads/{}", branch),
+            LinkTarget::Commit(sha) => sha,
+        },
+    };
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let archive_link = provider.archive_url(&parsed_url, &git_ref, format)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &archive_link,
+        &format!("{} archive of {}", format.name(), git_ref),
+    ))?;
+    Ok(())
+}
+
+/// The first release with the commit: the oldest of `release_tags` (latest
+/// first, as the API lists them) among the tags that contain it, or the
+/// oldest such tag when the releases aren't known.
+fn first_release_containing<'a>(
+    release_tags: Option<&[String]>,
+    tags: &'a [String],
+) -> Option<&'a String> {
+    match release_tags {
+        Some(release_tags) => release_tags
+            .iter()
+            .rev()
+            .find_map(|release| tags.iter().find(|tag| *tag == release)),
+        None => tags.first(),
+    }
+}
+
+/// Opens the release of `tag`, the first release with the commit
+/// `containing` names, or else the latest release.
+pub fn open_release(
+    git: &dyn GitBackend,
+    tag: Option<&str>,
+    containing: Option<&str>,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let found_tag = match containing {
+        Some(rev) => {
+            let commit_sha = resolve_commit_sha(git, rev)?;
+            let tags = git.tags_containing(&commit_sha)?;
+            #[cfg(feature = "api")]
+            let release_tags = crate::api::find_release_tags(
+                provider.name(),
+                &parsed_url,
+                forge_identified(git, &parsed_url, provider.as_ref())?,
+            )
+            .unwrap_or_else(|err| {
+                warning!("Could not list the releases, taking the tags: {}", err);
+                None
+            });
+            #[cfg(not(feature = "api"))]
+            let release_tags: Option<Vec<String>> = None;
+            let found = first_release_containing(release_tags.as_deref(), &tags)
+                .ok_or_else(|| anyhow!("No release contains '{}' yet", rev))?;
+            Some(found.clone())
+        }
+        None => tag.map(str::to_string),
+    };
+    let release_link = provider.release_url(&parsed_url, found_tag.as_deref())?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &release_link,
+        found_tag.as_deref().unwrap_or("latest release"),
+    ))?;
+    Ok(())
+}
+
+/// Opens one of the project-wide pages, such as the discussions.
+pub fn open_project_page(
+    git: &dyn GitBackend,
+    page: ProjectPage,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let page_link = provider.project_page_url(&parsed_url, page)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &page_link,
+        page.name(),
+    ))?;
+    Ok(())
+}
+
+/// Opens the repository's settings, or the given section of them.
+pub fn open_settings(
+    git: &dyn GitBackend,
+    section: Option<SettingsSection>,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let settings_link = provider.settings_url(&parsed_url, section)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &settings_link,
+        section.map_or("settings", |section| section.name()),
+    ))?;
+    Ok(())
+}
+
+/// Opens the wiki's home, or the page with the given title.
+pub fn open_wiki(
+    git: &dyn GitBackend,
+    page: Option<&str>,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let wiki_link = provider.wiki_url(&parsed_url, page)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &wiki_link,
+        page.unwrap_or("wiki"),
+    ))?;
+    Ok(())
+}
+
+pub fn open_tags(
+    git: &dyn GitBackend,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let tags_link = provider.tags_url(&parsed_url)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &tags_link,
+        "tags",
+    ))?;
+    Ok(())
+}
+
+pub fn open_tag(
+    git: &dyn GitBackend,
+    tag: &str,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let parsed_url = get_repo_url(git, remote)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let tag_link = provider.tag_url(&parsed_url, tag)?;
+
+    output.deliver_link(&repo_link(provider.as_ref(), &parsed_url, &tag_link, tag))?;
+    Ok(())
+}
+
+pub fn open_blame(
+    git: &dyn GitBackend,
+    input: &str,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let file_at_line = parse_path_and_line_arg(input, ':')?;
+    let repo_path = to_repo_path(git, file_at_line.filepath)?;
+    let file_at_line = FileAtLine {
+        filepath: &repo_path,
+        ..file_at_line
+    };
+    let remote_name = get_remote_name(git, remote)?;
+    if options.push {
+        push_current_branch(git, remote)?;
+    }
+    let link_target = get_link_target(git, &remote_name, options)?;
+    check_remote_mismatches(
+        remote_mismatches(git, &repo_path, &remote_name, &link_target)?,
+        options.strict,
+    )?;
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = on_sourcegraph(git, get_provider(git, &parsed_url)?, options)?;
+    let blame_link = get_blame_link(
+        provider.as_ref(),
+        &parsed_url,
+        &link_target.as_git_ref(),
+        &file_at_line,
+    )?;
+
+    output.deliver_link(&file_link(
+        provider.as_ref(),
+        &parsed_url,
+        &link_target.as_git_ref(),
+        &file_at_line,
+        &blame_link,
+        &format!("blame of {}", file_at_line),
+    ))?;
+    Ok(())
+}
+
+/// Opens a file in a web editor: `editor`, the URL prefix the file's page
+/// goes after, or else the provider's own.
+pub fn open_edit(
+    git: &dyn GitBackend,
+    input: &str,
+    editor: Option<&str>,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    refuse_sourcegraph(options, "web editor")?;
+    let file_at_line = parse_path_and_line_arg(input, ':')?;
+    let repo_path = to_repo_path(git, file_at_line.filepath)?;
+    let file_at_line = FileAtLine {
+        filepath: &repo_path,
+        ..file_at_line
+    };
+    let remote_name = get_remote_name(git, remote)?;
+    if options.push {
+        push_current_branch(git, remote)?;
+    }
+    // Edits are committed to a branch, so this never pins the HEAD commit.
+    let branch_options = LinkOptions {
+        permalink: false,
+        ..options
+    };
+    let link_target = get_link_target(git, &remote_name, branch_options)?;
+    check_remote_mismatches(
+        remote_mismatches(git, &repo_path, &remote_name, &link_target)?,
+        options.strict,
+    )?;
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let git_ref = link_target.as_git_ref();
+    let edit_link = match editor {
+        Some(prefix) => format!(
+            "{}{}",
+            prefix,
+            get_line_number_link(provider.as_ref(), &parsed_url, &git_ref, &file_at_line)
+        ),
+        None => provider.edit_url(&parsed_url, &git_ref, &repo_path, file_at_line.line_number)?,
+    };
+
+    output.deliver_link(&file_link(
+        provider.as_ref(),
+        &parsed_url,
+        &git_ref,
+        &file_at_line,
+        &edit_link,
+        &format!("edit {}", file_at_line),
+    ))?;
+    Ok(())
+}
+
+pub fn open_history(
+    git: &dyn GitBackend,
+    path: &str,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let repo_path = to_repo_path(git, path)?;
+    let remote_name = get_remote_name(git, remote)?;
+    if options.push {
+        push_current_branch(git, remote)?;
+    }
+    let link_target = get_link_target(git, &remote_name, options)?;
+    check_remote_mismatches(
+        remote_mismatches(git, &repo_path, &remote_name, &link_target)?,
+        options.strict,
+    )?;
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = on_sourcegraph(git, get_provider(git, &parsed_url)?, options)?;
+    let history_link = get_history_link(
+        provider.as_ref(),
+        &parsed_url,
+        &link_target.as_git_ref(),
+        &repo_path,
+    )?;
+
+    output.deliver_link(&file_link(
+        provider.as_ref(),
+        &parsed_url,
+        &link_target.as_git_ref(),
+        &FileAtLine::new(&repo_path, None, None),
+        &history_link,
+        &format!("history of {}", repo_path),
+    ))?;
+    Ok(())
+}
+
+/// Opens the profile of `user` on the provider of the remote's host.
+pub fn open_user(
+    git: &dyn GitBackend,
+    user: &str,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let remote_name = get_remote_name(git, remote)?;
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let user_link = provider.user_url(&parsed_url, user)?;
+
+    output.deliver_link(&repo_link(provider.as_ref(), &parsed_url, &user_link, user))?;
+    Ok(())
+}
+
+/// Opens the page of the organization `org`, by default the one that owns
+/// the repository.
+pub fn open_org(
+    git: &dyn GitBackend,
+    org: Option<&str>,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let remote_name = get_remote_name(git, remote)?;
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let org = match org {
+        Some(org) => org,
+        None => repo_owner(&parsed_url)
+            .ok_or_else(|| anyhow!("'{}' has no owner to open", parsed_url))?,
+    };
+    let org_link = provider.org_url(&parsed_url, org)?;
+
+    output.deliver_link(&repo_link(provider.as_ref(), &parsed_url, &org_link, org))?;
+    Ok(())
+}
+
+/// Opens the provider's code search of the repository for `pattern`, on the
+/// current branch and below `path` when asked to.
+pub fn open_code_search(
+    git: &dyn GitBackend,
+    pattern: &str,
+    path: Option<&str>,
+    current_branch: bool,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let remote_name = get_remote_name(git, remote)?;
+    let branch = if current_branch {
+        Some(get_local_branch_name(git)?)
+    } else {
+        None
+    };
+    let repo_path = path.map(|path| to_repo_path(git, path)).transpose()?;
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let search = CodeSearch {
+        pattern,
+        branch: branch.as_deref(),
+        path: repo_path.as_deref().filter(|path| !path.is_empty()),
+    };
+    let search_link = provider.code_search_url(&parsed_url, &search)?;
+
+    output.deliver_link(&Link {
+        branch,
+        path: repo_path,
+        ..repo_link(
+            provider.as_ref(),
+            &parsed_url,
+            &search_link,
+            &format!("search for '{}'", pattern),
+        )
+    })?;
+    Ok(())
+}
+
+/// Opens the compare view between the commits of `<base>..<head>`. Unlike
+/// `open_compare`, which keeps branch names in the link, both ends are
+/// pinned to the commits they are now.
+fn open_commit_range(
+    git: &dyn GitBackend,
+    range: &str,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let (base, head) = parse_compare_range(range)?;
+    let base_sha = resolve_commit_sha(git, base)?;
+    let head_sha = resolve_commit_sha(git, head)?;
+    let remote_name = get_remote_name(git, remote)?;
+    let mut mismatches = Vec::new();
+    for commit_sha in [&base_sha, &head_sha] {
+        mismatches.extend(unpushed_commit(git, commit_sha, &remote_name)?);
+    }
+    check_remote_mismatches(mismatches, options.strict)?;
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = on_sourcegraph(git, get_provider(git, &parsed_url)?, options)?;
+    let compare_link = provider.compare_url(&parsed_url, &base_sha, &head_sha)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &compare_link,
+        &format!(
+            "{}...{}",
+            &base_sha[..base_sha.len().min(7)],
+            &head_sha[..head_sha.len().min(7)]
+        ),
+    ))?;
+    Ok(())
+}
+
+/// Opens the compare view for `<base>..<head>`, or, without a range, for the
+/// remote's default branch against the current branch.
+pub fn open_compare(
+    git: &dyn GitBackend,
+    range: Option<&str>,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let remote_name = get_remote_name(git, remote)?;
+    let (base, head) = match range {
+        Some(range) => {
+            let (base, head) = parse_compare_range(range)?;
+            (base.to_string(), head.to_string())
+        }
+        None => (
+            get_default_branch(git, &remote_name)?,
+            get_local_branch_name(git).or_else(|_| get_head_sha(git))?,
+        ),
+    };
+    verify_ref(git, &base, &remote_name)?;
+    verify_ref(git, &head, &remote_name)?;
+    let parsed_url = get_parsed_url(git, remote_name)?;
+    let provider = get_provider(git, &parsed_url)?;
+    let compare_link = provider.compare_url(&parsed_url, &base, &head)?;
+
+    output.deliver_link(&repo_link(
+        provider.as_ref(),
+        &parsed_url,
+        &compare_link,
+        &format!("{}...{}", base, head),
+    ))?;
+    Ok(())
+}
+
+/// Reads `<path>:<line>[:<content>]` lines, as printed by `grep -n` or `rg -n`,
+/// from stdin and delivers a link for each of them.
+pub fn open_from_stdin(
+    git: &dyn GitBackend,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let lines = io::stdin().lock().lines().map(|line| Ok(line?));
+    open_locations(git, lines, &env::current_dir()?, options, remote, output)
+}
+
+/// Reads the output of rustc or cargo from stdin and delivers a link for
+/// each location in the repository its errors and warnings point at, once.
+pub fn open_from_diagnostics(
+    git: &dyn GitBackend,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let toplevel = get_toplevel(git)?;
+    let mut seen = HashSet::new();
+    let locations = io::stdin()
+        .lock()
+        .lines()
+        .filter_map(|line| match line {
+            Ok(line) => diagnostic_location(&line).map(|location| Ok(location.to_string())),
+            Err(err) => Some(Err(err.into())),
+        })
+        .filter(|location| match location {
+            // Dependencies and the standard library are elsewhere.
+            Ok(location)
+                if Path::new(location).is_absolute()
+                    && !Path::new(location).starts_with(&toplevel) =>
+            {
+                debug!("skips {}, which is outside the repository", location);
+                false
+            }
+            Ok(location) => seen.insert(location.clone()),
+            Err(_) => true,
+        });
+    // Cargo gives the paths relative to the workspace, wherever it runs.
+    let dir = cargo_root(&env::current_dir()?, &toplevel);
+    open_locations(git, locations, &dir, options, remote, output)
+}
+
+/// The directory cargo reports paths relative to when run in `dir`: the
+/// root of its workspace, or else of its package. Outside of a package, and
+/// for workspaces that aren't in the repository, that is `toplevel`.
+fn cargo_root(dir: &Path, toplevel: &Path) -> PathBuf {
+    let mut package = None;
+    for ancestor in dir
+        .ancestors()
+        .take_while(|ancestor| ancestor.starts_with(toplevel))
+    {
+        let manifest = match fs::read_to_string(ancestor.join("Cargo.toml")) {
+            Ok(manifest) => manifest,
+            Err(_) => continue,
+        };
+        if manifest
+            .parse::<toml::Table>()
+            .is_ok_and(|manifest| manifest.contains_key("workspace"))
+        {
+            return ancestor.to_path_buf();
+        }
+        package.get_or_insert(ancestor);
+    }
+    package.unwrap_or(toplevel).to_path_buf()
+}
+
+/// Delivers a link for each `<path>:<line>` location, relative to `dir`
+/// unless it is absolute.
+fn open_locations(
+    git: &dyn GitBackend,
+    locations: impl Iterator<Item = AnyhowResult<String>>,
+    dir: &Path,
+    options: LinkOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let remote_name = get_remote_name(git, remote)?;
+    if options.push {
+        push_current_branch(git, remote)?;
+    }
+    let link_target = get_link_target(git, &remote_name, options)?;
+    let parsed_url = get_parsed_url(git, remote_name.clone())?;
+    let provider = on_sourcegraph(git, get_provider(git, &parsed_url)?, options)?;
+    let toplevel = get_toplevel(git)?;
+    for line in locations {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let file_at_line = parse_path_and_line_arg(&line, ':')?;
+        let repo_path = resolve_repo_path(file_at_line.filepath, dir, &toplevel);
+        let file_at_line = FileAtLine {
+            filepath: &repo_path,
+            ..file_at_line
+        };
+        check_remote_mismatches(
+            remote_mismatches(git, &repo_path, &remote_name, &link_target)?,
+            options.strict,
+        )?;
+        let line_number_link = file_url(
+            provider.as_ref(),
+            &parsed_url,
+            &link_target.as_git_ref(),
+            &file_at_line,
+            options,
+        )?;
+        output.deliver_link(&file_link(
+            provider.as_ref(),
+            &parsed_url,
+            &link_target.as_git_ref(),
+            &file_at_line,
+            &line_number_link,
+            &file_at_line.to_string(),
+        ))?;
+    }
+    Ok(())
+}
+
+pub fn push_and_open_pr(
+    git: &dyn GitBackend,
+    push_options: &PushOptions,
+    remote: RemoteSelection,
+    output: &Output,
+) -> AnyhowResult<()> {
+    let current_branch = get_local_branch_name(git)?;
+    let remote_name = get_push_remote_name(git, remote)?;
+    let output_from_push = push_branch(git, &remote_name, &current_branch, push_options)?;
+    if let Some(pr_url) = find_pr_url(&output_from_push.stderr) {
+        output.deliver(pr_url)?;
+        return Ok(());
+    }
+    // Remotes only print a link when the push sent something, not when it
+    // was "Everything up-to-date". Then the PR may well exist already.
+    let parsed_url = get_parsed_url(git, remote_name.clone())?;
+    let provider = get_provider(git, &parsed_url)?;
+    let pr_url = match provider.branch_prs_url(&parsed_url, &current_branch) {
+        Ok(pr_url) => pr_url,
+        Err(_) => provider.compare_url(
+            &parsed_url,
+            &get_default_branch(git, &remote_name)?,
+            &current_branch,
+        )?,
+    };
+    output.deliver_link(&Link {
+        branch: Some(current_branch.clone()),
+        ..repo_link(provider.as_ref(), &parsed_url, &pr_url, &current_branch)
+    })?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::git::{DryRun, FakeGit};
+    use regex::Regex;
+
+    const SHA: &str = "998a1b3c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a";
+
+    fn delivered(output: &Output) -> Vec<String> {
+        output.delivered.borrow().clone()
+    }
+
+    #[test]
+    fn test_open_repo() {
+        let git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        let output = Output::default();
+        open_repo(
+            &git,
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(delivered(&output), ["https://github.com/oren0e/gitopen"]);
+        assert!(git.pushes.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_open_repo_picks_configured_remote() {
+        let mut git = FakeGit::new("git@github.com:me/gitopen.git", "main", SHA);
+        git.remotes.push((
+            "upstream".to_string(),
+            "https://gitlab.com/oren0e/gitopen.git".to_string(),
+        ));
+        git.set_config("gitopen.remote", "upstream");
+        let output = Output::default();
+        open_repo(
+            &git,
+            LinkOptions {
+                push: true,
+                ..LinkOptions::default()
+            },
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(delivered(&output), ["https://gitlab.com/oren0e/gitopen"]);
+        assert_eq!(*git.pushes.borrow(), [["upstream", "main"]]);
+        let unknown = RemoteSelection {
+            name: Some("fork"),
+            interactive: false,
+        };
+        assert!(open_repo(&git, LinkOptions::default(), unknown, &output).is_err());
+    }
+
+    #[test]
+    fn test_picked_remote_keeps_to_branches_without_one() {
+        let mut git = FakeGit::new("git@github.com:me/gitopen.git", "main", SHA);
+        git.remotes.push((
+            "upstream".to_string(),
+            "https://gitlab.com/oren0e/gitopen.git".to_string(),
+        ));
+        git.set_config("gitopen.pickedremote", "upstream");
+        let remote = RemoteSelection::default();
+        assert_eq!(get_remote_name(&git, remote).unwrap(), "origin");
+        git.config.borrow_mut().remove("branch.main.remote");
+        assert_eq!(get_remote_name(&git, remote).unwrap(), "upstream");
+    }
+
+    #[test]
+    fn test_open_change() {
+        let mut git = FakeGit::new("ssh://me@gerrit.corp.com:29418/tools/build", "main", SHA);
+        let change_id = "I8473b95934b5732ac55d26311a706c9c2bde9940";
+        git.commit_messages.insert(
+            "HEAD".to_string(),
+            format!("Fix the build\n\nChange-Id: {}\n", change_id),
+        );
+        git.commit_messages
+            .insert("HEAD~1".to_string(), "Add a test\n".to_string());
+        let output = Output::default();
+        open_change(&git, "HEAD", RemoteSelection::default(), &output).unwrap();
+        assert!(open_change(&git, "HEAD~1", RemoteSelection::default(), &output).is_err());
+        open_repo(
+            &git,
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output),
+            [
+                format!("https://gerrit.corp.com/q/{}", change_id),
+                "https://gerrit.corp.com/plugins/gitiles/tools/build".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_open_commit() {
+        let git = FakeGit::new("https://gitlab.com/group/project.git", "main", SHA);
+        let output = Output::default();
+        open_commit(
+            &git,
+            "HEAD",
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output),
+            [format!("https://gitlab.com/group/project/-/commit/{}", SHA)]
+        );
+        assert!(open_commit(
+            &git,
+            "v9.9.9",
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_open_commit_expands_and_checks_remote() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.revs.insert("998a1b3".to_string(), SHA.to_string());
+        let unpushed = "2222222222222222222222222222222222222222";
+        git.revs.insert("2222222".to_string(), unpushed.to_string());
+        let output = Output::default();
+        let strict = LinkOptions {
+            strict: true,
+            ..LinkOptions::default()
+        };
+        open_commit(&git, "998a1b3", strict, RemoteSelection::default(), &output).unwrap();
+        assert_eq!(
+            delivered(&output),
+            [format!("https://github.com/oren0e/gitopen/commit/{}", SHA)]
+        );
+        assert_eq!(
+            open_commit(&git, "2222222", strict, RemoteSelection::default(), &output)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "{} is not pushed to 'origin' yet, the link won't work until it is",
+                unpushed
+            )
+        );
+        // Without --strict it is only a warning.
+        open_commit(
+            &git,
+            "2222222",
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(delivered(&output).len(), 2);
+    }
+
+    #[test]
+    fn test_open_commit_range() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        let base = "1111111111111111111111111111111111111111";
+        git.revs.insert("v1.0".to_string(), base.to_string());
+        let output = Output::default();
+        let open = |rev| {
+            open_commit(
+                &git,
+                rev,
+                LinkOptions::default(),
+                RemoteSelection::default(),
+                &output,
+            )
+        };
+        open("v1.0..HEAD").unwrap();
+        open("v1.0...HEAD").unwrap();
+        assert_eq!(
+            delivered(&output),
+            vec![
+                format!(
+                    "https://github.com/oren0e/gitopen/compare/{}...{}",
+                    base, SHA
+                );
+                2
+            ]
+        );
+        assert_eq!(
+            open("v1.0..nope").unwrap_err().to_string(),
+            "'nope' does not name a commit in this repository"
+        );
+        assert!(open("v1.0..").is_err());
+    }
+
+    #[test]
+    fn test_open_last_change() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.revs.insert(SHA.to_string(), SHA.to_string());
+        git.blame
+            .insert("src/lib.rs:88".to_string(), SHA.to_string());
+        git.blame
+            .insert("src/lib.rs:90".to_string(), "0".repeat(40));
+        let output = Output::default();
+        let open = |input| {
+            open_last_change(
+                &git,
+                input,
+                LinkOptions::default(),
+                RemoteSelection::default(),
+                &output,
+            )
+        };
+        open("src/lib.rs:88-89").unwrap();
+        assert_eq!(
+            delivered(&output),
+            [format!("https://github.com/oren0e/gitopen/commit/{}", SHA)]
+        );
+        assert_eq!(
+            open("src/lib.rs:90").unwrap_err().to_string(),
+            "Line 90 of 'src/lib.rs' has changes that aren't committed yet"
+        );
+        assert!(open("src/lib.rs").is_err());
+        assert!(open("src/lib.rs:1000").is_err());
+    }
+
+    #[test]
+    fn test_open_pr_for_commit() {
+        let git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        let output = Output::default();
+        open_pr_for_commit(&git, "HEAD", RemoteSelection::default(), &output).unwrap();
+        let git = FakeGit::new("https://gitlab.com/group/project.git", "main", SHA);
+        open_pr_for_commit(&git, "HEAD", RemoteSelection::default(), &output).unwrap();
+        assert_eq!(
+            delivered(&output),
+            [
+                format!(
+                    "https://github.com/oren0e/gitopen/pulls?q=is%3Apr%20{}",
+                    SHA
+                ),
+                format!("https://gitlab.com/group/project/-/commit/{}", SHA)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_open_at_line_number_for_several_paths() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.toplevel = Some(env::current_dir().unwrap());
+        let output = Output::default();
+        open_at_line_number(
+            &git,
+            &["src/main.rs:10", "src", "./README.md:3-5", "."],
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output),
+            [
+                "https://github.com/oren0e/gitopen/blob/main/src/main.rs#L10",
+                "https://github.com/oren0e/gitopen/tree/main/src",
+                "https://github.com/oren0e/gitopen/blob/main/README.md#L3-L5",
+                "https://github.com/oren0e/gitopen/tree/main",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_open_unpushed_branch_without_default_branch() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.toplevel = Some(env::current_dir().unwrap());
+        // A fresh branch, and `origin` was never fetched.
+        git.symbolic_refs
+            .insert("HEAD".to_string(), "refs/heads/feat".to_string());
+        git.revs.remove("refs/remotes/origin/main");
+        let output = Output::default();
+        let strict = LinkOptions {
+            strict: true,
+            ..LinkOptions::default()
+        };
+        assert_eq!(
+            open_at_line_number(&git, &["src"], strict, RemoteSelection::default(), &output)
+                .unwrap_err()
+                .to_string(),
+            "'feat' is not pushed to 'origin', whose default branch is unknown, the link won't work until it is"
+        );
+        open_at_line_number(
+            &git,
+            &["src"],
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output),
+            ["https://github.com/oren0e/gitopen/tree/feat/src"]
+        );
+    }
+
+    #[test]
+    fn test_strict_refuses_branch_missing_on_remote() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.toplevel = Some(env::current_dir().unwrap());
+        // `feat` tracks `origin/feature`, so the remote has no `feat`.
+        git.symbolic_refs
+            .insert("HEAD".to_string(), "refs/heads/feat".to_string());
+        git.revs
+            .insert("feat@{upstream}".to_string(), SHA.to_string());
+        let output = Output::default();
+        let strict = LinkOptions {
+            strict: true,
+            ..LinkOptions::default()
+        };
+        assert_eq!(
+            open_at_line_number(
+                &git,
+                &["src/main.rs:10"],
+                strict,
+                RemoteSelection::default(),
+                &output
+            )
+            .unwrap_err()
+            .to_string(),
+            "'feat' is not on 'origin', the link won't work until it is pushed"
+        );
+        let link_target = LinkTarget::Branch("main".to_string());
+        assert_eq!(
+            remote_mismatches(&git, "src/main.rs", "origin", &link_target).unwrap(),
+            Vec::<String>::new()
+        );
+        assert!(delivered(&output).is_empty());
+    }
+
+    #[test]
+    fn test_cargo_root() {
+        let repo = env::temp_dir().join(format!("gitopen-test-cargo-root-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&repo);
+        for dir in ["rust/crates/foo/src", "tool/src", "docs"] {
+            fs::create_dir_all(repo.join(dir)).unwrap();
+        }
+        fs::write(
+            repo.join("rust/Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/foo\"]\n",
+        )
+        .unwrap();
+        let package = "[package]\nname = \"foo\"\n";
+        fs::write(repo.join("rust/crates/foo/Cargo.toml"), package).unwrap();
+        fs::write(repo.join("tool/Cargo.toml"), package).unwrap();
+        assert_eq!(
+            cargo_root(&repo.join("rust/crates/foo/src"), &repo),
+            repo.join("rust")
+        );
+        assert_eq!(cargo_root(&repo.join("tool/src"), &repo), repo.join("tool"));
+        assert_eq!(cargo_root(&repo.join("docs"), &repo), repo);
+        fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn test_user_config_and_ssh_aliases() {
+        let mut git = FakeGit::new("git@work:team/repo.git", "main", SHA);
+        git.ssh_hosts
+            .insert("work".to_string(), "github.com".to_string());
+        git.user_config = "browser = \"firefox\"\nfile_action = \"blame\"".to_string();
+        assert_eq!(
+            get_repo_url(&git, RemoteSelection::default()).unwrap(),
+            "https://github.com/team/repo"
+        );
+        assert_eq!(get_browser(&git).unwrap().as_deref(), Some("firefox"));
+        assert_eq!(get_file_action(&git).unwrap(), FileAction::Blame);
+        // The repository's git config wins over the config file.
+        git.set_config("gitopen.browser", "chromium");
+        assert_eq!(get_browser(&git).unwrap().as_deref(), Some("chromium"));
+    }
+
+    #[test]
+    fn test_default_and_file_actions() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.toplevel = Some(env::current_dir().unwrap());
+        git.revs.insert(SHA.to_string(), SHA.to_string());
+        let output = Output::default();
+        git.set_config("gitopen.defaultaction", "branch");
+        assert_eq!(get_default_action(&git).unwrap(), DefaultAction::Branch);
+        open_default(
+            &git,
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        git.set_config("gitopen.defaultaction", "commit");
+        open_default(
+            &git,
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+
+        git.set_config("gitopen.fileaction", "blame");
+        let options = LinkOptions {
+            file_action: get_file_action(&git).unwrap(),
+            ..LinkOptions::default()
+        };
+        open_at_line_number(
+            &git,
+            &["src/main.rs", "src/main.rs:10", "src"],
+            options,
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output),
+            [
+                "https://github.com/oren0e/gitopen/tree/main".to_string(),
+                format!("https://github.com/oren0e/gitopen/commit/{}", SHA),
+                "https://github.com/oren0e/gitopen/blame/main/src/main.rs".to_string(),
+                "https://github.com/oren0e/gitopen/blob/main/src/main.rs#L10".to_string(),
+                "https://github.com/oren0e/gitopen/tree/main/src".to_string(),
+            ]
+        );
+        // The history of a bare file keeps to the commit with --permalink.
+        git.set_config("gitopen.fileaction", "history");
+        let options = LinkOptions {
+            permalink: true,
+            file_action: get_file_action(&git).unwrap(),
+            ..LinkOptions::default()
+        };
+        open_at_line_number(
+            &git,
+            &["src/main.rs"],
+            options,
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output).last().unwrap(),
+            &format!(
+                "https://github.com/oren0e/gitopen/commits/{}/src/main.rs",
+                SHA
+            )
+        );
+        git.set_config("gitopen.fileaction", "annotate");
+        assert!(get_file_action(&git).is_err());
+    }
+
+    #[test]
+    fn test_open_on_sourcegraph() {
+        let mut git = FakeGit::new("git@gitlab.com:group/project.git", "main", SHA);
+        git.toplevel = Some(env::current_dir().unwrap());
+        let output = Output::default();
+        let options = LinkOptions {
+            sourcegraph: true,
+            ..LinkOptions::default()
+        };
+        open_repo(&git, options, RemoteSelection::default(), &output).unwrap();
+        open_commit(&git, "HEAD", options, RemoteSelection::default(), &output).unwrap();
+        open_commit(
+            &git,
+            "HEAD..main",
+            options,
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        open_at_line_number(
+            &git,
+            &["src/main.rs:10-12", "src"],
+            options,
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        // Issues aren't code and stay on the provider.
+        open_issues(&git, RemoteSelection::default(), &output).unwrap();
+        assert_eq!(
+            delivered(&output),
+            [
+                "https://sourcegraph.com/gitlab.com/group/project".to_string(),
+                format!(
+                    "https://sourcegraph.com/gitlab.com/group/project/-/commit/{}",
+                    SHA
+                ),
+                format!(
+                    "https://sourcegraph.com/gitlab.com/group/project/-/compare/{}...{}",
+                    SHA, SHA
+                ),
+                "https://sourcegraph.com/gitlab.com/group/project@main/-/blob/src/main.rs?L10-12"
+                    .to_string(),
+                "https://sourcegraph.com/gitlab.com/group/project@main/-/tree/src".to_string(),
+                "https://gitlab.com/group/project/-/issues".to_string(),
+            ]
+        );
+
+        git.user_config = "sourcegraph = \"https://sourcegraph.corp.com\"".to_string();
+        open_repo(&git, options, RemoteSelection::default(), &output).unwrap();
+        assert_eq!(
+            delivered(&output).last().unwrap(),
+            "https://sourcegraph.corp.com/gitlab.com/group/project"
+        );
+
+        let remote = RemoteSelection::default();
+        open_blame(&git, "src/main.rs:3", options, remote, &output).unwrap();
+        open_history(&git, "src", options, remote, &output).unwrap();
+        open_branch(&git, None, false, options, remote, &output).unwrap();
+        let blame_files = LinkOptions {
+            file_action: FileAction::Blame,
+            ..options
+        };
+        open_at_line_number(&git, &["src/main.rs"], blame_files, remote, &output).unwrap();
+        let sourcegraph_repo = "https://sourcegraph.corp.com/gitlab.com/group/project@main";
+        assert_eq!(
+            delivered(&output)[7..],
+            [
+                format!("{}/-/blame/src/main.rs?L3", sourcegraph_repo),
+                format!("{}/-/commits/src", sourcegraph_repo),
+                sourcegraph_repo.to_string(),
+                format!("{}/-/blame/src/main.rs", sourcegraph_repo),
+            ]
+        );
+        // Pages Sourcegraph doesn't have aren't opened on the git host instead.
+        assert!(open_edit(&git, "src/main.rs", None, options, remote, &output).is_err());
+        assert!(open_archive(&git, None, ArchiveFormat::Zip, options, remote, &output).is_err());
+        assert_eq!(delivered(&output).len(), 11);
+    }
+
+    #[test]
+    fn test_open_edit() {
+        let mut git = FakeGit::new("git@gitlab.com:group/project.git", "fix/typo", SHA);
+        git.toplevel = Some(env::current_dir().unwrap());
+        let output = Output::default();
+        open_edit(
+            &git,
+            "./README.md:3",
+            None,
+            LinkOptions {
+                permalink: true,
+                ..LinkOptions::default()
+            },
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output),
+            ["https://gitlab.com/-/ide/project/group/project/edit/fix/typo/-/README.md"]
+        );
+
+        open_edit(
+            &git,
+            "README.md",
+            Some("https://gitpod.io/#"),
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output).last().unwrap(),
+            "https://gitpod.io/#https://gitlab.com/group/project/-/blob/fix/typo/README.md"
+        );
+    }
+
+    #[test]
+    fn test_open_raw() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.toplevel = Some(env::current_dir().unwrap());
+        let output = Output::default();
+        let options = LinkOptions {
+            raw: true,
+            ..LinkOptions::default()
+        };
+        open_at_line_number(
+            &git,
+            &["src/main.rs:10"],
+            options,
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        // A directory later in the batch fails it before any link is out.
+        assert!(open_at_line_number(
+            &git,
+            &["src/main.rs:10", "src"],
+            options,
+            RemoteSelection::default(),
+            &output
+        )
+        .is_err());
+        assert_eq!(
+            delivered(&output),
+            ["https://raw.githubusercontent.com/oren0e/gitopen/main/src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_open_in_submodule() {
+        let submodule_sha = "0123456789abcdef0123456789abcdef01234567";
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.toplevel = Some(env::current_dir().unwrap());
+        git.submodules = vec![Submodule {
+            path: "libs/json".to_string(),
+            url: "../json.git".to_string(),
+        }];
+        git.revs
+            .insert("HEAD:libs/json".to_string(), submodule_sha.to_string());
+        let output = Output::default();
+        open_at_line_number(
+            &git,
+            &["libs/json/src/lib.rs:3", "src/main.rs:10"],
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        open_submodule(&git, "libs/json", RemoteSelection::default(), &output).unwrap();
+        assert!(open_submodule(&git, "libs", RemoteSelection::default(), &output).is_err());
+        assert_eq!(
+            delivered(&output),
+            [
+                format!(
+                    "https://github.com/oren0e/json/blob/{}/src/lib.rs#L3",
+                    submodule_sha
+                ),
+                "https://github.com/oren0e/gitopen/blob/main/src/main.rs#L10".to_string(),
+                format!("https://github.com/oren0e/json/tree/{}", submodule_sha),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_open_branch() {
+        let git = FakeGit::new("git@github.com:oren0e/gitopen.git", "feat/x", SHA);
+        let output = Output::default();
+        open_branch(
+            &git,
+            None,
+            false,
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        open_branch(
+            &git,
+            Some("release/1.x"),
+            false,
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        let git = FakeGit::new("https://gitlab.com/group/project.git", "main", SHA);
+        open_branch(
+            &git,
+            None,
+            false,
+            LinkOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output),
+            [
+                "https://github.com/oren0e/gitopen/tree/feat/x",
+                "https://github.com/oren0e/gitopen/tree/release/1.x",
+                "https://gitlab.com/group/project/-/tree/main",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_and_open_pr() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "feat/x", SHA);
+        git.push_output.stderr = "remote:\nremote: Create a pull request for 'feat/x' on GitHub by visiting:\nremote:      https://github.com/oren0e/gitopen/pull/new/feat/x\nremote:\n".to_string();
+        let output = Output::default();
+        push_and_open_pr(
+            &git,
+            &PushOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(*git.pushes.borrow(), [["origin", "feat/x"]]);
+        assert_eq!(
+            delivered(&output),
+            ["https://github.com/oren0e/gitopen/pull/new/feat/x"]
+        );
+    }
+
+    #[test]
+    fn test_push_and_open_pr_dry_run() {
+        let git = FakeGit::new("git@github.com:oren0e/gitopen.git", "feat/x", SHA);
+        let output = Output {
+            dry_run: true,
+            ..Output::default()
+        };
+        push_and_open_pr(
+            &DryRun { git: &git },
+            &PushOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert!(git.pushes.borrow().is_empty());
+        // Without the link the push would print, the one searching for it.
+        assert_eq!(
+            delivered(&output),
+            ["https://github.com/oren0e/gitopen/pulls?q=is%3Apr%20head%3Afeat%2Fx"]
+        );
+    }
+
+    #[test]
+    fn test_push_and_open_pr_sets_upstream() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "feat", SHA);
+        git.config.borrow_mut().remove("branch.feat.merge");
+        git.push_output.stderr = "Everything up-to-date\n".to_string();
+        push_and_open_pr(
+            &git,
+            &PushOptions::default(),
+            RemoteSelection::default(),
+            &Output::default(),
+        )
+        .unwrap();
+        git.set_config("gitopen.setupstream", "false");
+        push_and_open_pr(
+            &git,
+            &PushOptions::default(),
+            RemoteSelection::default(),
+            &Output::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            *git.pushes.borrow(),
+            [
+                vec!["--set-upstream", "origin", "feat"],
+                vec!["origin", "feat"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_and_open_pr_passes_push_options() {
+        let mut git = FakeGit::new("git@gitlab.com:group/project.git", "feat", SHA);
+        git.push_output.stderr = "Everything up-to-date\n".to_string();
+        let push_options = PushOptions {
+            force_with_lease: true,
+            push_options: vec!["ci.skip", "merge_request.create"],
+        };
+        push_and_open_pr(
+            &git,
+            &push_options,
+            RemoteSelection::default(),
+            &Output::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            *git.pushes.borrow(),
+            [[
+                "--force-with-lease",
+                "--push-option",
+                "ci.skip",
+                "--push-option",
+                "merge_request.create",
+                "origin",
+                "feat"
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_open_branch_pr() {
+        let git = FakeGit::new("git@gitlab.com:group/project.git", "feat", SHA);
+        let output = Output::default();
+        open_branch_pr(&git, RemoteSelection::default(), &output).unwrap();
+        assert_eq!(
+            delivered(&output),
+            ["https://gitlab.com/group/project/-/merge_requests?source_branch=feat"]
+        );
+    }
+
+    #[test]
+    fn test_open_new_pr() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "feat", SHA);
+        git.commit_messages.insert(
+            "HEAD".to_string(),
+            "Open new PRs\n\nWith a title.\n".to_string(),
+        );
+        git.revs.insert("release".to_string(), SHA.to_string());
+        let output = Output::default();
+        let options = NewPrOptions {
+            base: Some("release"),
+            fill: true,
+            ..NewPrOptions::default()
+        };
+        open_new_pr(&git, options, RemoteSelection::default(), &output).unwrap();
+        let options = NewPrOptions {
+            title: Some("Mine"),
+            base: Some("release"),
+            draft: true,
+            ..NewPrOptions::default()
+        };
+        open_new_pr(&git, options, RemoteSelection::default(), &output).unwrap();
+        assert_eq!(
+            delivered(&output),
+            [
+                "https://github.com/oren0e/gitopen/compare/release...feat?quick_pull=1&title=Open%20new%20PRs&body=With%20a%20title.",
+                "https://github.com/oren0e/gitopen/compare/release...feat?quick_pull=1&draft=1&title=Mine"
+            ]
+        );
+        let unknown_base = NewPrOptions {
+            base: Some("nope"),
+            ..NewPrOptions::default()
+        };
+        assert!(open_new_pr(&git, unknown_base, RemoteSelection::default(), &output).is_err());
+
+        git.revs.remove("refs/remotes/origin/feat");
+        git.config.borrow_mut().remove("branch.feat.merge");
+        let err = open_new_pr(&git, options, RemoteSelection::default(), &output).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<GitOpenError>(),
+            Some(&GitOpenError::NoUpstream {
+                branch: "feat".to_string(),
+                remote: "origin".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_open_new_pr_from_fork() {
+        let mut git = FakeGit::new("git@github.com:me/gitopen.git", "feat", SHA);
+        git.remotes.push((
+            "upstream".to_string(),
+            "git@github.com:oren0e/gitopen.git".to_string(),
+        ));
+        git.symbolic_refs.insert(
+            "refs/remotes/upstream/HEAD".to_string(),
+            "refs/remotes/upstream/master".to_string(),
+        );
+        let upstream = RemoteSelection {
+            name: Some("upstream"),
+            interactive: false,
+        };
+        let output = Output::default();
+        open_new_pr(&git, NewPrOptions::default(), upstream, &output).unwrap();
+        assert_eq!(
+            delivered(&output),
+            ["https://github.com/oren0e/gitopen/compare/master...me:feat?quick_pull=1"]
+        );
+    }
+
+    #[test]
+    fn test_triangular_workflow() {
+        let mut git = FakeGit::new("git@github.com:me/gitopen.git", "feat", SHA);
+        git.remotes.push((
+            "upstream".to_string(),
+            "git@github.com:oren0e/gitopen.git".to_string(),
+        ));
+        git.symbolic_refs.insert(
+            "refs/remotes/upstream/HEAD".to_string(),
+            "refs/remotes/upstream/master".to_string(),
+        );
+        git.set_config("branch.feat.remote", "upstream");
+        git.set_config("remote.pushDefault", "origin");
+        git.push_output.stderr = "remote:\nremote: Create a pull request for 'feat' on GitHub by visiting:\nremote:      https://github.com/me/gitopen/pull/new/feat\n".to_string();
+        let output = Output::default();
+        open_repo(
+            &git,
+            LinkOptions {
+                push: true,
+                ..LinkOptions::default()
+            },
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        push_and_open_pr(
+            &git,
+            &PushOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        open_new_pr(
+            &git,
+            NewPrOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            *git.pushes.borrow(),
+            [["origin", "feat"], ["origin", "feat"]]
+        );
+        assert_eq!(
+            delivered(&output),
+            [
+                "https://github.com/oren0e/gitopen",
+                "https://github.com/me/gitopen/pull/new/feat",
+                "https://github.com/oren0e/gitopen/compare/master...me:feat?quick_pull=1"
+            ]
+        );
+
+        git.set_config("branch.feat.pushRemote", "upstream");
+        open_repo(
+            &git,
+            LinkOptions {
+                push: true,
+                ..LinkOptions::default()
+            },
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(git.pushes.borrow().last().unwrap(), &["upstream", "feat"]);
+    }
+
+    #[test]
+    fn test_push_and_open_pr_explains_rejections() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "feat", SHA);
+        git.push_output = GitOutput {
+            success: false,
+            stdout: String::new(),
+            stderr: " ! [rejected]        feat -> feat (non-fast-forward)\n".to_string(),
+        };
+        let err = push_and_open_pr(
+            &git,
+            &PushOptions::default(),
+            RemoteSelection::default(),
+            &Output::default(),
+        )
+        .unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 5);
+        let err = err.to_string();
+        assert!(err.starts_with("Pushing 'feat' to 'origin' failed, the remote branch has commits"));
+        assert!(err.ends_with("(non-fast-forward)"));
+    }
+
+    #[test]
+    fn test_push_and_open_pr_when_up_to_date() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "feat/x", SHA);
+        git.push_output.stderr = "Everything up-to-date\n".to_string();
+        let output = Output::default();
+        push_and_open_pr(
+            &git,
+            &PushOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output),
+            ["https://github.com/oren0e/gitopen/pulls?q=is%3Apr%20head%3Afeat%2Fx"]
+        );
+
+        let mut git = FakeGit::new("https://gitea.com/org/repo.git", "feat", SHA);
+        git.symbolic_refs.insert(
+            "refs/remotes/origin/HEAD".to_string(),
+            "refs/remotes/origin/main".to_string(),
+        );
+        let output = Output::default();
+        push_and_open_pr(
+            &git,
+            &PushOptions::default(),
+            RemoteSelection::default(),
+            &output,
+        )
+        .unwrap();
+        assert_eq!(
+            delivered(&output),
+            ["https://gitea.com/org/repo/compare/main...feat"]
+        );
+    }
+
+    #[test]
+    fn test_push_and_open_pr_on_detached_head() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.symbolic_refs.clear();
+        let err = push_and_open_pr(
+            &git,
+            &PushOptions::default(),
+            RemoteSelection::default(),
+            &Output::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<GitOpenError>(),
+            Some(&GitOpenError::DetachedHead)
+        );
+        assert!(git.pushes.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_open_archive() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", SHA);
+        git.revs
+            .insert("refs/tags/v1.0".to_string(), SHA.to_string());
+        git.revs.insert(SHA.to_string(), SHA.to_string());
+        let output = Output::default();
+        let open = |rev, format| {
+            open_archive(
+                &git,
+                rev,
+                format,
+                LinkOptions::default(),
+                RemoteSelection::default(),
+                &output,
+            )
+        };
+        open(None, ArchiveFormat::Zip).unwrap();
+        open(Some("v1.0"), ArchiveFormat::TarGz).unwrap();
+        open(Some(SHA), ArchiveFormat::Zip).unwrap();
+        assert_eq!(
+            delivered(&output),
+            [
+                "https://github.com/oren0e/gitopen/archive/refs/heads/main.zip".to_string(),
+                "https://github.com/oren0e/gitopen/archive/refs/tags/v1.0.tar.gz".to_string(),
+                format!("https://github.com/oren0e/gitopen/archive/{}.zip", SHA),
+            ]
+        );
+        assert!(open(Some("nope"), ArchiveFormat::Zip).is_err());
+    }
+
+    #[test]
+    fn test_first_release_containing() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", "abc123");
+        for tag in ["v1.1.0", "v1.0.1", "v1.0.0-rc1"] {
+            git.revs
+                .insert(format!("refs/tags/{}", tag), "abc123".to_string());
+        }
+        let tags = git.tags_containing("abc123").unwrap();
+        let releases = [
+            "v1.1.0".to_string(),
+            "v1.0.1".to_string(),
+            "v1.0.0".to_string(),
+        ];
+        assert_eq!(
+            first_release_containing(Some(&releases), &tags).map(String::as_str),
+            Some("v1.0.1")
+        );
+        assert_eq!(
+            first_release_containing(None, &tags).map(String::as_str),
+            Some("v1.0.0-rc1")
+        );
+        assert_eq!(first_release_containing(Some(&releases[2..]), &tags), None);
+    }
 
     #[test]
     fn test_correct_pr_parsing_from_output() {
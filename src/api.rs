@@ -0,0 +1,768 @@
+//! Asks the GitHub and GitLab REST APIs what a URL alone can't say, like
+//! which pull request is open for a branch. Only built with the `api`
+//! feature; without it, or without a token, gitopen links to a search.
+//!
+//! The token comes from the environment, or else from the `gh` and `glab`
+//! command line tools, which keep it in their config or the keyring. It is
+//! only sent over https, and to a self-hosted instance only once the host
+//! is known to run the forge: `GH_TOKEN` and friends are for github.com and
+//! gitlab.com alone, other hosts get `GH_ENTERPRISE_TOKEN` or their own.
+//!
+//! Self-hosted instances with names that don't give the forge away can also
+//! be asked which one they run, at endpoints that need no token.
+
+use crate::match_logic::{build_query_string, encode_segment, get_host, split_repo_url};
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tracing::{debug, trace};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Forge {
+    GitHub,
+    GitLab,
+}
+
+impl Forge {
+    fn for_provider(provider_name: &str) -> Option<Forge> {
+        match provider_name {
+            "github" => Some(Forge::GitHub),
+            "gitlab" => Some(Forge::GitLab),
+            _ => None,
+        }
+    }
+
+    /// The host of the forge's public instance.
+    fn public_host(self) -> &'static str {
+        match self {
+            Forge::GitHub => "github.com",
+            Forge::GitLab => "gitlab.com",
+        }
+    }
+
+    /// The environment variables with a token for `host`. Those of the
+    /// public instance aren't handed to any other host.
+    fn token_vars(self, host: &str) -> &'static [&'static str] {
+        match self {
+            Forge::GitHub if host == self.public_host() => &["GH_TOKEN", "GITHUB_TOKEN"],
+            Forge::GitHub => &["GH_ENTERPRISE_TOKEN", "GITHUB_ENTERPRISE_TOKEN"],
+            Forge::GitLab if host == self.public_host() => &["GITLAB_TOKEN"],
+            Forge::GitLab => &[],
+        }
+    }
+
+    /// Where the API of the instance at `origin` (`https://host`) lives.
+    /// GitHub Enterprise serves it under `/api/v3` of its own host.
+    fn api_base(self, origin: &str) -> String {
+        match self {
+            Forge::GitHub if origin == "https://github.com" => "https://api.github.com".to_string(),
+            Forge::GitHub => format!("{}/api/v3", origin),
+            Forge::GitLab => format!("{}/api/v4", origin),
+        }
+    }
+
+    fn auth_header(self, token: &str) -> (&'static str, String) {
+        match self {
+            Forge::GitHub => ("Authorization", format!("Bearer {}", token)),
+            Forge::GitLab => ("PRIVATE-TOKEN", token.to_string()),
+        }
+    }
+
+    /// The field of a pull request or CI run with its web page.
+    fn web_url_field(self) -> &'static str {
+        match self {
+            Forge::GitHub => "html_url",
+            Forge::GitLab => "web_url",
+        }
+    }
+
+    /// The config file of the forge's command line tool and the key its
+    /// token is stored under, per host.
+    fn cli_config(self, var: &impl Fn(&str) -> Option<String>) -> Option<(PathBuf, &'static str)> {
+        let config_dir = |own_var: &str, name: &str| match var(own_var) {
+            Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+            _ => match var("XDG_CONFIG_HOME") {
+                Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join(name)),
+                _ => Some(PathBuf::from(var("HOME")?).join(".config").join(name)),
+            },
+        };
+        match self {
+            Forge::GitHub => Some((
+                config_dir("GH_CONFIG_DIR", "gh")?.join("hosts.yml"),
+                "oauth_token",
+            )),
+            Forge::GitLab => Some((
+                config_dir("GLAB_CONFIG_DIR", "glab-cli")?.join("config.yml"),
+                "token",
+            )),
+        }
+    }
+
+    /// Asks the command line tool for its token, which finds it in the
+    /// keyring too.
+    fn cli_token(self, host: &str) -> Option<String> {
+        let args: &[&str] = match self {
+            Forge::GitHub => &["gh", "auth", "token", "--hostname", host],
+            Forge::GitLab => &["glab", "config", "get", "token", "--host", host],
+        };
+        let output = Command::new(args[0])
+            .args(&args[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        Some(token).filter(|token| !token.is_empty())
+    }
+}
+
+/// Reads `key` from the section of `host` in a `gh` or `glab` YAML config,
+/// where hosts are keys holding their settings, one per line.
+fn yaml_host_value(yaml: &str, host: &str, key: &str) -> Option<String> {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut lines = yaml.lines();
+    let host_indent = lines
+        .by_ref()
+        .find(|line| line.trim().strip_suffix(':').map(unquote) == Some(host))
+        .map(indent)?;
+    lines
+        .take_while(|line| line.trim().is_empty() || indent(line) > host_indent)
+        .filter_map(|line| line.trim().strip_prefix(key)?.strip_prefix(':'))
+        .map(unquote)
+        .find(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// The token for `host`: from the environment, the command line tool's
+/// config file, or the tool itself.
+fn find_token(forge: Forge, host: &str, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    if let Some(token) = forge
+        .token_vars(host)
+        .iter()
+        .find_map(|name| var(name).filter(|token| !token.is_empty()))
+    {
+        return Some(token);
+    }
+    if let Some((path, key)) = forge.cli_config(&var) {
+        if let Some(token) = fs::read_to_string(path)
+            .ok()
+            .and_then(|yaml| yaml_host_value(&yaml, host, key))
+        {
+            return Some(token);
+        }
+    }
+    forge.cli_token(host)
+}
+
+/// The API of the forge a repository is on, with the token to call it.
+#[derive(Debug, PartialEq)]
+struct Client {
+    forge: Forge,
+    api_base: String,
+    project: String,
+    token: String,
+}
+
+impl Client {
+    /// A client for the repository, or `None` when its provider has no API
+    /// gitopen knows or there is no token for its host. `identified` says
+    /// the host is known to run the provider, rather than just falling back
+    /// to GitHub; hosts other than the public instances need it, and all of
+    /// them https.
+    fn for_repo(
+        provider_name: &str,
+        repo_url: &str,
+        identified: bool,
+        token: impl Fn(Forge, &str) -> Option<String>,
+    ) -> Option<Client> {
+        let forge = Forge::for_provider(provider_name)?;
+        let host = get_host(repo_url);
+        let (origin, project) = split_repo_url(repo_url);
+        if !origin.starts_with("https://") {
+            debug!("sends no token to {}, which isn't https", origin);
+            return None;
+        }
+        if !identified && host != forge.public_host() {
+            debug!(
+                "sends no token to {}, not known to run {}",
+                host, provider_name
+            );
+            return None;
+        }
+        let token = token(forge, host)?;
+        Some(Client {
+            forge,
+            api_base: forge.api_base(origin),
+            project: project.to_string(),
+            token,
+        })
+    }
+
+    /// The request listing the open pull requests from `branch`.
+    fn branch_prs_path(&self, branch: &str) -> String {
+        match self.forge {
+            Forge::GitHub => {
+                let owner = self.project.split('/').next().unwrap_or_default();
+                let head = format!("{}:{}", owner, branch);
+                format!(
+                    "/repos/{}/pulls{}",
+                    self.project,
+                    build_query_string(&[("head", &head), ("state", "open")])
+                )
+            }
+            Forge::GitLab => format!(
+                "/projects/{}/merge_requests{}",
+                encode_segment(&self.project),
+                build_query_string(&[("source_branch", branch), ("state", "opened")])
+            ),
+        }
+    }
+
+    /// The request listing the pull requests that contain the commit.
+    fn commit_prs_path(&self, commit_sha: &str) -> String {
+        match self.forge {
+            Forge::GitHub => format!("/repos/{}/commits/{}/pulls", self.project, commit_sha),
+            Forge::GitLab => format!(
+                "/projects/{}/repository/commits/{}/merge_requests",
+                encode_segment(&self.project),
+                commit_sha
+            ),
+        }
+    }
+
+    /// The request listing the failed CI runs of `branch`, latest first.
+    fn failed_runs_path(&self, branch: &str) -> String {
+        match self.forge {
+            Forge::GitHub => format!(
+                "/repos/{}/actions/runs{}",
+                self.project,
+                build_query_string(&[("branch", branch), ("status", "failure"), ("per_page", "1")])
+            ),
+            Forge::GitLab => format!(
+                "/projects/{}/pipelines{}",
+                encode_segment(&self.project),
+                build_query_string(&[("ref", branch), ("status", "failed"), ("per_page", "1")])
+            ),
+        }
+    }
+
+    /// The request listing the releases, latest first.
+    fn releases_path(&self) -> String {
+        match self.forge {
+            Forge::GitHub => format!("/repos/{}/releases?per_page=100", self.project),
+            Forge::GitLab => format!(
+                "/projects/{}/releases?per_page=100",
+                encode_segment(&self.project)
+            ),
+        }
+    }
+
+    /// The web page of the pull request that merged a commit, out of those
+    /// containing it: the merged one, or else the first.
+    fn merging_web_url(&self, prs: &Value) -> Option<String> {
+        let is_merged = |pr: &Value| match self.forge {
+            Forge::GitHub => pr
+                .get("merged_at")
+                .is_some_and(|merged_at| !merged_at.is_null()),
+            Forge::GitLab => pr.get("state").and_then(Value::as_str) == Some("merged"),
+        };
+        let prs = prs.as_array()?;
+        prs.iter()
+            .find(|pr| is_merged(pr))
+            .or_else(|| prs.first())?
+            .get(self.forge.web_url_field())?
+            .as_str()
+            .map(str::to_string)
+    }
+
+    fn get(&self, path: &str) -> AnyhowResult<Value> {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(10)))
+            .build()
+            .into();
+        let (header, value) = self.forge.auth_header(&self.token);
+        let url = format!("{}{}", self.api_base, path);
+        debug!("GET {}", url);
+        let mut response = agent
+            .get(&url)
+            .header(header, &value)
+            .header("User-Agent", "gitopen")
+            .call()
+            .map_err(|err| anyhow!("Request to {} failed: {}", url, err))?;
+        let text = response.body_mut().read_to_string()?;
+        trace!(
+            status = response.status().as_u16(),
+            body = text,
+            "{} answered",
+            url
+        );
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// The web page of the first pull request or CI run in a list the API
+    /// returned. GitHub wraps its runs in an object.
+    fn first_web_url(&self, list: &Value) -> Option<String> {
+        list.get("workflow_runs")
+            .unwrap_or(list)
+            .get(0)?
+            .get(self.forge.web_url_field())?
+            .as_str()
+            .map(str::to_string)
+    }
+}
+
+/// The open pull request from `branch`, or `None` when there is none or
+/// the API can't be asked.
+pub fn find_branch_pr(
+    provider_name: &str,
+    repo_url: &str,
+    identified: bool,
+    branch: &str,
+) -> AnyhowResult<Option<String>> {
+    let client = match client_for_repo(provider_name, repo_url, identified) {
+        Some(client) => client,
+        None => return Ok(None),
+    };
+    let prs = client.get(&client.branch_prs_path(branch))?;
+    Ok(client.first_web_url(&prs))
+}
+
+/// The pull request that introduced the commit, or `None` when there is
+/// none or the API can't be asked.
+pub fn find_commit_pr(
+    provider_name: &str,
+    repo_url: &str,
+    identified: bool,
+    commit_sha: &str,
+) -> AnyhowResult<Option<String>> {
+    let client = match client_for_repo(provider_name, repo_url, identified) {
+        Some(client) => client,
+        None => return Ok(None),
+    };
+    let prs = client.get(&client.commit_prs_path(commit_sha))?;
+    Ok(client.merging_web_url(&prs))
+}
+
+/// The latest failed CI run of `branch`, or `None` when there is none or
+/// the API can't be asked.
+pub fn find_failed_run(
+    provider_name: &str,
+    repo_url: &str,
+    identified: bool,
+    branch: &str,
+) -> AnyhowResult<Option<String>> {
+    let client = match client_for_repo(provider_name, repo_url, identified) {
+        Some(client) => client,
+        None => return Ok(None),
+    };
+    let runs = client.get(&client.failed_runs_path(branch))?;
+    Ok(client.first_web_url(&runs))
+}
+
+/// The tags of the repository's releases, latest first, or `None` when the
+/// API can't be asked. Both forges name the tag `tag_name`.
+pub fn find_release_tags(
+    provider_name: &str,
+    repo_url: &str,
+    identified: bool,
+) -> AnyhowResult<Option<Vec<String>>> {
+    let client = match client_for_repo(provider_name, repo_url, identified) {
+        Some(client) => client,
+        None => return Ok(None),
+    };
+    let releases = client.get(&client.releases_path())?;
+    Ok(Some(release_tags(&releases)))
+}
+
+fn release_tags(releases: &Value) -> Vec<String> {
+    releases
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|release| release.get("tag_name")?.as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The endpoints that tell self-hosted forges apart, in the order they are
+/// asked, with the provider each one gives away.
+const PROBES: [(&str, &str); 3] = [
+    ("/api/v4/version", "gitlab"),
+    ("/api/v1/version", "gitea"),
+    ("/api/v3", "github"),
+];
+
+/// Whether the response to the probe of `provider_name` came from that
+/// forge, rather than from a page or proxy that answers any path.
+fn is_forge_response(
+    provider_name: &str,
+    status: u16,
+    enterprise_header: bool,
+    body: &str,
+) -> bool {
+    let json: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+    let has = |key: &str| json.get(key).is_some_and(Value::is_string);
+    match provider_name {
+        // GitLab only tells its version to signed-in users.
+        "gitlab" => {
+            (status == 200 && has("version") && has("revision"))
+                || (status == 401
+                    && json.get("message").and_then(Value::as_str) == Some("401 Unauthorized"))
+        }
+        "gitea" => status == 200 && has("version"),
+        // Private GitHub Enterprise instances want a token here too, but
+        // still say what they are.
+        "github" => enterprise_header || (status == 200 && has("current_user_url")),
+        _ => false,
+    }
+}
+
+/// Asks the instance at `origin` (`https://host`) which forge it runs.
+/// `Ok(None)` means it answered like none of them, errors that it couldn't
+/// be reached.
+pub fn probe_forge(origin: &str) -> AnyhowResult<Option<&'static str>> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(3)))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    for (path, provider_name) in PROBES.iter() {
+        let url = format!("{}{}", origin, path);
+        debug!("probing {} for {}", url, provider_name);
+        let mut response = agent
+            .get(&url)
+            .header("User-Agent", "gitopen")
+            .call()
+            .map_err(|err| anyhow!("Request to {} failed: {}", url, err))?;
+        let enterprise_header = response
+            .headers()
+            .contains_key("x-github-enterprise-version");
+        let body = response.body_mut().read_to_string().unwrap_or_default();
+        if is_forge_response(
+            provider_name,
+            response.status().as_u16(),
+            enterprise_header,
+            &body,
+        ) {
+            return Ok(Some(provider_name));
+        }
+    }
+    Ok(None)
+}
+
+fn client_for_repo(provider_name: &str, repo_url: &str, identified: bool) -> Option<Client> {
+    Client::for_repo(provider_name, repo_url, identified, |forge, host| {
+        find_token(forge, host, |name| env::var(name).ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars<'a>(set: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            set.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    fn tokens<'a>(set: &'a [(&'a str, &'a str)]) -> impl Fn(Forge, &str) -> Option<String> + 'a {
+        move |forge, host| {
+            forge
+                .token_vars(host)
+                .iter()
+                .find_map(|name| vars(set)(name))
+        }
+    }
+
+    #[test]
+    fn test_client_for_repo() {
+        let github = Client::for_repo(
+            "github",
+            "https://github.com/org/repo",
+            true,
+            tokens(&[("GITHUB_TOKEN", "t0k")]),
+        )
+        .unwrap();
+        assert_eq!(github.api_base, "https://api.github.com");
+        assert_eq!(github.project, "org/repo");
+        assert_eq!(
+            Client::for_repo(
+                "github",
+                "https://github.corp.com/org/repo",
+                true,
+                tokens(&[("GH_ENTERPRISE_TOKEN", "t0k")]),
+            )
+            .unwrap()
+            .api_base,
+            "https://github.corp.com/api/v3"
+        );
+        assert_eq!(
+            Client::for_repo(
+                "gitlab",
+                "https://gitlab.com/group/sub/project",
+                true,
+                tokens(&[("GITLAB_TOKEN", "t0k")]),
+            )
+            .unwrap()
+            .api_base,
+            "https://gitlab.com/api/v4"
+        );
+        assert!(
+            Client::for_repo("github", "https://github.com/org/repo", true, tokens(&[])).is_none()
+        );
+        assert!(Client::for_repo(
+            "gitea",
+            "https://gitea.com/org/repo",
+            true,
+            tokens(&[("GITHUB_TOKEN", "t0k")]),
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_no_token_for_unknown_host() {
+        let env_tokens = tokens(&[("GH_TOKEN", "t0k"), ("GITHUB_TOKEN", "t0k")]);
+        assert!(
+            Client::for_repo("github", "https://git.example.com/o/r", false, &env_tokens).is_none()
+        );
+        assert!(
+            Client::for_repo("github", "https://git.example.com/o/r", true, &env_tokens).is_none()
+        );
+        assert!(Client::for_repo("github", "http://github.com/o/r", true, &env_tokens).is_none());
+        assert!(Client::for_repo(
+            "github",
+            "http://github.corp.com/o/r",
+            true,
+            tokens(&[("GH_ENTERPRISE_TOKEN", "t0k")]),
+        )
+        .is_none());
+        assert!(Client::for_repo(
+            "github",
+            "https://git.example.com/o/r",
+            false,
+            tokens(&[("GH_ENTERPRISE_TOKEN", "t0k")]),
+        )
+        .is_none());
+        assert_eq!(
+            find_token(
+                Forge::GitHub,
+                "git.example.com",
+                vars(&[("GH_TOKEN", "t0k"), ("HOME", "/nonexistent")])
+            ),
+            None
+        );
+        assert_eq!(
+            find_token(
+                Forge::GitLab,
+                "gitlab.example.com",
+                vars(&[("GITLAB_TOKEN", "t0k"), ("HOME", "/nonexistent")])
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_forge_response() {
+        assert!(is_forge_response(
+            "gitlab",
+            200,
+            false,
+            r#"{"version":"17.0.1","revision":"abc"}"#
+        ));
+        assert!(is_forge_response(
+            "gitlab",
+            401,
+            false,
+            r#"{"message":"401 Unauthorized"}"#
+        ));
+        assert!(!is_forge_response(
+            "gitlab",
+            401,
+            false,
+            "<html>Sign in</html>"
+        ));
+        assert!(is_forge_response(
+            "gitea",
+            200,
+            false,
+            r#"{"version":"1.21.0"}"#
+        ));
+        assert!(!is_forge_response(
+            "gitea",
+            404,
+            false,
+            r#"{"version":"1.21.0"}"#
+        ));
+        assert!(is_forge_response(
+            "github",
+            200,
+            false,
+            r#"{"current_user_url":"https://github.corp.com/api/v3/user"}"#
+        ));
+        assert!(is_forge_response("github", 401, true, ""));
+        assert!(!is_forge_response("github", 200, false, "<html></html>"));
+    }
+
+    #[test]
+    fn test_find_token() {
+        let gh_hosts = "github.com:\n    user: me\n    oauth_token: gho_abc\n    git_protocol: ssh\ngithub.corp.com:\n    oauth_token: \"gho_corp\"\n";
+        assert_eq!(
+            yaml_host_value(gh_hosts, "github.corp.com", "oauth_token").as_deref(),
+            Some("gho_corp")
+        );
+        assert_eq!(
+            yaml_host_value(gh_hosts, "github.com", "oauth_token").as_deref(),
+            Some("gho_abc")
+        );
+        let glab_config = "git_protocol: ssh\nhosts:\n    gitlab.com:\n        api_host: gitlab.com\n        token: glpat-xyz\n    gitlab.corp.com:\n        api_host: gitlab.corp.com\n";
+        assert_eq!(
+            yaml_host_value(glab_config, "gitlab.com", "token").as_deref(),
+            Some("glpat-xyz")
+        );
+        assert_eq!(
+            yaml_host_value(glab_config, "gitlab.corp.com", "token"),
+            None
+        );
+        assert_eq!(
+            find_token(
+                Forge::GitHub,
+                "github.com",
+                vars(&[("GH_TOKEN", "from-env")])
+            )
+            .as_deref(),
+            Some("from-env")
+        );
+        assert_eq!(
+            Forge::GitLab
+                .cli_config(&vars(&[("HOME", "/home/me")]))
+                .unwrap(),
+            (
+                PathBuf::from("/home/me/.config/glab-cli/config.yml"),
+                "token"
+            )
+        );
+    }
+
+    #[test]
+    fn test_commit_prs() {
+        let gitlab = Client::for_repo(
+            "gitlab",
+            "https://gitlab.com/group/project",
+            true,
+            tokens(&[("GITLAB_TOKEN", "t0k")]),
+        )
+        .unwrap();
+        assert_eq!(
+            gitlab.commit_prs_path("998a1b3"),
+            "/projects/group%2Fproject/repository/commits/998a1b3/merge_requests"
+        );
+        let mrs: Value = serde_json::from_str(
+            r#"[{"state": "closed", "web_url": "https://gitlab.com/group/project/-/merge_requests/3"},
+                {"state": "merged", "web_url": "https://gitlab.com/group/project/-/merge_requests/4"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            gitlab.merging_web_url(&mrs).as_deref(),
+            Some("https://gitlab.com/group/project/-/merge_requests/4")
+        );
+
+        let github = Client::for_repo(
+            "github",
+            "https://github.com/org/repo",
+            true,
+            tokens(&[("GITHUB_TOKEN", "t0k")]),
+        )
+        .unwrap();
+        let prs: Value = serde_json::from_str(
+            r#"[{"merged_at": null, "html_url": "https://github.com/org/repo/pull/8"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            github.merging_web_url(&prs).as_deref(),
+            Some("https://github.com/org/repo/pull/8")
+        );
+    }
+
+    #[test]
+    fn test_failed_runs() {
+        let github = Client::for_repo(
+            "github",
+            "https://github.com/org/repo",
+            true,
+            tokens(&[("GITHUB_TOKEN", "t0k")]),
+        )
+        .unwrap();
+        assert_eq!(
+            github.failed_runs_path("main"),
+            "/repos/org/repo/actions/runs?branch=main&status=failure&per_page=1"
+        );
+        let runs: Value = serde_json::from_str(
+            r#"{"total_count": 1, "workflow_runs": [{"html_url": "https://github.com/org/repo/actions/runs/42"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            github.first_web_url(&runs).as_deref(),
+            Some("https://github.com/org/repo/actions/runs/42")
+        );
+    }
+
+    #[test]
+    fn test_branch_prs() {
+        let github = Client::for_repo(
+            "github",
+            "https://github.com/org/repo",
+            true,
+            tokens(&[("GITHUB_TOKEN", "t0k")]),
+        )
+        .unwrap();
+        assert_eq!(
+            github.branch_prs_path("feat/x"),
+            "/repos/org/repo/pulls?head=org%3Afeat%2Fx&state=open"
+        );
+        let prs: Value = serde_json::from_str(
+            r#"[{"number": 7, "html_url": "https://github.com/org/repo/pull/7"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            github.first_web_url(&prs).as_deref(),
+            Some("https://github.com/org/repo/pull/7")
+        );
+        assert_eq!(github.first_web_url(&Value::Array(vec![])), None);
+
+        let gitlab = Client::for_repo(
+            "gitlab",
+            "https://gitlab.com/group/sub/project",
+            true,
+            tokens(&[("GITLAB_TOKEN", "t0k")]),
+        )
+        .unwrap();
+        assert_eq!(
+            gitlab.branch_prs_path("feat"),
+            "/projects/group%2Fsub%2Fproject/merge_requests?source_branch=feat&state=opened"
+        );
+        assert_eq!(
+            gitlab.releases_path(),
+            "/projects/group%2Fsub%2Fproject/releases?per_page=100"
+        );
+        let releases: Value = serde_json::from_str(
+            r#"[{"tag_name": "v2.0.0"}, {"name": "draft"}, {"tag_name": "v1.0.0"}]"#,
+        )
+        .unwrap();
+        assert_eq!(release_tags(&releases), vec!["v2.0.0", "v1.0.0"]);
+    }
+}
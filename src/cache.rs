@@ -0,0 +1,302 @@
+//! Remembers the web URL of each repository's remotes across invocations, so
+//! that editor plugins asking for many links in a row skip `git` and the ssh
+//! config for them. Also remembers the forge each probed host runs, which
+//! doesn't change.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// How many remotes, and how many probed hosts, are remembered. The most
+/// recently stored ones win.
+const MAX_ENTRIES: usize = 200;
+
+fn cache_path(name: &str) -> Option<PathBuf> {
+    let cache_dir = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?)
+            .join(".cache"),
+    };
+    Some(cache_dir.join("gitopen").join(name))
+}
+
+fn write_cache(path: &Path, text: &str) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir).and_then(|_| fs::write(path, text));
+    }
+}
+
+/// How many git config files are read for the includes they name.
+const MAX_CONFIG_FILES: usize = 100;
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// The files `include.path` and `includeIf.<condition>.path` name in the
+/// git config `text` of a file in `dir`. Whether a condition holds doesn't
+/// matter, the file may change either way.
+fn include_paths(text: &str, dir: &Path, home: Option<&Path>) -> Vec<PathBuf> {
+    let mut in_include = false;
+    let mut paths = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(section) = line.strip_prefix('[') {
+            in_include = section.to_ascii_lowercase().starts_with("include");
+            continue;
+        }
+        let value = match line.split_once('=') {
+            Some((key, value)) if in_include && key.trim().eq_ignore_ascii_case("path") => {
+                value.trim().trim_matches('"')
+            }
+            _ => continue,
+        };
+        let path = match (value.strip_prefix("~/"), home) {
+            (Some(in_home), Some(home)) => home.join(in_home),
+            _ => dir.join(value),
+        };
+        paths.push(path);
+    }
+    paths
+}
+
+/// The git config files the URL of a remote may come from: the
+/// repository's, the user's and the system's, and those they include.
+fn git_config_files(git_dir: &Path) -> Vec<PathBuf> {
+    // With `extensions.worktreeConfig`, each worktree has a config of its own.
+    let mut files = vec![git_dir.join("config"), git_dir.join("config.worktree")];
+    // Worktrees share the config of the repository they were added to.
+    if let Ok(common_dir) = fs::read_to_string(git_dir.join("commondir")) {
+        files.push(git_dir.join(common_dir.trim()).join("config"));
+    }
+    let home = home_dir();
+    if let Some(home) = &home {
+        files.push(home.join(".gitconfig"));
+        files.push(home.join(".config").join("git").join("config"));
+    }
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => files.push(PathBuf::from(dir).join("git").join("config")),
+        _ => {}
+    }
+    for var in ["GIT_CONFIG_GLOBAL", "GIT_CONFIG_SYSTEM"] {
+        match env::var_os(var) {
+            Some(file) if !file.is_empty() => files.push(PathBuf::from(file)),
+            _ => {}
+        }
+    }
+    files.push(PathBuf::from("/etc/gitconfig"));
+
+    // Each file is read once, so includes that include each other end.
+    let mut index = 0;
+    while index < files.len().min(MAX_CONFIG_FILES) {
+        let file = files[index].clone();
+        index += 1;
+        let text = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        let dir = file.parent().unwrap_or_else(|| Path::new(""));
+        for included in include_paths(&text, dir, home.as_deref()) {
+            if !files.contains(&included) {
+                files.push(included);
+            }
+        }
+    }
+    files
+}
+
+/// Changes whenever a file the URL is derived from changes: the git config
+/// files with their `insteadOf` rewrites, and the ssh config with its host
+/// aliases. `None` when git is given config in the environment, with
+/// `git -c` or `GIT_CONFIG_COUNT`, which no file tells has changed.
+fn stamp(git_dir: &Path) -> Option<u128> {
+    if ["GIT_CONFIG_PARAMETERS", "GIT_CONFIG_COUNT"]
+        .iter()
+        .any(|var| env::var_os(var).is_some())
+    {
+        return None;
+    }
+    let mut sources = git_config_files(git_dir);
+    if let Some(home) = home_dir() {
+        sources.push(home.join(".ssh").join("config"));
+    }
+    let stamp = sources
+        .iter()
+        .filter_map(|source| fs::metadata(source).and_then(|meta| meta.modified()).ok())
+        .filter_map(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_nanos())
+        .max()
+        .unwrap_or(0);
+    Some(stamp)
+}
+
+/// A cache line: git dir, remote name, stamp and URL, separated by tabs.
+#[derive(Debug, PartialEq)]
+struct Entry {
+    git_dir: String,
+    remote_name: String,
+    stamp: u128,
+    url: String,
+}
+
+impl Entry {
+    fn parse(line: &str) -> Option<Entry> {
+        let mut fields = line.splitn(4, '\t');
+        Some(Entry {
+            git_dir: fields.next()?.to_string(),
+            remote_name: fields.next()?.to_string(),
+            stamp: fields.next()?.parse().ok()?,
+            url: fields.next()?.to_string(),
+        })
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\n",
+            self.git_dir, self.remote_name, self.stamp, self.url
+        )
+    }
+
+    fn is_for(&self, git_dir: &str, remote_name: &str) -> bool {
+        self.git_dir == git_dir && self.remote_name == remote_name
+    }
+}
+
+fn read_entries() -> Vec<Entry> {
+    cache_path("remote-urls")
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|text| text.lines().filter_map(Entry::parse).collect())
+        .unwrap_or_default()
+}
+
+/// The URL stored for the remote, unless a config file changed since.
+pub fn lookup_remote_url(git_dir: &Path, remote_name: &str) -> Option<String> {
+    let git_dir_key = git_dir.to_str()?;
+    let stamp = stamp(git_dir)?;
+    read_entries()
+        .into_iter()
+        .find(|entry| entry.is_for(git_dir_key, remote_name) && entry.stamp == stamp)
+        .map(|entry| entry.url)
+}
+
+/// Stores the URL for the remote. Failing to is not worth an error, the next
+/// run just works it out again.
+pub fn store_remote_url(git_dir: &Path, remote_name: &str, url: &str) {
+    let (git_dir_key, path, stamp) =
+        match (git_dir.to_str(), cache_path("remote-urls"), stamp(git_dir)) {
+            (Some(git_dir_key), Some(path), Some(stamp)) => (git_dir_key, path, stamp),
+            _ => return,
+        };
+    if [git_dir_key, remote_name, url]
+        .iter()
+        .any(|field| field.contains(['\t', '\n']))
+    {
+        return;
+    }
+    let mut entries: Vec<Entry> = read_entries()
+        .into_iter()
+        .filter(|entry| !entry.is_for(git_dir_key, remote_name))
+        .collect();
+    entries.push(Entry {
+        git_dir: git_dir_key.to_string(),
+        remote_name: remote_name.to_string(),
+        stamp,
+        url: url.to_string(),
+    });
+    let skip = entries.len().saturating_sub(MAX_ENTRIES);
+    let text: String = entries.iter().skip(skip).map(Entry::to_line).collect();
+    write_cache(&path, &text);
+}
+
+/// What a forges cache line, `host\tprovider`, says about a host: the
+/// provider, or `None` for hosts that run none gitopen recognized (`-`).
+#[cfg(feature = "api")]
+fn parse_forge_line<'a>(line: &'a str, host: &str) -> Option<Option<&'a str>> {
+    match line.split_once('\t')? {
+        (line_host, "-") if line_host == host => Some(None),
+        (line_host, provider_name) if line_host == host => Some(Some(provider_name)),
+        _ => None,
+    }
+}
+
+/// The forge `host` was found to run, when it was probed before.
+#[cfg(feature = "api")]
+pub fn lookup_forge(host: &str) -> Option<Option<String>> {
+    let text = fs::read_to_string(cache_path("forges")?).ok()?;
+    text.lines()
+        .find_map(|line| parse_forge_line(line, host))
+        .map(|provider_name| provider_name.map(str::to_string))
+}
+
+/// Stores the forge `host` was found to run, if any.
+#[cfg(feature = "api")]
+pub fn store_forge(host: &str, provider_name: Option<&str>) {
+    let path = match cache_path("forges") {
+        Some(path) if !host.contains(['\t', '\n']) => path,
+        _ => return,
+    };
+    let mut lines: Vec<String> = fs::read_to_string(&path)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| parse_forge_line(line, host).is_none())
+        .map(|line| format!("{}\n", line))
+        .collect();
+    lines.push(format!("{}\t{}\n", host, provider_name.unwrap_or("-")));
+    let skip = lines.len().saturating_sub(MAX_ENTRIES);
+    write_cache(&path, &lines[skip..].concat());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_round_trip() {
+        let entry = Entry {
+            git_dir: "/home/me/gitopen/.git".to_string(),
+            remote_name: "origin".to_string(),
+            stamp: 1_700_000_000_123_456_789,
+            url: "https://github.com/oren0e/gitopen".to_string(),
+        };
+        assert_eq!(Entry::parse(entry.to_line().trim_end()), Some(entry));
+        assert_eq!(
+            Entry::parse("/repo/.git\torigin\tnot-a-stamp\thttps://x"),
+            None
+        );
+        assert_eq!(Entry::parse("/repo/.git\torigin"), None);
+    }
+
+    #[test]
+    fn test_include_paths() {
+        let text = "[user]\n\tname = Me\n\tpath = not/an/include\n[include]\n\tpath = ~/.gitconfig.local\n[includeIf \"gitdir:~/work/\"]\n\tPath = \"work.inc\"\n[url \"git@github.com:\"]\n\tinsteadOf = gh:\n";
+        assert_eq!(
+            include_paths(
+                text,
+                Path::new("/home/me/.config/git"),
+                Some(Path::new("/home/me"))
+            ),
+            vec![
+                PathBuf::from("/home/me/.gitconfig.local"),
+                PathBuf::from("/home/me/.config/git/work.inc"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "api")]
+    #[test]
+    fn test_parse_forge_line() {
+        assert_eq!(
+            parse_forge_line("git.corp.com\tgitlab", "git.corp.com"),
+            Some(Some("gitlab"))
+        );
+        assert_eq!(
+            parse_forge_line("git.corp.com\t-", "git.corp.com"),
+            Some(None)
+        );
+        assert_eq!(parse_forge_line("git.corp.com\tgitlab", "corp.com"), None);
+        assert_eq!(parse_forge_line("git.corp.com", "git.corp.com"), None);
+    }
+}
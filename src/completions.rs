@@ -0,0 +1,159 @@
+//! Shell completions. clap generates the subcommands and options, and the
+//! scripts here add the branches, remotes and tracked files of the
+//! repository being completed in, which they get from `gitopen complete`.
+
+use crate::actions::{get_remote_name, RemoteSelection};
+use crate::git::GitBackend;
+use crate::match_logic::branch_choices;
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+
+/// The shells `gitopen completions` prints completions for.
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell", "elvish"];
+
+/// The subcommands whose argument is a file, as `-l` and `--path-and-line`
+/// are for the top-level command.
+const FILE_SUBCOMMANDS: &[&str] = &["blame", "edit", "history", "last-change"];
+
+const BASH_COMPLETIONS: &str = r#"
+_gitopen_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}" kind=""
+    if [[ ${cur} != -* ]]; then
+        case "${prev}" in
+            --remote) kind="remotes" ;;
+            branch) kind="branches" ;;
+            blame|edit|history|last-change|-l|--path-and-line) kind="files" ;;
+        esac
+    fi
+    if [[ -n ${kind} ]]; then
+        COMPREPLY=( $(compgen -W "$(gitopen complete ${kind} 2>/dev/null)" -- "${cur}") )
+        return 0
+    fi
+    _gitopen "$@"
+}
+complete -F _gitopen_dynamic -o bashdefault -o default gitopen
+"#;
+
+const ZSH_COMPLETIONS: &str = r#"_gitopen_remotes() {
+    local remotes; remotes=(${(f)"$(gitopen complete remotes 2>/dev/null)"})
+    _describe -t remotes 'remote' remotes
+}
+_gitopen_branches() {
+    local branches; branches=(${(f)"$(gitopen complete branches 2>/dev/null)"})
+    _describe -t branches 'branch' branches
+}
+_gitopen_files() {
+    local files; files=(${(f)"$(gitopen complete files 2>/dev/null)"})
+    _multi_parts / files
+}
+
+"#;
+
+const FISH_COMPLETIONS: &str = r#"
+complete -c gitopen -l remote -x -a "(gitopen complete remotes 2>/dev/null)"
+complete -c gitopen -n "__fish_seen_subcommand_from branch" -f -a "(gitopen complete branches 2>/dev/null)"
+complete -c gitopen -n "__fish_seen_subcommand_from blame edit history last-change" -f -a "(gitopen complete files 2>/dev/null)"
+complete -c gitopen -n "__fish_use_subcommand" -s l -l path-and-line -x -a "(gitopen complete files 2>/dev/null)"
+"#;
+
+/// The completions clap `generated` for `shell`, with the branches,
+/// remotes and files completed from the repository in bash, zsh and fish.
+pub fn with_dynamic_completions(shell: &str, generated: &str) -> String {
+    match shell {
+        "bash" => format!("{}{}", generated, BASH_COMPLETIONS),
+        "zsh" => with_zsh_completions(generated),
+        "fish" => format!("{}{}", generated, FISH_COMPLETIONS),
+        _ => generated.to_string(),
+    }
+}
+
+/// Gives the `_arguments` specs of zsh the functions completing remotes,
+/// branches and files, defined before the script calls `_gitopen`.
+fn with_zsh_completions(generated: &str) -> String {
+    let mut subcommand = "";
+    let mut lines: Vec<String> = Vec::new();
+    for line in generated.lines() {
+        if line.starts_with('(') && line.ends_with(')') {
+            subcommand = line.trim_start_matches('(').trim_end_matches(')');
+        }
+        let completed_with = if line.starts_with("'--remote=[") {
+            Some("_gitopen_remotes")
+        } else if line.starts_with("'-l+[") || line.starts_with("'--path-and-line=[") {
+            Some("_gitopen_files")
+        } else if subcommand == "branch" && line.starts_with("'::NAME") {
+            Some("_gitopen_branches")
+        } else if FILE_SUBCOMMANDS.contains(&subcommand) && line.starts_with("':PATH") {
+            Some("_gitopen_files")
+        } else {
+            None
+        };
+        let line = match (completed_with, line.strip_suffix("' \\")) {
+            (Some(function), Some(spec)) if spec.ends_with(":_files") => {
+                format!("{}:{}' \\", spec.trim_end_matches(":_files"), function)
+            }
+            (Some(function), Some(spec)) => format!("{}: :{}' \\", spec, function),
+            _ => line.to_string(),
+        };
+        if line == "_gitopen \"$@\"" {
+            lines.push(ZSH_COMPLETIONS.to_string());
+        }
+        lines.push(line);
+    }
+    lines.join("\n") + "\n"
+}
+
+/// What the completions offer for `kind`: the local branches and those of
+/// the remote, the remotes, or the files git tracks.
+pub fn completion_candidates(git: &dyn GitBackend, kind: &str) -> AnyhowResult<Vec<String>> {
+    match kind {
+        "branches" => {
+            let remote = RemoteSelection {
+                name: None,
+                interactive: false,
+            };
+            let remote_name = get_remote_name(git, remote)?;
+            Ok(branch_choices(&git.branches()?, &remote_name))
+        }
+        "remotes" => git.remotes(),
+        "files" => git.tracked_files(),
+        _ => Err(anyhow!("gitopen doesn't complete '{}'", kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::FakeGit;
+
+    #[test]
+    fn test_completion_candidates() {
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", "abc123");
+        git.revs
+            .insert("refs/remotes/origin/feat".to_string(), "def456".to_string());
+        git.files = vec!["src/main.rs".to_string()];
+        assert_eq!(
+            completion_candidates(&git, "branches").unwrap(),
+            vec!["feat", "main"]
+        );
+        assert_eq!(
+            completion_candidates(&git, "remotes").unwrap(),
+            vec!["origin"]
+        );
+        assert_eq!(
+            completion_candidates(&git, "files").unwrap(),
+            vec!["src/main.rs"]
+        );
+        assert!(completion_candidates(&git, "tags").is_err());
+    }
+
+    #[test]
+    fn test_zsh_completions() {
+        let generated = "_gitopen() {\n'--remote=[The remote]' \\\n(branch)\n'::NAME -- The branch:_files' \\\n(history)\n':PATH:_files' \\\n}\n\n_gitopen \"$@\"";
+        let completions = with_dynamic_completions("zsh", generated);
+        assert!(completions.contains("'--remote=[The remote]: :_gitopen_remotes' \\\n"));
+        assert!(completions.contains("'::NAME -- The branch:_gitopen_branches' \\\n"));
+        assert!(completions.contains("':PATH:_gitopen_files' \\\n"));
+        assert!(completions.ends_with("_gitopen_files() {\n    local files; files=(${(f)\"$(gitopen complete files 2>/dev/null)\"})\n    _multi_parts / files\n}\n\n\n_gitopen \"$@\"\n"));
+        assert_eq!(with_dynamic_completions("elvish", generated), generated);
+    }
+}
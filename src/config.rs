@@ -0,0 +1,373 @@
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// URL templates for a host none of the built-in providers fit, e.g. cgit or
+/// an internal forge. The placeholders `{host}`, `{repo}`, `{branch}`,
+/// `{path}`, `{line}`, `{end_line}` and `{sha}` are filled in per link.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct UrlTemplates {
+    pub repo: Option<String>,
+    pub commit: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<String>,
+    pub tree: Option<String>,
+    /// Templates of pages this version doesn't build, which a checked-in
+    /// `.gitopen.toml` written for a newer one may have.
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+impl UrlTemplates {
+    /// Fills the templates missing here from `other`.
+    pub fn or(&self, other: &UrlTemplates) -> UrlTemplates {
+        UrlTemplates {
+            repo: self.repo.clone().or_else(|| other.repo.clone()),
+            commit: self.commit.clone().or_else(|| other.commit.clone()),
+            file: self.file.clone().or_else(|| other.file.clone()),
+            line: self.line.clone().or_else(|| other.line.clone()),
+            tree: self.tree.clone().or_else(|| other.tree.clone()),
+            unknown: BTreeMap::new(),
+        }
+    }
+
+    /// The templates that are set, by their names.
+    pub fn entries(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("repo", &self.repo),
+            ("commit", &self.commit),
+            ("file", &self.file),
+            ("line", &self.line),
+            ("tree", &self.tree),
+        ]
+        .iter()
+        .filter_map(|&(name, template)| Some((name, template.as_deref()?)))
+        .collect()
+    }
+
+    fn set(&mut self, name: &str, template: &str) {
+        let slot = match name {
+            "repo" => &mut self.repo,
+            "commit" => &mut self.commit,
+            "file" => &mut self.file,
+            "line" => &mut self.line,
+            "tree" => &mut self.tree,
+            _ => {
+                self.unknown
+                    .insert(name.to_string(), toml::Value::String(template.to_string()));
+                return;
+            }
+        };
+        *slot = Some(template.to_string());
+    }
+}
+
+/// Settings for a single repository, from a checked-in `.gitopen.toml` and
+/// the `gitopen.*` git config values, which win over the file. Keys that
+/// only newer versions know are kept apart, to be warned about rather than
+/// fail every command.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct RepoConfig {
+    pub remote: Option<String>,
+    pub provider: Option<String>,
+    pub default_branch: Option<String>,
+    pub templates: Option<UrlTemplates>,
+    /// Whether `gitopen -p` sets the upstream of branches that have none,
+    /// which it does unless this is `false`.
+    pub set_upstream: Option<bool>,
+    /// Only read from git config: a checked-in file must not pick a command
+    /// gitopen runs.
+    #[serde(skip)]
+    pub browser: Option<String>,
+    /// What bare `gitopen` opens, e.g. `branch`.
+    pub default_action: Option<String>,
+    /// What `gitopen <path>` opens for a file without a line, e.g. `blame`.
+    pub file_action: Option<String>,
+    /// Also only from git config, as it is how one person likes links
+    /// printed.
+    #[serde(skip)]
+    pub format: Option<String>,
+    /// The remote picked when gitopen asked, kept in git config. Unlike
+    /// `remote`, it only applies to branches without a remote of their own.
+    #[serde(skip)]
+    pub picked_remote: Option<String>,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+    /// The `gitopen.*` git config keys this version doesn't know.
+    #[serde(skip)]
+    unknown_git_keys: Vec<String>,
+}
+
+impl RepoConfig {
+    pub fn parse(text: &str) -> AnyhowResult<RepoConfig> {
+        let repo_config: RepoConfig = toml::from_str(text).map_err(|err| anyhow!("{}", err))?;
+        for key in ["browser", "format"] {
+            if repo_config.unknown.contains_key(key) {
+                return Err(anyhow!(
+                    "'{}' is only read from git config, set it with `git config gitopen.{}`",
+                    key,
+                    key
+                ));
+            }
+        }
+        Ok(repo_config)
+    }
+
+    /// The keys of `.gitopen.toml` and the `gitopen.*` git config this
+    /// version of gitopen doesn't know, and ignores.
+    pub fn unknown_keys(&self) -> Vec<String> {
+        let templates = self
+            .templates
+            .iter()
+            .flat_map(|templates| templates.unknown.keys());
+        self.unknown
+            .keys()
+            .cloned()
+            .chain(templates.map(|name| format!("templates.{}", name)))
+            .chain(self.unknown_git_keys.iter().cloned())
+            .collect()
+    }
+
+    /// Applies the output of `git config --get-regexp '^gitopen\.'`, e.g.
+    /// `gitopen.remote upstream` or `gitopen.template.commit https://...`.
+    pub fn apply_git_config(&mut self, git_config: &str) -> AnyhowResult<()> {
+        for line in git_config.lines() {
+            let (key, value) = match line.split_once(' ') {
+                Some((key, value)) => (key, value.trim()),
+                None => continue,
+            };
+            match key.strip_prefix("gitopen.") {
+                Some("remote") => self.remote = Some(value.to_string()),
+                Some("provider") => self.provider = Some(value.to_string()),
+                Some("defaultbranch") => self.default_branch = Some(value.to_string()),
+                Some("browser") => self.browser = Some(value.to_string()),
+                Some("format") => self.format = Some(value.to_string()),
+                Some("pickedremote") => self.picked_remote = Some(value.to_string()),
+                Some("defaultaction") => self.default_action = Some(value.to_string()),
+                Some("fileaction") => self.file_action = Some(value.to_string()),
+                Some("setupstream") => self.set_upstream = Some(parse_git_bool(key, value)?),
+                Some(other) => match other.strip_prefix("template.") {
+                    Some(name) => self
+                        .templates
+                        .get_or_insert_with(UrlTemplates::default)
+                        .set(name, value),
+                    None => self.unknown_git_keys.push(key.to_string()),
+                },
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a boolean the way git does, e.g. `true`, `no` or `1`.
+fn parse_git_bool(key: &str, value: &str) -> AnyhowResult<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" | "" => Ok(false),
+        _ => Err(anyhow!(
+            "Invalid boolean '{}' for git config key '{}'",
+            value,
+            key
+        )),
+    }
+}
+
+/// The contents of `~/.config/gitopen/config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The command that opens URLs, e.g. `firefox` or `chromium --incognito`.
+    pub browser: Option<String>,
+    /// The remote opened in repositories that have it, unless the branch
+    /// tracks another one, e.g. `upstream` for those who work on forks.
+    pub remote: Option<String>,
+    /// How links are printed without `--format`, e.g. `markdown`.
+    pub format: Option<String>,
+    /// What bare `gitopen` opens: `repo` (the default), `branch`, `pr` or
+    /// `commit`.
+    pub default_action: Option<String>,
+    /// What `gitopen <path>` opens for a file without a line: `blob` (the
+    /// default), `blame` or `history`.
+    pub file_action: Option<String>,
+    /// The URL prefix of the web editor `gitopen edit` opens file pages in,
+    /// e.g. `https://gitpod.io/#`, instead of the provider's own.
+    pub editor: Option<String>,
+    /// The Sourcegraph instance `--sourcegraph` links to, e.g.
+    /// `https://sourcegraph.company.com`.
+    pub sourcegraph: Option<String>,
+    #[serde(default)]
+    pub hosts: HashMap<String, UrlTemplates>,
+    /// The provider of hosts that aren't recognized by their name, e.g.
+    /// `"git.company.com" = "cgit"`.
+    #[serde(default)]
+    pub providers: HashMap<String, String>,
+    /// The `gitopen-<name>` executables that build the URLs of hosts, e.g.
+    /// `"code.company.com" = "corpbrowse"`.
+    #[serde(default)]
+    pub plugins: HashMap<String, String>,
+    /// Whether hosts that are neither recognized nor in `providers` are
+    /// asked which forge they run, in builds with the `api` feature.
+    #[serde(default)]
+    pub probe: bool,
+    /// How many seconds a git command may take before gitopen stops it, 30
+    /// by default.
+    pub git_timeout: Option<u64>,
+    /// The same for `git push`, which may wait for credentials to be typed
+    /// in, 300 by default.
+    pub push_timeout: Option<u64>,
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?)
+            .join(".config"),
+    };
+    Some(config_dir.join("gitopen").join("config.toml"))
+}
+
+impl Config {
+    pub fn parse(text: &str) -> AnyhowResult<Config> {
+        let config: Config = toml::from_str(text).map_err(|err| anyhow!("{}", err))?;
+        // Unlike `.gitopen.toml`, the file is only read by the gitopen it
+        // was written for, so a misspelled template is an error.
+        for (host, templates) in &config.hosts {
+            if let Some(name) = templates.unknown.keys().next() {
+                return Err(anyhow!("Unknown URL template '{}' for {}", name, host));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Reads the config file. A missing file is the same as an empty one.
+    pub fn load() -> AnyhowResult<Config> {
+        let path = match config_path() {
+            Some(path) if path.is_file() => path,
+            _ => return Ok(Config::default()),
+        };
+        let text = fs::read_to_string(&path)?;
+        Config::parse(&text).map_err(|err| anyhow!("Invalid config {}: {}", path.display(), err))
+    }
+
+    pub fn templates_for_host(&self, host: &str) -> Option<&UrlTemplates> {
+        self.hosts.get(host)
+    }
+
+    pub fn provider_for_host(&self, host: &str) -> Option<&str> {
+        self.providers.get(host).map(String::as_str)
+    }
+
+    pub fn plugin_for_host(&self, host: &str) -> Option<&str> {
+        self.plugins.get(host).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+[hosts."git.corp.com"]
+commit = "https://{host}/cgit/{repo}/commit/?id={sha}"
+line = "https://{host}/cgit/{repo}/tree/{path}?h={branch}#n{line}"
+"#,
+        )
+        .unwrap();
+        let templates = config.templates_for_host("git.corp.com").unwrap();
+        assert_eq!(
+            templates.commit.as_deref(),
+            Some("https://{host}/cgit/{repo}/commit/?id={sha}")
+        );
+        assert_eq!(templates.file, None);
+        assert!(config.templates_for_host("github.com").is_none());
+    }
+
+    #[test]
+    fn test_parse_config_rejects_unknown_templates() {
+        assert!(Config::parse("[hosts.\"git.corp.com\"]\nblob = \"x\"\n").is_err());
+        assert!(Config::parse("").unwrap().hosts.is_empty());
+        assert!(!Config::parse("").unwrap().probe);
+        assert!(Config::parse("probe = true\n").unwrap().probe);
+        assert_eq!(
+            Config::parse("editor = \"https://gitpod.io/#\"\n")
+                .unwrap()
+                .editor
+                .as_deref(),
+            Some("https://gitpod.io/#")
+        );
+    }
+
+    #[test]
+    fn test_repo_config() {
+        let mut repo_config = RepoConfig::parse(
+            r#"
+remote = "upstream"
+default-branch = "trunk"
+
+[templates]
+commit = "https://mirror/{repo}/c/{sha}"
+"#,
+        )
+        .unwrap();
+        repo_config
+            .apply_git_config(
+                "gitopen.remote fork\ngitopen.provider gitlab\ngitopen.template.line https://mirror/{path}#{line}\n",
+            )
+            .unwrap();
+        assert_eq!(repo_config.remote.as_deref(), Some("fork"));
+        assert_eq!(repo_config.provider.as_deref(), Some("gitlab"));
+        assert_eq!(repo_config.default_branch.as_deref(), Some("trunk"));
+        let templates = repo_config.templates.unwrap();
+        assert_eq!(
+            templates.commit.as_deref(),
+            Some("https://mirror/{repo}/c/{sha}")
+        );
+        assert_eq!(
+            templates.line.as_deref(),
+            Some("https://mirror/{path}#{line}")
+        );
+        // Keys of newer versions are ignored, but a checked-in file still
+        // can't pick the browser.
+        let mut repo_config = RepoConfig::parse(
+            "colour = \"always\"\nremote = \"upstream\"\n[templates]\nblame = \"https://mirror/{path}\"\n",
+        )
+        .unwrap();
+        repo_config
+            .apply_git_config("gitopen.colour always\ngitopen.template.history x\n")
+            .unwrap();
+        assert_eq!(repo_config.remote.as_deref(), Some("upstream"));
+        assert_eq!(
+            repo_config.unknown_keys(),
+            [
+                "colour",
+                "templates.blame",
+                "templates.history",
+                "gitopen.colour"
+            ]
+        );
+        assert!(RepoConfig::parse("browser = \"sh -c evil\"\n").is_err());
+        assert_eq!(
+            RepoConfig::parse("set-upstream = false\n")
+                .unwrap()
+                .set_upstream,
+            Some(false)
+        );
+        let mut repo_config = RepoConfig::default();
+        repo_config
+            .apply_git_config("gitopen.setupstream no\n")
+            .unwrap();
+        assert_eq!(repo_config.set_upstream, Some(false));
+        assert!(repo_config
+            .apply_git_config("gitopen.setupstream maybe\n")
+            .is_err());
+    }
+}
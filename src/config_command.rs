@@ -0,0 +1,514 @@
+//! `gitopen config`: reads and writes the settings of the config file and
+//! the `gitopen.*` git config of a repository, so neither has to be edited
+//! by hand, and lists the settings they add up to.
+
+use crate::actions::{get_repo_config, DefaultAction, FileAction};
+use crate::config::{config_path, Config, RepoConfig};
+use crate::git::GitBackend;
+use crate::output::Format;
+use crate::providers::provider_by_name;
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+use std::fs;
+use std::path::PathBuf;
+use toml_edit::{value, DocumentMut, Item, Table, TableLike};
+
+/// Where `gitopen config` reads and writes a setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// The config file, `~/.config/gitopen/config.toml`.
+    Global,
+    /// The `gitopen.*` values of the repository's git config, which win
+    /// over a checked-in `.gitopen.toml` and the config file.
+    Repo,
+}
+
+/// The keys of the config file with a single value.
+const GLOBAL_KEYS: &[&str] = &[
+    "browser",
+    "default_action",
+    "editor",
+    "file_action",
+    "format",
+    "git_timeout",
+    "probe",
+    "push_timeout",
+    "remote",
+    "sourcegraph",
+];
+
+/// The keys of the repository's settings, named as in `.gitopen.toml`.
+const REPO_KEYS: &[&str] = &[
+    "browser",
+    "default-action",
+    "default-branch",
+    "file-action",
+    "format",
+    "provider",
+    "remote",
+    "set-upstream",
+];
+
+const TEMPLATES: &[&str] = &["repo", "commit", "file", "line", "tree"];
+
+/// The path of tables to `key` in the config file, where the host of
+/// `hosts.<host>.<template>` and `providers.<host>` may have dots of its own.
+fn global_key_path(key: &str) -> AnyhowResult<Vec<&str>> {
+    if GLOBAL_KEYS.contains(&key) {
+        return Ok(vec![key]);
+    }
+    let unknown = || {
+        if REPO_KEYS.contains(&key) || key.starts_with("templates.") {
+            anyhow!("'{}' is set per repository, add --repo", key)
+        } else {
+            anyhow!("Unknown config key '{}'", key)
+        }
+    };
+    match key.split_once('.').ok_or_else(unknown)? {
+        ("providers", host) | ("plugins", host) if !host.is_empty() => {
+            Ok(vec![&key[..key.len() - host.len() - 1], host])
+        }
+        ("hosts", rest) => match rest.rsplit_once('.') {
+            Some((host, template)) if !host.is_empty() && TEMPLATES.contains(&template) => {
+                Ok(vec!["hosts", host, template])
+            }
+            _ => Err(anyhow!(
+                "URL templates of a host are set as hosts.<host>.<{}>",
+                TEMPLATES.join("|")
+            )),
+        },
+        _ => Err(unknown()),
+    }
+}
+
+/// The git config key a repository's setting is kept under, e.g.
+/// `gitopen.defaultbranch` for `default-branch`.
+fn repo_git_key(key: &str) -> AnyhowResult<String> {
+    match key.strip_prefix("templates.") {
+        Some(template) if TEMPLATES.contains(&template) => {
+            Ok(format!("gitopen.template.{}", template))
+        }
+        Some(_) => Err(anyhow!(
+            "URL templates are set as templates.<{}>",
+            TEMPLATES.join("|")
+        )),
+        None if REPO_KEYS.contains(&key) => Ok(format!("gitopen.{}", key.replace('-', ""))),
+        None if global_key_path(key).is_ok() => Err(anyhow!(
+            "'{}' is only set in the config file, leave out --repo",
+            key
+        )),
+        None => Err(anyhow!("Unknown config key '{}'", key)),
+    }
+}
+
+/// The name of a repository's setting for the git config key it is kept
+/// under, the other way around from `repo_git_key`.
+fn repo_key(git_key: &str) -> String {
+    let name = git_key.trim_start_matches("gitopen.");
+    if let Some(template) = name.strip_prefix("template.") {
+        return format!("templates.{}", template);
+    }
+    REPO_KEYS
+        .iter()
+        .find(|key| key.replace('-', "") == name)
+        .map_or_else(|| name.to_string(), |key| key.to_string())
+}
+
+/// Rejects the values gitopen would only fail on later.
+fn check_value(key: &str, value: &str) -> AnyhowResult<()> {
+    if key == "format" {
+        Format::from_name(value)?;
+    } else if key == "default-action" || key == "default_action" {
+        DefaultAction::from_name(value)?;
+    } else if key == "file-action" || key == "file_action" {
+        FileAction::from_name(value)?;
+    } else if key == "provider" || key.starts_with("providers.") {
+        provider_by_name(value)?;
+    }
+    Ok(())
+}
+
+/// `text` as the TOML value of `key`, which is a string for most keys.
+fn toml_value(key: &str, text: &str) -> AnyhowResult<Item> {
+    match key {
+        "probe" => match text {
+            "true" => Ok(value(true)),
+            "false" => Ok(value(false)),
+            _ => Err(anyhow!("'probe' is true or false, not '{}'", text)),
+        },
+        "git_timeout" | "push_timeout" => match text.parse::<u32>() {
+            Ok(seconds) => Ok(value(i64::from(seconds))),
+            Err(_) => Err(anyhow!("'{}' is a number of seconds, not '{}'", key, text)),
+        },
+        _ => Ok(value(text)),
+    }
+}
+
+/// The config file `text` with `key` set to `new_value`, keeping its
+/// comments and layout.
+fn set_in_toml(text: &str, key: &str, new_value: &str) -> AnyhowResult<String> {
+    let path = global_key_path(key)?;
+    check_value(key, new_value)?;
+    let mut document: DocumentMut = text.parse().map_err(|err| anyhow!("{}", err))?;
+    let (name, tables) = path.split_last().expect("keys have a name");
+    let mut table: &mut dyn TableLike = document.as_table_mut();
+    for table_name in tables {
+        table = table
+            .entry(table_name)
+            .or_insert_with(|| {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            })
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow!("'{}' is not a table in the config file", table_name))?;
+    }
+    // Assigned in place, the key keeps the comments above it.
+    match table.get_mut(name) {
+        Some(item) => *item = toml_value(key, new_value)?,
+        None => {
+            table.insert(name, toml_value(key, new_value)?);
+        }
+    }
+    let text = document.to_string();
+    Config::parse(&text)?;
+    Ok(text)
+}
+
+/// A TOML value the way `gitopen config get` prints it, strings without
+/// their quotes.
+fn value_text(item: &Item) -> Option<String> {
+    let toml_value = item.as_value()?;
+    Some(match toml_value.as_str() {
+        Some(text) => text.to_string(),
+        None => toml_value.to_string().trim().to_string(),
+    })
+}
+
+fn get_from_toml(text: &str, key: &str) -> AnyhowResult<Option<String>> {
+    let path = global_key_path(key)?;
+    let document: DocumentMut = text.parse().map_err(|err| anyhow!("{}", err))?;
+    let mut item = document.as_item();
+    for name in path {
+        item = match item.get(name) {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+    }
+    Ok(value_text(item))
+}
+
+/// Every value of the config file `text`, by its `gitopen config` key.
+fn toml_entries(text: &str) -> AnyhowResult<Vec<(String, String)>> {
+    fn collect(prefix: &str, table: &dyn TableLike, entries: &mut Vec<(String, String)>) {
+        for (name, item) in table.iter() {
+            let key = if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", prefix, name)
+            };
+            match item.as_table_like() {
+                Some(table) => collect(&key, table, entries),
+                None => entries.extend(value_text(item).map(|text| (key, text))),
+            }
+        }
+    }
+    let document: DocumentMut = text.parse().map_err(|err| anyhow!("{}", err))?;
+    let mut entries = Vec::new();
+    collect("", document.as_table(), &mut entries);
+    Ok(entries)
+}
+
+/// The settings of a repository in the output of `git config --get-regexp
+/// '^gitopen\.'`.
+fn repo_entries(git_config: &str) -> Vec<(String, String)> {
+    git_config
+        .lines()
+        .filter_map(|line| {
+            let (git_key, value) = line.split_once(' ')?;
+            Some((repo_key(git_key), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// The settings gitopen goes by, the repository's over the config file's.
+fn effective_settings(config: &Config, repo_config: &RepoConfig) -> Vec<(String, String)> {
+    let either = |repo: &Option<String>, global: &Option<String>| repo.clone().or(global.clone());
+    let mut settings: Vec<(String, Option<String>)> = vec![
+        (
+            "browser".to_string(),
+            either(&repo_config.browser, &config.browser),
+        ),
+        (
+            "default-action".to_string(),
+            either(&repo_config.default_action, &config.default_action),
+        ),
+        (
+            "default-branch".to_string(),
+            repo_config.default_branch.clone(),
+        ),
+        ("editor".to_string(), config.editor.clone()),
+        (
+            "file-action".to_string(),
+            either(&repo_config.file_action, &config.file_action),
+        ),
+        (
+            "format".to_string(),
+            either(&repo_config.format, &config.format),
+        ),
+        (
+            "git_timeout".to_string(),
+            config.git_timeout.map(|seconds| seconds.to_string()),
+        ),
+        (
+            "probe".to_string(),
+            config.probe.then(|| "true".to_string()),
+        ),
+        ("provider".to_string(), repo_config.provider.clone()),
+        (
+            "push_timeout".to_string(),
+            config.push_timeout.map(|seconds| seconds.to_string()),
+        ),
+        (
+            "remote".to_string(),
+            either(&repo_config.remote, &config.remote),
+        ),
+        (
+            "set-upstream".to_string(),
+            repo_config.set_upstream.map(|set| set.to_string()),
+        ),
+        ("sourcegraph".to_string(), config.sourcegraph.clone()),
+    ];
+    if let Some(templates) = &repo_config.templates {
+        for (name, template) in templates.entries() {
+            settings.push((format!("templates.{}", name), Some(template.to_string())));
+        }
+    }
+    let mut by_host: Vec<(String, Option<String>)> = Vec::new();
+    for (host, templates) in &config.hosts {
+        for (name, template) in templates.entries() {
+            by_host.push((
+                format!("hosts.{}.{}", host, name),
+                Some(template.to_string()),
+            ));
+        }
+    }
+    for (table, values) in [
+        ("plugins", &config.plugins),
+        ("providers", &config.providers),
+    ] {
+        for (host, value) in values {
+            by_host.push((format!("{}.{}", table, host), Some(value.clone())));
+        }
+    }
+    by_host.sort();
+    settings.extend(by_host);
+    settings
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+}
+
+/// The path of the config file and what it holds, nothing if it doesn't
+/// exist yet.
+fn read_config_file() -> AnyhowResult<(PathBuf, String)> {
+    let path = config_path().ok_or_else(|| {
+        anyhow!("Neither $XDG_CONFIG_HOME nor $HOME is set to find the config file in")
+    })?;
+    let text = if path.is_file() {
+        fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+    Ok((path, text))
+}
+
+/// Prints the value of `key` in `scope`, or without one the value gitopen
+/// goes by.
+pub fn get_setting(git: &dyn GitBackend, key: &str, scope: Option<Scope>) -> AnyhowResult<()> {
+    let setting = match scope {
+        Some(Scope::Global) => get_from_toml(&read_config_file()?.1, key)?,
+        Some(Scope::Repo) => git.config_get(&repo_git_key(key)?)?,
+        None => {
+            if global_key_path(key).is_err() {
+                repo_git_key(key)?;
+            }
+            list(git, None)?
+                .into_iter()
+                // In both spellings of the keys the files share.
+                .find(|(name, _)| *name == key.replace('_', "-") || name == key)
+                .map(|(_, value)| value)
+        }
+    };
+    match setting {
+        Some(setting) => {
+            println!("{}", setting);
+            Ok(())
+        }
+        None => Err(anyhow!("'{}' is not set", key)),
+    }
+}
+
+/// Sets `key` to `value` in `scope`, after checking gitopen can use it.
+pub fn set_setting(
+    git: &dyn GitBackend,
+    key: &str,
+    value: &str,
+    scope: Scope,
+    dry_run: bool,
+) -> AnyhowResult<()> {
+    match scope {
+        Scope::Global => {
+            let (path, text) = read_config_file()?;
+            let text = set_in_toml(&text, key, value)
+                .map_err(|err| anyhow!("Could not set '{}' in {}: {}", key, path.display(), err))?;
+            if dry_run {
+                println!("would write {}:\n{}", path.display(), text);
+                return Ok(());
+            }
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, text)?;
+        }
+        Scope::Repo => {
+            let git_key = repo_git_key(key)?;
+            check_value(key, value)?;
+            RepoConfig::default().apply_git_config(&format!("{} {}", git_key, value))?;
+            git.config_set(&git_key, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn list(git: &dyn GitBackend, scope: Option<Scope>) -> AnyhowResult<Vec<(String, String)>> {
+    match scope {
+        Some(Scope::Global) => toml_entries(&read_config_file()?.1),
+        Some(Scope::Repo) => Ok(repo_entries(&git.config_get_regexp(r"^gitopen\.")?)),
+        None => Ok(effective_settings(
+            &git.user_config()?,
+            &get_repo_config(git)?,
+        )),
+    }
+}
+
+/// Prints the settings of `scope`, or without one all that gitopen goes
+/// by.
+pub fn list_settings(git: &dyn GitBackend, scope: Option<Scope>) -> AnyhowResult<()> {
+    for (key, value) in list(git, scope)? {
+        println!("{} = {}", key, value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::FakeGit;
+
+    #[test]
+    fn test_set_in_toml() {
+        let text = "# Set by hand.\nbrowser = \"firefox\"\n";
+        let text =
+            set_in_toml(text, "hosts.git.corp.com.commit", "https://{host}/c/{sha}").unwrap();
+        let text = set_in_toml(&text, "providers.git.corp.com", "gitlab").unwrap();
+        let text = set_in_toml(&text, "probe", "true").unwrap();
+        let text = set_in_toml(&text, "browser", "chromium --incognito").unwrap();
+        assert_eq!(
+            text,
+            "# Set by hand.\nbrowser = \"chromium --incognito\"\nprobe = true\n\n[hosts.\"git.corp.com\"]\ncommit = \"https://{host}/c/{sha}\"\n\n[providers]\n\"git.corp.com\" = \"gitlab\"\n"
+        );
+        let config = Config::parse(&text).unwrap();
+        assert_eq!(config.provider_for_host("git.corp.com"), Some("gitlab"));
+        assert_eq!(
+            get_from_toml(&text, "hosts.git.corp.com.commit")
+                .unwrap()
+                .as_deref(),
+            Some("https://{host}/c/{sha}")
+        );
+        assert_eq!(
+            get_from_toml(&text, "probe").unwrap().as_deref(),
+            Some("true")
+        );
+        assert_eq!(get_from_toml(&text, "editor").unwrap(), None);
+        assert_eq!(
+            toml_entries(&text).unwrap()[2],
+            (
+                "hosts.git.corp.com.commit".to_string(),
+                "https://{host}/c/{sha}".to_string()
+            )
+        );
+
+        assert!(set_in_toml("", "probe", "maybe").is_err());
+        assert!(set_in_toml("", "git_timeout", "-1").is_err());
+        assert!(set_in_toml("", "format", "pdf").is_err());
+        assert!(set_in_toml("", "providers.git.corp.com", "svn").is_err());
+        assert!(set_in_toml("", "hosts.git.corp.com.blob", "x").is_err());
+        assert_eq!(
+            set_in_toml("", "default-branch", "trunk")
+                .unwrap_err()
+                .to_string(),
+            "'default-branch' is set per repository, add --repo"
+        );
+    }
+
+    #[test]
+    fn test_repo_settings() {
+        let git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", "abc123");
+        set_setting(&git, "default-branch", "trunk", Scope::Repo, false).unwrap();
+        set_setting(
+            &git,
+            "templates.line",
+            "https://m/{path}#{line}",
+            Scope::Repo,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            git.config_get("gitopen.defaultbranch").unwrap().as_deref(),
+            Some("trunk")
+        );
+        assert!(set_setting(&git, "set-upstream", "maybe", Scope::Repo, false).is_err());
+        assert_eq!(
+            set_setting(&git, "probe", "true", Scope::Repo, false)
+                .unwrap_err()
+                .to_string(),
+            "'probe' is only set in the config file, leave out --repo"
+        );
+        assert_eq!(
+            list(&git, Some(Scope::Repo)).unwrap(),
+            vec![
+                ("default-branch".to_string(), "trunk".to_string()),
+                (
+                    "templates.line".to_string(),
+                    "https://m/{path}#{line}".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_effective_settings() {
+        let config = Config::parse(
+            "browser = \"firefox\"\nremote = \"upstream\"\n[providers]\n\"git.corp.com\" = \"gitlab\"\n",
+        )
+        .unwrap();
+        let mut repo_config = RepoConfig::default();
+        repo_config
+            .apply_git_config("gitopen.browser chromium\ngitopen.format markdown\n")
+            .unwrap();
+        let settings: Vec<String> = effective_settings(&config, &repo_config)
+            .into_iter()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect();
+        assert_eq!(
+            settings,
+            vec![
+                "browser = chromium",
+                "format = markdown",
+                "remote = upstream",
+                "providers.git.corp.com = gitlab"
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::actions::{
+    get_link_target, get_parsed_url, get_provider, get_remote_name, get_toplevel,
+    resolve_commit_sha, LinkOptions, LinkTarget, RemoteSelection,
+};
+use crate::git::SystemGit;
+use crate::match_logic::{
+    get_commit_link, get_line_number_link, get_tree_link, normalize_repo_path, resolve_repo_path,
+    FileAtLine,
+};
+use crate::providers::{provider_for_url, GitRef, Provider};
+use anyhow::Result as AnyhowResult;
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+/// A web URL built by a `UrlBuilder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url(String);
+
+impl Url {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A repository's web location: the remote's URL, its provider and the ref
+/// file links point at.
+pub struct RepoContext {
+    repo_url: String,
+    provider: Box<dyn Provider>,
+    link_target: LinkTarget,
+    toplevel: Option<PathBuf>,
+}
+
+impl RepoContext {
+    /// Finds the repository of the current directory, and the remote and
+    /// branch to link to, like `gitopen` does without `--remote`. git is
+    /// asked for its config and for HEAD once each, and for the remote's URL
+    /// when it isn't cached.
+    pub fn discover() -> AnyhowResult<RepoContext> {
+        RepoContext::discover_remote(None)
+    }
+
+    /// Like `discover`, but for the given remote when there is one.
+    pub fn discover_remote(remote_name: Option<&str>) -> AnyhowResult<RepoContext> {
+        let git = SystemGit;
+        let remote_name = get_remote_name(
+            &git,
+            RemoteSelection {
+                name: remote_name,
+                interactive: false,
+            },
+        )?;
+        let link_target = get_link_target(&git, &remote_name, LinkOptions::default())?;
+        let repo_url = get_parsed_url(&git, remote_name)?;
+        Ok(RepoContext {
+            provider: get_provider(&git, &repo_url)?,
+            repo_url,
+            link_target,
+            toplevel: get_toplevel(&git).ok(),
+        })
+    }
+
+    /// A repository at a web URL without looking at any git checkout, so file
+    /// paths are taken relative to the repository root.
+    pub fn from_parts(repo_url: &str, branch: &str) -> RepoContext {
+        RepoContext {
+            repo_url: repo_url.to_string(),
+            provider: provider_for_url(repo_url),
+            link_target: LinkTarget::Branch(branch.to_string()),
+            toplevel: None,
+        }
+    }
+
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Builds URLs on the context's branch.
+    pub fn url_builder(&self) -> UrlBuilder<'_> {
+        UrlBuilder {
+            context: self,
+            git_ref: self.link_target.as_git_ref(),
+        }
+    }
+
+    /// Builds permalinks, pinned to the commit `rev` resolves to.
+    pub fn permalink_builder(&self, rev: &str) -> AnyhowResult<PermalinkBuilder<'_>> {
+        Ok(PermalinkBuilder {
+            context: self,
+            commit_sha: resolve_commit_sha(&SystemGit, rev)?,
+        })
+    }
+
+    fn repo_path(&self, path: &str) -> String {
+        match (&self.toplevel, env::current_dir()) {
+            (Some(toplevel), Ok(cwd)) => resolve_repo_path(path, &cwd, toplevel),
+            _ => normalize_repo_path(path).to_string(),
+        }
+    }
+}
+
+/// Builds the URLs of a repository's pages on one ref. Paths are relative to
+/// the current directory, or absolute.
+pub struct UrlBuilder<'a> {
+    context: &'a RepoContext,
+    git_ref: GitRef<'a>,
+}
+
+impl UrlBuilder<'_> {
+    pub fn repo(&self) -> Url {
+        Url(self.context.provider.repo_url(&self.context.repo_url))
+    }
+
+    pub fn commit(&self, commit_sha: &str) -> Url {
+        Url(get_commit_link(
+            self.context.provider.as_ref(),
+            &self.context.repo_url,
+            commit_sha,
+        ))
+    }
+
+    pub fn file(&self, path: &str) -> Url {
+        self.file_at(path, None, None)
+    }
+
+    pub fn file_line(&self, path: &str, line_number: u32) -> Url {
+        self.file_at(path, Some(line_number), None)
+    }
+
+    pub fn file_lines(&self, path: &str, start_line: u32, end_line: u32) -> Url {
+        self.file_at(path, Some(start_line), Some(end_line))
+    }
+
+    pub fn tree(&self, path: &str) -> Url {
+        Url(get_tree_link(
+            self.context.provider.as_ref(),
+            &self.context.repo_url,
+            &self.git_ref,
+            &self.context.repo_path(path),
+        ))
+    }
+
+    fn file_at(&self, path: &str, line_number: Option<u32>, end_line: Option<u32>) -> Url {
+        let repo_path = self.context.repo_path(path);
+        let line_number = line_number.map(|line| line.to_string());
+        let end_line = end_line.map(|line| line.to_string());
+        Url(get_line_number_link(
+            self.context.provider.as_ref(),
+            &self.context.repo_url,
+            &self.git_ref,
+            &FileAtLine::new(&repo_path, line_number.as_deref(), end_line.as_deref()),
+        ))
+    }
+}
+
+/// A `UrlBuilder` for a resolved commit, which it owns.
+pub struct PermalinkBuilder<'a> {
+    context: &'a RepoContext,
+    commit_sha: String,
+}
+
+impl PermalinkBuilder<'_> {
+    pub fn urls(&self) -> UrlBuilder<'_> {
+        UrlBuilder {
+            context: self.context,
+            git_ref: GitRef::Commit(&self.commit_sha),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_builder() {
+        let context = RepoContext::from_parts("https://gitlab.com/group/project.git", "main");
+        let urls = context.url_builder();
+        assert_eq!(context.provider_name(), "gitlab");
+        assert_eq!(urls.repo().as_str(), "https://gitlab.com/group/project");
+        assert_eq!(
+            urls.commit("998a1b3").as_str(),
+            "https://gitlab.com/group/project/-/commit/998a1b3"
+        );
+        assert_eq!(
+            urls.file("./src/main.rs").as_str(),
+            "https://gitlab.com/group/project/-/blob/main/src/main.rs"
+        );
+        assert_eq!(
+            urls.file_lines("src/main.rs", 10, 25).to_string(),
+            "https://gitlab.com/group/project/-/blob/main/src/main.rs#L10-25"
+        );
+        assert_eq!(
+            urls.tree("src/").into_string(),
+            "https://gitlab.com/group/project/-/tree/main/src"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! `gitopen doctor`: checks what gitopen needs to open links, one step at a
+//! time, and says how to fix what's missing instead of failing on the
+//! first error.
+
+use crate::actions::{
+    get_browser, get_local_branch_name, get_parsed_url, get_remote_name, get_repo_config,
+    load_config, RemoteSelection,
+};
+use crate::config::config_path;
+use crate::git::GitBackend;
+use crate::match_logic::get_host;
+use crate::output::{browser_problem, paint};
+use crate::providers::detect_provider;
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+use std::io::{self, IsTerminal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    /// gitopen still works, with links that may not be what's wanted.
+    Warning,
+    /// gitopen can't open links until it's fixed.
+    Failed,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Check {
+    status: Status,
+    found: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(found: String) -> Check {
+        Check {
+            status: Status::Ok,
+            found,
+            fix: None,
+        }
+    }
+
+    fn warning(found: String, fix: String) -> Check {
+        Check {
+            status: Status::Warning,
+            found,
+            fix: Some(fix),
+        }
+    }
+
+    fn failed(found: String, fix: String) -> Check {
+        Check {
+            status: Status::Failed,
+            found,
+            fix: Some(fix),
+        }
+    }
+}
+
+/// Checks the repository, its branch and remote, and the provider of the
+/// remote's host. Stops at the first check the later ones depend on.
+fn repo_checks(git: &dyn GitBackend, remote: RemoteSelection) -> Vec<Check> {
+    let mut checks = Vec::new();
+    match git.toplevel() {
+        Ok(Some(toplevel)) => checks.push(Check::ok(format!(
+            "inside the git repository {}",
+            toplevel.display()
+        ))),
+        Ok(None) | Err(_) => {
+            checks.push(Check::failed(
+                "not inside a git repository".to_string(),
+                "run gitopen in a clone, or point it at one with `gitopen -C <path>`".to_string(),
+            ));
+            return checks;
+        }
+    }
+
+    // The doctor never asks which remote to open.
+    let remote = RemoteSelection {
+        interactive: false,
+        ..remote
+    };
+    let remote_name = get_remote_name(git, remote);
+    let shown_remote = remote_name.as_deref().unwrap_or("origin");
+    match get_local_branch_name(git) {
+        Ok(branch) => {
+            checks.push(Check::ok(format!("on the branch '{}'", branch)));
+            let upstream = (
+                git.config_get(&format!("branch.{}.remote", branch)),
+                git.config_get(&format!("branch.{}.merge", branch)),
+            );
+            match upstream {
+                (Ok(Some(upstream_remote)), Ok(Some(merge))) => checks.push(Check::ok(format!(
+                    "'{}' tracks '{}/{}'",
+                    branch,
+                    upstream_remote,
+                    merge.trim_start_matches("refs/heads/")
+                ))),
+                _ => checks.push(Check::warning(
+                    format!("'{}' has no upstream branch", branch),
+                    format!(
+                        "push it with `git push -u {} {}`, until then links to it may not exist on the remote",
+                        shown_remote, branch
+                    ),
+                )),
+            }
+        }
+        Err(_) => checks.push(Check::warning(
+            "HEAD is detached, there is no current branch".to_string(),
+            "check out a branch with `git switch <branch>`, until then links point at the HEAD commit"
+                .to_string(),
+        )),
+    }
+
+    let remote_name = match remote_name {
+        Ok(remote_name) => {
+            checks.push(Check::ok(format!("opens the remote '{}'", remote_name)));
+            remote_name
+        }
+        Err(err) => {
+            checks.push(Check::failed(
+                format!("no remote to open: {}", err),
+                "add one with `git remote add origin <url>`, or pick one with `--remote <name>`"
+                    .to_string(),
+            ));
+            return checks;
+        }
+    };
+    let parsed_url = match get_parsed_url(git, remote_name.clone()) {
+        Ok(parsed_url) => {
+            checks.push(Check::ok(format!("'{}' is {}", remote_name, parsed_url)));
+            parsed_url
+        }
+        Err(err) => {
+            checks.push(Check::failed(
+                format!("the URL of '{}' can't be read: {}", remote_name, err),
+                format!(
+                    "gitopen reads https, ssh and `git@host:owner/repo` URLs, set one with `git remote set-url {} <url>`",
+                    remote_name
+                ),
+            ));
+            return checks;
+        }
+    };
+    checks.push(provider_check(git, &parsed_url));
+    checks
+}
+
+/// Which provider builds the links of the remote's host, and why.
+fn provider_check(git: &dyn GitBackend, parsed_url: &str) -> Check {
+    let fix_config = "fix the config with the error above".to_string();
+    let repo_config = match get_repo_config(git) {
+        Ok(repo_config) => repo_config,
+        Err(err) => return Check::failed(err.to_string(), fix_config),
+    };
+    let config = match load_config(git, parsed_url, &repo_config) {
+        Ok(config) => config,
+        Err(err) => return Check::failed(err.to_string(), fix_config),
+    };
+    let host = get_host(parsed_url);
+    let found = if let Some(provider) = &repo_config.provider {
+        format!("the repository's config sets the provider '{}'", provider)
+    } else if let Some(provider) = config.provider_for_host(host) {
+        format!("[providers] sets the provider '{}' for {}", provider, host)
+    } else if repo_config.templates.is_some() || config.templates_for_host(host).is_some() {
+        format!("URL templates are configured for {}", host)
+    } else if let Some(provider) = detect_provider(parsed_url) {
+        format!("{} is a {} host", host, provider.name())
+    } else {
+        let config_file = config_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "the config file".to_string());
+        return Check::warning(
+            format!("{} is not a host gitopen recognizes, links are GitHub's", host),
+            format!(
+                "add `\"{}\" = \"gitlab\"` (or gitea, bitbucket, ...) under [providers] in {}, or set `probe = true` there",
+                host, config_file
+            ),
+        );
+    };
+    match config.plugin_for_host(host) {
+        Some(plugin) => Check::ok(format!("{}, with the plugin gitopen-{}", found, plugin)),
+        None => Check::ok(found),
+    }
+}
+
+/// Whether URLs can be opened with `browser`, the configured browser or the
+/// system's.
+fn browser_check(git: &dyn GitBackend, browser: Option<&str>) -> Check {
+    let configured = match browser {
+        Some(browser) => Some(browser.to_string()),
+        None => get_browser(git).ok().flatten(),
+    };
+    match browser_problem(configured.as_deref()) {
+        None => Check::ok(match &configured {
+            Some(browser) => format!("opens links with '{}'", browser),
+            None => "opens links in the default browser".to_string(),
+        }),
+        Some(problem) => Check::warning(
+            format!("can't open a browser: {}", problem),
+            "set `browser` in the config file or $BROWSER, or use `--print` or `--copy`"
+                .to_string(),
+        ),
+    }
+}
+
+/// Prints every check with how to fix it, and fails when gitopen can't
+/// open links as things are.
+pub fn run_doctor(
+    git: &dyn GitBackend,
+    remote: RemoteSelection,
+    browser: Option<&str>,
+) -> AnyhowResult<()> {
+    let mut checks = repo_checks(git, remote);
+    checks.push(browser_check(git, browser));
+    let to_terminal = io::stdout().is_terminal();
+    for check in &checks {
+        let (status, style) = match check.status {
+            Status::Ok => ("ok", "1;32"),
+            Status::Warning => ("warning", "1;33"),
+            Status::Failed => ("error", "1;31"),
+        };
+        // Padded before it is colored, which the width would count.
+        let status = paint(&format!("{:<8}", status), style, to_terminal);
+        println!("{}{}", status, check.found);
+        if let Some(fix) = &check.fix {
+            println!("{:<8}fix: {}", "", fix);
+        }
+    }
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == Status::Failed)
+        .count();
+    match failed {
+        0 => Ok(()),
+        1 => Err(anyhow!("1 check failed")),
+        _ => Err(anyhow!("{} checks failed", failed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::FakeGit;
+    use std::env;
+
+    fn statuses(checks: &[Check]) -> Vec<Status> {
+        checks.iter().map(|check| check.status).collect()
+    }
+
+    #[test]
+    fn test_repo_checks() {
+        let remote = RemoteSelection::default();
+        let mut git = FakeGit::new("git@github.com:oren0e/gitopen.git", "main", "abc123");
+        assert_eq!(
+            repo_checks(&git, remote),
+            vec![Check::failed(
+                "not inside a git repository".to_string(),
+                "run gitopen in a clone, or point it at one with `gitopen -C <path>`".to_string(),
+            )]
+        );
+
+        git.toplevel = Some(env::current_dir().unwrap());
+        let checks = repo_checks(&git, remote);
+        assert_eq!(statuses(&checks), vec![Status::Ok; 6]);
+        assert_eq!(checks[2].found, "'main' tracks 'origin/main'");
+        assert_eq!(checks[5].found, "github.com is a github host");
+
+        git.symbolic_refs.clear();
+        git.remotes[0].1 = "https://code.corp.com/team/repo".to_string();
+        let checks = repo_checks(&git, remote);
+        assert_eq!(
+            statuses(&checks),
+            vec![
+                Status::Ok,
+                Status::Warning,
+                Status::Ok,
+                Status::Ok,
+                Status::Warning
+            ]
+        );
+        assert_eq!(
+            checks[1].fix.as_deref(),
+            Some("check out a branch with `git switch <branch>`, until then links point at the HEAD commit")
+        );
+
+        git.remotes.clear();
+        let checks = repo_checks(&git, remote);
+        assert_eq!(checks.last().unwrap().status, Status::Failed);
+        assert_eq!(
+            checks.last().unwrap().found,
+            "no remote to open: This repository has no remotes"
+        );
+    }
+}
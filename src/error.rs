@@ -0,0 +1,176 @@
+//! The errors gitopen can say more about than their message: what to do
+//! about them, and an exit code of their own for scripts to tell them apart.
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitOpenError {
+    NotARepo,
+    DetachedHead,
+    /// The branch isn't on the remote, so there is nothing to link to yet.
+    NoUpstream {
+        branch: String,
+        remote: String,
+    },
+    UnparseableRemote {
+        url: String,
+    },
+    /// The provider of `host` has no such page.
+    UnsupportedProvider {
+        host: String,
+        provider: String,
+        feature: String,
+    },
+    /// `browser` describes what was run, e.g. `'firefox'`.
+    BrowserFailed {
+        browser: String,
+        reason: String,
+    },
+    /// `reason` says in plain words why, when gitopen can tell from what
+    /// git printed, which is `output`.
+    PushFailed {
+        branch: String,
+        remote: String,
+        reason: Option<String>,
+        output: String,
+    },
+}
+
+impl GitOpenError {
+    /// What the user can do about the error.
+    pub fn hint(&self) -> String {
+        match self {
+            GitOpenError::NotARepo => {
+                "run gitopen in a clone, or point it at one with `gitopen -C <path>`".to_string()
+            }
+            GitOpenError::DetachedHead => {
+                "check out a branch with `git switch <branch>`, or link the commit with `gitopen commit HEAD`".to_string()
+            }
+            GitOpenError::NoUpstream { branch, remote } => format!(
+                "push it with `gitopen -p` or `git push -u {} {}`",
+                remote, branch
+            ),
+            GitOpenError::UnparseableRemote { .. } => {
+                "gitopen reads https, ssh and `git@host:owner/repo` URLs, fix the remote with `git remote set-url <name> <url>`".to_string()
+            }
+            GitOpenError::UnsupportedProvider { host, provider, .. } => format!(
+                "if {} is not a {} host, set its provider under [providers] in the config file",
+                host, provider
+            ),
+            GitOpenError::BrowserFailed { .. } => {
+                "set `browser` in the config file or $BROWSER, or use `--print` or `--copy`"
+                    .to_string()
+            }
+            GitOpenError::PushFailed { branch, remote, .. } => format!(
+                "fix what git says above and push with `git push {} {}`, then run gitopen without pushing",
+                remote, branch
+            ),
+        }
+    }
+
+    /// The exit code gitopen ends with, which scripts can rely on. Other
+    /// errors exit with 1, and 0 means the link was opened.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GitOpenError::NotARepo => 2,
+            GitOpenError::UnparseableRemote { .. } | GitOpenError::UnsupportedProvider { .. } => 3,
+            GitOpenError::BrowserFailed { .. } => 4,
+            GitOpenError::PushFailed { .. } => 5,
+            GitOpenError::DetachedHead => 6,
+            GitOpenError::NoUpstream { .. } => 7,
+        }
+    }
+}
+
+impl fmt::Display for GitOpenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GitOpenError::NotARepo => write!(f, "Not inside a git repository"),
+            GitOpenError::DetachedHead => {
+                write!(f, "HEAD is detached, there is no current branch")
+            }
+            GitOpenError::NoUpstream { branch, remote } => {
+                write!(f, "'{}' is not pushed to '{}' yet", branch, remote)
+            }
+            GitOpenError::UnparseableRemote { url } => {
+                write!(f, "'{}' is not a git remote URL gitopen can read", url)
+            }
+            GitOpenError::UnsupportedProvider {
+                provider, feature, ..
+            } => write!(f, "{} does not support {}", provider, feature),
+            GitOpenError::BrowserFailed { browser, reason } => {
+                write!(f, "Could not open the link with {}: {}", browser, reason)
+            }
+            GitOpenError::PushFailed {
+                branch,
+                remote,
+                reason,
+                output,
+            } => match reason {
+                Some(reason) => write!(
+                    f,
+                    "Pushing '{}' to '{}' failed, {}:\n{}",
+                    branch, remote, reason, output
+                ),
+                None => write!(
+                    f,
+                    "Pushing '{}' to '{}' failed:\n{}",
+                    branch, remote, output
+                ),
+            },
+        }
+    }
+}
+
+impl Error for GitOpenError {}
+
+/// The exit code for an error that ends gitopen.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<GitOpenError>()
+        .map_or(1, GitOpenError::exit_code)
+}
+
+/// What to do about an error that ends gitopen, when gitopen knows.
+pub fn hint(err: &anyhow::Error) -> Option<String> {
+    err.downcast_ref::<GitOpenError>().map(GitOpenError::hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_exit_code_and_hint() {
+        let err = anyhow::Error::from(GitOpenError::NoUpstream {
+            branch: "feat".to_string(),
+            remote: "origin".to_string(),
+        });
+        assert_eq!(err.to_string(), "'feat' is not pushed to 'origin' yet");
+        assert_eq!(exit_code(&err), 7);
+        assert_eq!(
+            hint(&err).as_deref(),
+            Some("push it with `gitopen -p` or `git push -u origin feat`")
+        );
+        // Context doesn't hide what the error was.
+        let err = err.context("Could not open the pull request");
+        assert_eq!(exit_code(&err), 7);
+
+        let err = anyhow::Error::from(GitOpenError::PushFailed {
+            branch: "feat".to_string(),
+            remote: "origin".to_string(),
+            reason: None,
+            output: "fatal: unable to access".to_string(),
+        });
+        assert_eq!(
+            err.to_string(),
+            "Pushing 'feat' to 'origin' failed:\nfatal: unable to access"
+        );
+        assert_eq!(exit_code(&err), 5);
+
+        let err = anyhow!("Remote 'origin' has no URL");
+        assert_eq!(exit_code(&err), 1);
+        assert_eq!(hint(&err), None);
+    }
+}
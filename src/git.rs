@@ -0,0 +1,921 @@
+//! Everything gitopen asks git, behind a trait so that the actions can run
+//! against an in-memory repository in tests.
+
+use crate::config::Config;
+#[cfg(feature = "gix")]
+use crate::gix_backend;
+use crate::match_logic::{parse_submodules, Submodule};
+use crate::ssh_config::lookup_hostname;
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{debug, trace};
+
+/// What a git command printed, for commands whose failure is worth reporting.
+#[derive(Debug, Default, Clone)]
+pub struct GitOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub trait GitBackend {
+    /// The ref `name` points at, e.g. `refs/heads/main` for `HEAD`, or `None`
+    /// when `name` is not a symbolic ref.
+    fn symbolic_ref(&self, name: &str) -> AnyhowResult<Option<String>>;
+
+    fn config_get(&self, key: &str) -> AnyhowResult<Option<String>>;
+
+    /// The `<key> <value>` lines of `git config --get-regexp <pattern>`.
+    fn config_get_regexp(&self, pattern: &str) -> AnyhowResult<String>;
+
+    /// Sets `key` in the repository's own config.
+    fn config_set(&self, key: &str, value: &str) -> AnyhowResult<()>;
+
+    /// The full SHA `rev` resolves to, or `None` when it doesn't.
+    fn rev_parse(&self, rev: &str) -> AnyhowResult<Option<String>>;
+
+    /// The root of the working tree, or `None` outside of one.
+    fn toplevel(&self) -> AnyhowResult<Option<PathBuf>>;
+
+    /// The repository's git directory, when it can be found without asking
+    /// git. Only used to key caches.
+    fn git_dir(&self) -> Option<PathBuf>;
+
+    fn remotes(&self) -> AnyhowResult<Vec<String>>;
+
+    /// The submodules the working tree's `.gitmodules` declares.
+    fn submodules(&self) -> AnyhowResult<Vec<Submodule>>;
+
+    /// The remote's URL with `url.<base>.insteadOf` rewrites applied, or
+    /// `None` when there is no such remote.
+    fn remote_url(&self, remote_name: &str) -> AnyhowResult<Option<String>>;
+
+    /// `git status --porcelain` for the paths matching `pathspec`.
+    fn status(&self, pathspec: &str) -> AnyhowResult<String>;
+
+    /// The full message of the commit `rev` names, or `None` when it doesn't.
+    fn commit_message(&self, rev: &str) -> AnyhowResult<Option<String>>;
+
+    /// The number of commits in `range`, or `None` when it doesn't resolve.
+    fn count_commits(&self, range: &str) -> AnyhowResult<Option<u32>>;
+
+    /// The full names of the local and remote-tracking branches, e.g.
+    /// `refs/heads/main` and `refs/remotes/origin/main`.
+    fn branches(&self) -> AnyhowResult<Vec<String>>;
+
+    /// The files git tracks below the current directory, relative to it.
+    fn tracked_files(&self) -> AnyhowResult<Vec<String>>;
+
+    /// The latest `count` commits of HEAD, one `<short sha> <subject>` line
+    /// each like `git log --oneline`.
+    fn recent_commits(&self, count: usize) -> AnyhowResult<Vec<String>>;
+
+    /// The remote-tracking branches matching `pattern` that contain the commit.
+    fn remote_branches_containing(
+        &self,
+        commit_sha: &str,
+        pattern: &str,
+    ) -> AnyhowResult<Vec<String>>;
+
+    /// The tags whose history contains the commit, oldest first.
+    fn tags_containing(&self, commit_sha: &str) -> AnyhowResult<Vec<String>>;
+
+    /// The commit that last changed the line of `path`, as `git blame` says,
+    /// or `None` when git can't blame it. Changes that aren't committed are
+    /// the commit `0000000...`.
+    fn blame_line(&self, path: &str, line: u32) -> AnyhowResult<Option<String>>;
+
+    /// Runs `git push <args>`. The remote's messages are in `stderr`.
+    fn push(&self, args: &[&str]) -> AnyhowResult<GitOutput>;
+
+    /// The user's `~/.config/gitopen/config.toml`. Not git's, but read here
+    /// so that tests don't see the config of whoever runs them.
+    fn user_config(&self) -> AnyhowResult<Config>;
+
+    /// The web host `~/.ssh/config` renames the host alias `alias` to, for
+    /// the same reason.
+    fn ssh_hostname(&self, alias: &str) -> Option<String>;
+}
+
+/// Runs the `git` on the `PATH` in the current directory.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemGit;
+
+/// The config of the repository in a directory, read with a single
+/// `git config --list` the first time a key is asked for there. `None`
+/// until then, and again after gitopen changes the config.
+type ConfigEntries = Option<(Option<PathBuf>, Vec<(String, String)>)>;
+static CONFIG_ENTRIES: Mutex<ConfigEntries> = Mutex::new(None);
+
+/// What a worktree's HEAD is, which gitopen asks for in a single
+/// `git rev-parse` before building almost any link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HeadState {
+    sha: String,
+    toplevel: PathBuf,
+    /// `None` when HEAD is detached.
+    branch_ref: Option<String>,
+}
+
+/// The HEAD of the worktree in a directory, like `CONFIG_ENTRIES`. The
+/// state is `None` where one call can't tell, e.g. outside a worktree or on
+/// a branch without commits, and each is then asked for on its own.
+type HeadStates = Option<(Option<PathBuf>, Option<HeadState>)>;
+static HEAD_STATE: Mutex<HeadStates> = Mutex::new(None);
+
+/// Reads `git rev-parse HEAD --show-toplevel --symbolic-full-name HEAD`.
+fn parse_head_state(stdout: &str) -> Option<HeadState> {
+    let mut lines = stdout.lines();
+    let (sha, toplevel, symbolic_name) = (lines.next()?, lines.next()?, lines.next()?);
+    Some(HeadState {
+        sha: sha.to_string(),
+        toplevel: PathBuf::from(toplevel),
+        branch_ref: Some(symbolic_name.to_string()).filter(|name| name != "HEAD"),
+    })
+}
+
+/// The `(key, value)` pairs of `git config --list -z`, in the order git
+/// reads them. Keys without a value, which git takes as true, have an empty
+/// one.
+fn parse_config_list(text: &str) -> Vec<(String, String)> {
+    text.split('\0')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('\n') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (entry.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// `key` as `git config --list` prints it: the section and the variable
+/// name are case-insensitive and printed in lowercase, a subsection is not.
+fn canonical_key(key: &str) -> String {
+    match (key.find('.'), key.rfind('.')) {
+        (Some(first), Some(last)) => format!(
+            "{}{}{}",
+            key[..first].to_lowercase(),
+            &key[first..last],
+            key[last..].to_lowercase()
+        ),
+        _ => key.to_lowercase(),
+    }
+}
+
+/// How long git commands and pushes may take, from the config file.
+fn timeouts() -> (Duration, Duration) {
+    static TIMEOUTS: OnceLock<(Duration, Duration)> = OnceLock::new();
+    *TIMEOUTS.get_or_init(|| {
+        let config = Config::load().unwrap_or_default();
+        (
+            Duration::from_secs(config.git_timeout.unwrap_or(30)),
+            Duration::from_secs(config.push_timeout.unwrap_or(300)),
+        )
+    })
+}
+
+fn read_in_background<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+/// Runs the command with its output captured, like `Command::output`, but
+/// kills it and returns `None` once `timeout` has passed.
+fn output_within(command: &mut Command, timeout: Duration) -> io::Result<Option<Output>> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Read as it runs, or a command printing more than a pipe holds would
+    // never finish.
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let mut pause = Duration::from_millis(1);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            // An ssh git started may still hold the pipes, so the readers
+            // are left to finish on their own.
+            return Ok(None);
+        }
+        thread::sleep(pause);
+        pause = (pause * 2).min(Duration::from_millis(50));
+    };
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+impl SystemGit {
+    fn run(&self, args: &[&str]) -> AnyhowResult<GitOutput> {
+        self.run_with(args, Stdio::null(), timeouts().0, "git_timeout")
+    }
+
+    /// Runs git, stopping it once `timeout` has passed, which the config
+    /// file's `setting` raises.
+    fn run_with(
+        &self,
+        args: &[&str],
+        stdin: Stdio,
+        timeout: Duration,
+        setting: &str,
+    ) -> AnyhowResult<GitOutput> {
+        debug!("git {}", args.join(" "));
+        let output = output_within(Command::new("git").args(args).stdin(stdin), timeout)?
+            .ok_or_else(|| {
+                anyhow!(
+                    "`git {}` did not finish within {}s and was stopped, set `{}` in the config file to wait longer",
+                    args.join(" "),
+                    timeout.as_secs(),
+                    setting
+                )
+            })?;
+        let output = GitOutput {
+            success: output.status.success(),
+            // Commit subjects and paths needn't be UTF-8, and one that isn't
+            // shouldn't fail the whole command.
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        };
+        trace!(
+            success = output.success,
+            stdout = output.stdout.trim_end(),
+            stderr = output.stderr.trim_end(),
+            "git {} finished",
+            args.first().unwrap_or(&"")
+        );
+        Ok(output)
+    }
+
+    /// Starts reading the config on another thread, so that it is ready by
+    /// the time gitopen asks for a key instead of after the other commands.
+    pub fn prefetch_config(&self) {
+        thread::spawn(|| {
+            let _ = SystemGit.with_config(|_| ());
+        });
+    }
+
+    /// Calls `f` with the config entries, reading them first when they
+    /// haven't been yet.
+    fn with_config<T>(&self, f: impl FnOnce(&[(String, String)]) -> T) -> AnyhowResult<T> {
+        let dir = env::current_dir().ok();
+        let mut entries = CONFIG_ENTRIES.lock().unwrap_or_else(|err| err.into_inner());
+        match &*entries {
+            Some((read_in, entries)) if *read_in == dir => return Ok(f(entries)),
+            _ => {}
+        }
+        let output = self.run(&["config", "--list", "-z"])?;
+        let read = parse_config_list(&output.stdout);
+        let value = f(&read);
+        *entries = Some((dir, read));
+        Ok(value)
+    }
+
+    fn head_state(&self) -> AnyhowResult<Option<HeadState>> {
+        let dir = env::current_dir().ok();
+        let mut state = HEAD_STATE.lock().unwrap_or_else(|err| err.into_inner());
+        match &*state {
+            Some((read_in, head_state)) if *read_in == dir => return Ok(head_state.clone()),
+            _ => {}
+        }
+        let output = self.run(&[
+            "rev-parse",
+            "HEAD",
+            "--show-toplevel",
+            "--symbolic-full-name",
+            "HEAD",
+        ])?;
+        let head_state = if output.success {
+            parse_head_state(&output.stdout)
+        } else {
+            None
+        };
+        *state = Some((dir, head_state.clone()));
+        Ok(head_state)
+    }
+
+    /// The trimmed output of a command that prints a single value, or `None`
+    /// when it fails.
+    fn value(&self, args: &[&str]) -> AnyhowResult<Option<String>> {
+        let output = self.run(args)?;
+        Ok(Some(output.stdout.trim().to_string()).filter(|_| output.success))
+    }
+}
+
+impl GitBackend for SystemGit {
+    fn symbolic_ref(&self, name: &str) -> AnyhowResult<Option<String>> {
+        #[cfg(feature = "gix")]
+        if let Ok(target) = gix_backend::symbolic_ref(name) {
+            debug!(?target, "gix read the symbolic ref {}", name);
+            return Ok(target);
+        }
+        if name == "HEAD" {
+            if let Some(head_state) = self.head_state()? {
+                return Ok(head_state.branch_ref);
+            }
+        }
+        self.value(&["symbolic-ref", "--quiet", name])
+    }
+
+    fn config_get(&self, key: &str) -> AnyhowResult<Option<String>> {
+        #[cfg(feature = "gix")]
+        if let Ok(value) = gix_backend::config_get(key) {
+            debug!(?value, "gix read the config {}", key);
+            return Ok(value);
+        }
+        let key = canonical_key(key);
+        // Like `git config --get`, the last value wins.
+        self.with_config(|entries| {
+            entries
+                .iter()
+                .rev()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.clone())
+        })
+    }
+
+    fn config_get_regexp(&self, pattern: &str) -> AnyhowResult<String> {
+        let pattern = Regex::new(pattern)?;
+        self.with_config(|entries| {
+            entries
+                .iter()
+                .filter(|(key, _)| pattern.is_match(key))
+                .map(|(key, value)| format!("{} {}\n", key, value))
+                .collect()
+        })
+    }
+
+    fn config_set(&self, key: &str, value: &str) -> AnyhowResult<()> {
+        let output = self.run(&["config", key, value])?;
+        *CONFIG_ENTRIES.lock().unwrap_or_else(|err| err.into_inner()) = None;
+        if !output.success {
+            return Err(anyhow!(
+                "Setting '{}' failed:\n{}",
+                key,
+                output.stderr.trim_end()
+            ));
+        }
+        Ok(())
+    }
+
+    fn rev_parse(&self, rev: &str) -> AnyhowResult<Option<String>> {
+        if rev == "HEAD" || rev == "HEAD^{commit}" {
+            if let Some(head_state) = self.head_state()? {
+                return Ok(Some(head_state.sha));
+            }
+        }
+        self.value(&["rev-parse", "--verify", "--quiet", rev])
+    }
+
+    fn toplevel(&self) -> AnyhowResult<Option<PathBuf>> {
+        if let Some(head_state) = self.head_state()? {
+            return Ok(Some(head_state.toplevel));
+        }
+        Ok(self
+            .value(&["rev-parse", "--show-toplevel"])?
+            .map(PathBuf::from))
+    }
+
+    fn git_dir(&self) -> Option<PathBuf> {
+        let cwd = env::current_dir().ok()?;
+        if let Some(git_dir) = env::var_os("GIT_DIR") {
+            return Some(cwd.join(git_dir));
+        }
+        for dir in cwd.ancestors() {
+            let dot_git = dir.join(".git");
+            if dot_git.is_dir() {
+                return Some(dot_git);
+            }
+            if dot_git.is_file() {
+                // Worktrees and submodules point at their git dir from a file.
+                let text = fs::read_to_string(&dot_git).ok()?;
+                return Some(dir.join(text.strip_prefix("gitdir:")?.trim()));
+            }
+        }
+        None
+    }
+
+    fn remotes(&self) -> AnyhowResult<Vec<String>> {
+        // The remotes `git remote` lists are those with a URL in the config.
+        self.with_config(|entries| {
+            let mut remotes: Vec<String> = Vec::new();
+            for (key, _) in entries {
+                let name = key
+                    .strip_prefix("remote.")
+                    .and_then(|key| key.strip_suffix(".url"));
+                if let Some(name) = name {
+                    if !remotes.iter().any(|remote| remote == name) {
+                        remotes.push(name.to_string());
+                    }
+                }
+            }
+            remotes
+        })
+    }
+
+    fn submodules(&self) -> AnyhowResult<Vec<Submodule>> {
+        let gitmodules = match self.toplevel()? {
+            Some(toplevel) if toplevel.join(".gitmodules").is_file() => {
+                toplevel.join(".gitmodules")
+            }
+            _ => return Ok(vec![]),
+        };
+        let gitmodules = gitmodules
+            .to_str()
+            .ok_or_else(|| anyhow!("{} is not valid UTF-8", gitmodules.display()))?;
+        let config = self.run(&[
+            "config",
+            "--file",
+            gitmodules,
+            "--get-regexp",
+            r"^submodule\.",
+        ])?;
+        Ok(parse_submodules(&config.stdout))
+    }
+
+    fn remote_url(&self, remote_name: &str) -> AnyhowResult<Option<String>> {
+        #[cfg(feature = "gix")]
+        if let Ok(remote_url) = gix_backend::remote_url(remote_name) {
+            debug!(remote_url, "gix read the URL of {}", remote_name);
+            return Ok(Some(remote_url));
+        }
+        self.value(&["remote", "get-url", remote_name])
+    }
+
+    fn status(&self, pathspec: &str) -> AnyhowResult<String> {
+        Ok(self.run(&["status", "--porcelain", "--", pathspec])?.stdout)
+    }
+
+    fn commit_message(&self, rev: &str) -> AnyhowResult<Option<String>> {
+        self.value(&["log", "-1", "--format=%B", rev, "--"])
+    }
+
+    fn count_commits(&self, range: &str) -> AnyhowResult<Option<u32>> {
+        Ok(self
+            .value(&["rev-list", "--count", range])?
+            .and_then(|count| count.parse().ok()))
+    }
+
+    fn branches(&self) -> AnyhowResult<Vec<String>> {
+        let output = self.run(&[
+            "for-each-ref",
+            "--format=%(refname)",
+            "refs/heads",
+            "refs/remotes",
+        ])?;
+        Ok(output.stdout.lines().map(str::to_string).collect())
+    }
+
+    fn tracked_files(&self) -> AnyhowResult<Vec<String>> {
+        let output = self.run(&["ls-files"])?;
+        Ok(output.stdout.lines().map(str::to_string).collect())
+    }
+
+    fn recent_commits(&self, count: usize) -> AnyhowResult<Vec<String>> {
+        let output = self.run(&[
+            "log",
+            "--format=%h %s",
+            &format!("--max-count={}", count),
+            "--",
+        ])?;
+        Ok(output.stdout.lines().map(str::to_string).collect())
+    }
+
+    fn remote_branches_containing(
+        &self,
+        commit_sha: &str,
+        pattern: &str,
+    ) -> AnyhowResult<Vec<String>> {
+        let stdout = self
+            .run(&[
+                "branch",
+                "--remotes",
+                "--contains",
+                commit_sha,
+                "--list",
+                pattern,
+            ])?
+            .stdout;
+        Ok(stdout.lines().map(|name| name.trim().to_string()).collect())
+    }
+
+    fn blame_line(&self, path: &str, line: u32) -> AnyhowResult<Option<String>> {
+        let lines = format!("-L{},{}", line, line);
+        let output = self.run(&["blame", &lines, "--porcelain", "--", path])?;
+        if !output.success {
+            return Ok(None);
+        }
+        // The porcelain format starts with `<sha> <line> <line> <count>`.
+        Ok(output.stdout.split_whitespace().next().map(str::to_string))
+    }
+
+    fn tags_containing(&self, commit_sha: &str) -> AnyhowResult<Vec<String>> {
+        let stdout = self
+            .run(&["tag", "--contains", commit_sha, "--sort=creatordate"])?
+            .stdout;
+        Ok(stdout.lines().map(str::to_string).collect())
+    }
+
+    fn push(&self, args: &[&str]) -> AnyhowResult<GitOutput> {
+        let mut push_args = vec!["push"];
+        push_args.extend_from_slice(args);
+        // Credentials may have to be typed in for the push.
+        self.run_with(&push_args, Stdio::inherit(), timeouts().1, "push_timeout")
+    }
+
+    fn user_config(&self) -> AnyhowResult<Config> {
+        Config::load()
+    }
+
+    fn ssh_hostname(&self, alias: &str) -> Option<String> {
+        lookup_hostname(alias)
+    }
+}
+
+/// Asks `git` everything, but only prints the pushes and config writes it
+/// would make, for `--dry-run`.
+pub struct DryRun<'a> {
+    pub git: &'a dyn GitBackend,
+}
+
+impl GitBackend for DryRun<'_> {
+    fn symbolic_ref(&self, name: &str) -> AnyhowResult<Option<String>> {
+        self.git.symbolic_ref(name)
+    }
+
+    fn config_get(&self, key: &str) -> AnyhowResult<Option<String>> {
+        self.git.config_get(key)
+    }
+
+    fn config_get_regexp(&self, pattern: &str) -> AnyhowResult<String> {
+        self.git.config_get_regexp(pattern)
+    }
+
+    fn config_set(&self, key: &str, value: &str) -> AnyhowResult<()> {
+        println!("would run: git config {} {}", key, value);
+        Ok(())
+    }
+
+    fn rev_parse(&self, rev: &str) -> AnyhowResult<Option<String>> {
+        self.git.rev_parse(rev)
+    }
+
+    fn toplevel(&self) -> AnyhowResult<Option<PathBuf>> {
+        self.git.toplevel()
+    }
+
+    fn git_dir(&self) -> Option<PathBuf> {
+        self.git.git_dir()
+    }
+
+    fn remotes(&self) -> AnyhowResult<Vec<String>> {
+        self.git.remotes()
+    }
+
+    fn submodules(&self) -> AnyhowResult<Vec<Submodule>> {
+        self.git.submodules()
+    }
+
+    fn remote_url(&self, remote_name: &str) -> AnyhowResult<Option<String>> {
+        self.git.remote_url(remote_name)
+    }
+
+    fn status(&self, pathspec: &str) -> AnyhowResult<String> {
+        self.git.status(pathspec)
+    }
+
+    fn commit_message(&self, rev: &str) -> AnyhowResult<Option<String>> {
+        self.git.commit_message(rev)
+    }
+
+    fn count_commits(&self, range: &str) -> AnyhowResult<Option<u32>> {
+        self.git.count_commits(range)
+    }
+
+    fn branches(&self) -> AnyhowResult<Vec<String>> {
+        self.git.branches()
+    }
+
+    fn tracked_files(&self) -> AnyhowResult<Vec<String>> {
+        self.git.tracked_files()
+    }
+
+    fn recent_commits(&self, count: usize) -> AnyhowResult<Vec<String>> {
+        self.git.recent_commits(count)
+    }
+
+    fn remote_branches_containing(
+        &self,
+        commit_sha: &str,
+        pattern: &str,
+    ) -> AnyhowResult<Vec<String>> {
+        self.git.remote_branches_containing(commit_sha, pattern)
+    }
+
+    fn tags_containing(&self, commit_sha: &str) -> AnyhowResult<Vec<String>> {
+        self.git.tags_containing(commit_sha)
+    }
+
+    fn blame_line(&self, path: &str, line: u32) -> AnyhowResult<Option<String>> {
+        self.git.blame_line(path, line)
+    }
+
+    /// Succeeds without output, as a push of nothing new would.
+    fn push(&self, args: &[&str]) -> AnyhowResult<GitOutput> {
+        println!("would run: git push {}", args.join(" "));
+        Ok(GitOutput {
+            success: true,
+            ..GitOutput::default()
+        })
+    }
+
+    fn user_config(&self) -> AnyhowResult<Config> {
+        self.git.user_config()
+    }
+
+    fn ssh_hostname(&self, alias: &str) -> Option<String> {
+        self.git.ssh_hostname(alias)
+    }
+}
+
+/// A repository that only exists in memory. Refs, config and remotes are
+/// whatever the test sets up, and pushes are recorded instead of sent.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct FakeGit {
+    pub symbolic_refs: std::collections::HashMap<String, String>,
+    pub config: std::cell::RefCell<std::collections::BTreeMap<String, String>>,
+    pub revs: std::collections::HashMap<String, String>,
+    pub commit_messages: std::collections::HashMap<String, String>,
+    /// The commits `blame_line` gives, by `<path>:<line>`.
+    pub blame: std::collections::HashMap<String, String>,
+    pub log: Vec<String>,
+    pub files: Vec<String>,
+    pub remotes: Vec<(String, String)>,
+    pub submodules: Vec<Submodule>,
+    pub toplevel: Option<PathBuf>,
+    pub status: String,
+    pub push_output: GitOutput,
+    pub pushes: std::cell::RefCell<Vec<Vec<String>>>,
+    /// The text of the user's config file, empty unless a test sets it.
+    pub user_config: String,
+    /// The host aliases `~/.ssh/config` renames, to their web hosts.
+    pub ssh_hosts: std::collections::HashMap<String, String>,
+}
+
+#[cfg(test)]
+impl FakeGit {
+    /// A repository with `remote_url` as `origin` and `branch` checked out
+    /// at `sha`, which the remote has too.
+    pub fn new(remote_url: &str, branch: &str, sha: &str) -> FakeGit {
+        let mut git = FakeGit::default();
+        git.remotes
+            .push(("origin".to_string(), remote_url.to_string()));
+        git.symbolic_refs
+            .insert("HEAD".to_string(), format!("refs/heads/{}", branch));
+        git.set_config(&format!("branch.{}.remote", branch), "origin");
+        git.set_config(
+            &format!("branch.{}.merge", branch),
+            &format!("refs/heads/{}", branch),
+        );
+        for rev in [
+            "HEAD".to_string(),
+            branch.to_string(),
+            format!("refs/remotes/origin/{}", branch),
+        ] {
+            git.revs.insert(rev, sha.to_string());
+        }
+        git.push_output.success = true;
+        git
+    }
+
+    pub fn set_config(&self, key: &str, value: &str) {
+        self.config
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string());
+    }
+}
+
+#[cfg(test)]
+impl GitBackend for FakeGit {
+    fn symbolic_ref(&self, name: &str) -> AnyhowResult<Option<String>> {
+        Ok(self.symbolic_refs.get(name).cloned())
+    }
+
+    fn config_get(&self, key: &str) -> AnyhowResult<Option<String>> {
+        Ok(self.config.borrow().get(key).cloned())
+    }
+
+    /// Only supports the `^prefix\.` patterns gitopen uses.
+    fn config_get_regexp(&self, pattern: &str) -> AnyhowResult<String> {
+        let prefix = pattern.trim_start_matches('^').replace("\\.", ".");
+        Ok(self
+            .config
+            .borrow()
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, value)| format!("{} {}\n", key, value))
+            .collect())
+    }
+
+    fn config_set(&self, key: &str, value: &str) -> AnyhowResult<()> {
+        self.set_config(key, value);
+        Ok(())
+    }
+
+    fn rev_parse(&self, rev: &str) -> AnyhowResult<Option<String>> {
+        let rev = rev.trim_end_matches("^{commit}");
+        Ok(self.revs.get(rev).cloned())
+    }
+
+    fn toplevel(&self) -> AnyhowResult<Option<PathBuf>> {
+        Ok(self.toplevel.clone())
+    }
+
+    fn git_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn remotes(&self) -> AnyhowResult<Vec<String>> {
+        Ok(self.remotes.iter().map(|(name, _)| name.clone()).collect())
+    }
+
+    fn submodules(&self) -> AnyhowResult<Vec<Submodule>> {
+        Ok(self.submodules.clone())
+    }
+
+    fn remote_url(&self, remote_name: &str) -> AnyhowResult<Option<String>> {
+        Ok(self
+            .remotes
+            .iter()
+            .find(|(name, _)| name == remote_name)
+            .map(|(_, url)| url.clone()))
+    }
+
+    fn status(&self, _pathspec: &str) -> AnyhowResult<String> {
+        Ok(self.status.clone())
+    }
+
+    fn commit_message(&self, rev: &str) -> AnyhowResult<Option<String>> {
+        Ok(self.commit_messages.get(rev).cloned())
+    }
+
+    fn branches(&self) -> AnyhowResult<Vec<String>> {
+        let mut branches: Vec<String> = self
+            .revs
+            .keys()
+            .filter(|name| name.starts_with("refs/heads/") || name.starts_with("refs/remotes/"))
+            .cloned()
+            .collect();
+        branches.sort();
+        Ok(branches)
+    }
+
+    fn tracked_files(&self) -> AnyhowResult<Vec<String>> {
+        Ok(self.files.clone())
+    }
+
+    fn recent_commits(&self, count: usize) -> AnyhowResult<Vec<String>> {
+        Ok(self.log.iter().take(count).cloned().collect())
+    }
+
+    fn count_commits(&self, range: &str) -> AnyhowResult<Option<u32>> {
+        let (base, head) = range.split_once("..").unwrap_or(("", range));
+        Ok(match (self.revs.get(base), self.revs.get(head)) {
+            (Some(base), Some(head)) => Some(if base == head { 0 } else { 1 }),
+            _ => None,
+        })
+    }
+
+    fn remote_branches_containing(
+        &self,
+        commit_sha: &str,
+        pattern: &str,
+    ) -> AnyhowResult<Vec<String>> {
+        let prefix = format!("refs/remotes/{}", pattern.trim_end_matches('*'));
+        Ok(self
+            .revs
+            .iter()
+            .filter(|(rev, sha)| rev.starts_with(&prefix) && *sha == commit_sha)
+            .map(|(rev, _)| rev.trim_start_matches("refs/remotes/").to_string())
+            .collect())
+    }
+
+    fn blame_line(&self, path: &str, line: u32) -> AnyhowResult<Option<String>> {
+        Ok(self.blame.get(&format!("{}:{}", path, line)).cloned())
+    }
+
+    /// The tags at the commit, by name.
+    fn tags_containing(&self, commit_sha: &str) -> AnyhowResult<Vec<String>> {
+        let mut tags: Vec<String> = self
+            .revs
+            .iter()
+            .filter(|(rev, sha)| rev.starts_with("refs/tags/") && *sha == commit_sha)
+            .map(|(rev, _)| rev.trim_start_matches("refs/tags/").to_string())
+            .collect();
+        tags.sort();
+        Ok(tags)
+    }
+
+    fn push(&self, args: &[&str]) -> AnyhowResult<GitOutput> {
+        self.pushes
+            .borrow_mut()
+            .push(args.iter().map(|arg| arg.to_string()).collect());
+        Ok(self.push_output.clone())
+    }
+
+    fn user_config(&self) -> AnyhowResult<Config> {
+        Config::parse(&self.user_config)
+    }
+
+    fn ssh_hostname(&self, alias: &str) -> Option<String> {
+        self.ssh_hosts.get(alias).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config_list() {
+        let entries = parse_config_list(
+            "user.name\nOren\0branch.Feat/X.remote\norigin\0core.bare\0gitopen.note\nfirst\nsecond\0",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                ("user.name".to_string(), "Oren".to_string()),
+                ("branch.Feat/X.remote".to_string(), "origin".to_string()),
+                ("core.bare".to_string(), String::new()),
+                ("gitopen.note".to_string(), "first\nsecond".to_string()),
+            ]
+        );
+        assert_eq!(
+            canonical_key("Branch.Feat/X.pushRemote"),
+            "branch.Feat/X.pushremote"
+        );
+        assert_eq!(canonical_key("remote.pushDefault"), "remote.pushdefault");
+        assert_eq!(canonical_key("core"), "core");
+    }
+
+    #[test]
+    fn test_parse_head_state() {
+        assert_eq!(
+            parse_head_state("abc123\n/home/oren/gitopen\nrefs/heads/main\n"),
+            Some(HeadState {
+                sha: "abc123".to_string(),
+                toplevel: PathBuf::from("/home/oren/gitopen"),
+                branch_ref: Some("refs/heads/main".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_head_state("abc123\n/home/oren/gitopen\nHEAD\n").map(|state| state.branch_ref),
+            Some(None)
+        );
+        assert_eq!(parse_head_state("abc123\n"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_within() {
+        let output = output_within(
+            Command::new("sh").args(["-c", "echo out; echo err >&2"]),
+            Duration::from_secs(10),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+
+        let started = Instant::now();
+        let output = output_within(
+            Command::new("sh").args(["-c", "sleep 10"]),
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        assert!(output.is_none());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,45 @@
+//! Reads refs, config and remote URLs straight from the repository with
+//! gitoxide, instead of running `git` for each of them.
+
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+use gix::bstr::ByteSlice;
+use gix::remote::Direction;
+
+/// Opens the repository `GIT_DIR` and `GIT_WORK_TREE` name, as git does, or
+/// else the one the current directory is in.
+fn open_repo() -> AnyhowResult<gix::Repository> {
+    Ok(gix::ThreadSafeRepository::discover_with_environment_overrides(".")?.to_thread_local())
+}
+
+/// The ref `name` points at, or `None` when it isn't symbolic, like HEAD
+/// when it is detached.
+pub fn symbolic_ref(name: &str) -> AnyhowResult<Option<String>> {
+    let repo = open_repo()?;
+    let reference = match repo.try_find_reference(name)? {
+        Some(reference) => reference,
+        None => return Ok(None),
+    };
+    Ok(reference
+        .target()
+        .try_name()
+        .map(|target| target.as_bstr().to_str_lossy().into_owned()))
+}
+
+pub fn config_get(key: &str) -> AnyhowResult<Option<String>> {
+    Ok(open_repo()?
+        .config_snapshot()
+        .string(key)
+        .map(|value| value.to_str_lossy().into_owned()))
+}
+
+/// The remote's fetch URL with `url.<base>.insteadOf` rewrites applied, as
+/// `git remote get-url` prints it.
+pub fn remote_url(remote_name: &str) -> AnyhowResult<String> {
+    let repo = open_repo()?;
+    let remote = repo.find_remote(remote_name)?;
+    let url = remote
+        .url(Direction::Fetch)
+        .ok_or_else(|| anyhow!("Remote '{}' has no URL", remote_name))?;
+    Ok(url.to_bstring().to_str_lossy().into_owned())
+}
@@ -0,0 +1,54 @@
+//! Builds the web URLs of a git repository: its home page, commits and files
+//! at a line, for GitHub, GitLab, Bitbucket, Gitea, sourcehut, Azure DevOps
+//! and hosts configured with URL templates.
+//!
+//! ```
+//! use gitopen::RepoContext;
+//!
+//! let context = RepoContext::from_parts("https://github.com/oren0e/gitopen", "master");
+//! let urls = context.url_builder();
+//! assert_eq!(
+//!     urls.file_line("src/main.rs", 10).as_str(),
+//!     "https://github.com/oren0e/gitopen/blob/master/src/main.rs#L10"
+//! );
+//! ```
+//!
+//! `RepoContext::discover()` finds the repository, remote and branch from the
+//! current directory instead, the way the `gitopen` command does.
+
+/// Prints a warning to stderr, like `eprintln!` after a `warning:` prefix,
+/// unless `--quiet`.
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::output::report($crate::output::Label::Warning, &format!($($arg)*))
+    };
+}
+
+/// The same with a `note:` prefix, for what isn't a problem.
+macro_rules! note {
+    ($($arg:tt)*) => {
+        $crate::output::report($crate::output::Label::Note, &format!($($arg)*))
+    };
+}
+
+pub mod actions;
+#[cfg(feature = "api")]
+mod api;
+mod cache;
+pub mod completions;
+pub mod config;
+pub mod config_command;
+mod context;
+pub mod doctor;
+pub mod error;
+pub mod git;
+#[cfg(feature = "gix")]
+mod gix_backend;
+pub mod match_logic;
+pub mod output;
+mod picker;
+mod plugin;
+pub mod providers;
+mod ssh_config;
+
+pub use context::{PermalinkBuilder, RepoContext, Url, UrlBuilder};
@@ -1,14 +1,72 @@
-use clap::{crate_version, App, Arg};
+use clap::{crate_version, App, AppSettings, Arg, Shell, SubCommand};
 
-use crate::actions::{open_at_line_number, open_commit, open_repo, push_and_open_pr};
 use anyhow::anyhow;
 use anyhow::Result as AnyhowResult;
+use gitopen::actions::{
+    get_browser, get_file_action, get_format, open_archive, open_at_line_number, open_blame,
+    open_branch, open_branch_pr, open_change, open_ci, open_code_search, open_commit, open_compare,
+    open_default, open_edit, open_from_diagnostics, open_from_stdin, open_history, open_issue,
+    open_issues, open_last_change, open_new_issue, open_new_pr, open_org, open_pr,
+    open_pr_for_commit, open_project_page, open_release, open_releases, open_settings,
+    open_submodule, open_tag, open_tags, open_user, open_wiki, pick_commit, pick_file,
+    push_and_open_pr, LinkOptions, NewPrOptions, PushOptions, RemoteSelection,
+};
+use gitopen::completions::{completion_candidates, with_dynamic_completions, SHELLS};
+use gitopen::config_command::{get_setting, list_settings, set_setting, Scope};
+use gitopen::doctor::run_doctor;
+use gitopen::error::{exit_code, hint};
+use gitopen::git::{DryRun, GitBackend, SystemGit};
+use gitopen::output::{report, set_color, set_quiet, Format, Label, Output};
+use gitopen::providers::{ArchiveFormat, ProjectPage, SettingsSection};
+use std::env;
+use std::io;
+use std::process;
+use tracing::Level;
 
-mod actions;
-mod match_logic;
+fn main() {
+    if let Err(err) = run() {
+        report(Label::Error, &format!("{:#}", err));
+        if let Some(hint) = hint(&err) {
+            report(Label::Hint, &hint);
+        }
+        process::exit(exit_code(&err));
+    }
+}
+
+/// Logs to stderr from `-v` on, at the trace level from `-vv`.
+fn init_logging(verbosity: u64) {
+    let level = match verbosity {
+        0 => return,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .without_time()
+        .with_target(false)
+        .init();
+}
 
-fn main() -> AnyhowResult<()> {
-    let matches = App::new("Gitopen")
+fn run() -> AnyhowResult<()> {
+    let settings_sections: Vec<&str> = SettingsSection::ALL
+        .iter()
+        .map(|section| section.name())
+        .collect();
+    let scope_args = [
+        Arg::with_name("global")
+            .long("global")
+            .help("Uses the config file, ~/.config/gitopen/config.toml"),
+        Arg::with_name("repo")
+            .long("repo")
+            .conflicts_with("global")
+            .help("Uses the gitopen.* git config of the repository"),
+    ];
+    let archive_formats: Vec<&str> = ArchiveFormat::ALL
+        .iter()
+        .map(|format| format.name())
+        .collect();
+    let mut app = App::new("Gitopen")
         .version(crate_version!())
         .author("Oren Epshtain")
         .about("Utility to open repo from terminal and pull requests after push")
@@ -18,13 +76,30 @@ fn main() -> AnyhowResult<()> {
                 .long("push-open-pr")
                 .help("Pushes to current branch and opens corresponding PR"),
         )
+        .arg(
+            Arg::with_name("force_with_lease")
+                .long("force-with-lease")
+                .requires("push_and_pr")
+                .help("Force-pushes with -p unless the remote branch moved, e.g. after a rebase"),
+        )
+        .arg(
+            Arg::with_name("push_option")
+                .short("o")
+                .long("push-option")
+                .value_name("OPTION")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("push_and_pr")
+                .help("Passes a push option to the remote with -p, e.g. 'ci.skip'"),
+        )
         .arg(
             Arg::with_name("open_commit")
                 .short("c")
                 .long("commit")
                 .value_name("COMMIT")
                 .takes_value(true)
-                .help("Opens the specified commit"),
+                .help("Opens the specified commit (any revision, e.g. a SHA, tag or HEAD~2), or compares a range like A..B"),
         )
         .arg(
             Arg::with_name("open_line_number")
@@ -34,25 +109,771 @@ fn main() -> AnyhowResult<()> {
                 .takes_value(true)
                 .help("Open the specified filepath at the specified line number"),
         )
+        .arg(
+            Arg::with_name("PATH")
+                .multiple(true)
+                .conflicts_with("open_line_number")
+                .help("Directories to open in the tree view, or '<filepath>[:<line-number>]'s"),
+        )
+        .arg(
+            Arg::with_name("pick_file")
+                .short("i")
+                .long("interactive")
+                // Rather than on the global `--quiet`, which zsh completions
+                // of the subcommands, where there is no `-i`, choke on.
+                .conflicts_with_all(&["open_line_number", "PATH", "stdin", "no_interactive", "quiet"])
+                .help("Asks which tracked file to open, and at which lines"),
+        )
+        .arg(
+            Arg::with_name("stdin")
+                .long("stdin")
+                .conflicts_with("open_line_number")
+                .help("Reads '<filepath>:<line-number>' lines (e.g. from `rg -n`) from stdin"),
+        )
+        .arg(
+            Arg::with_name("from_diagnostics")
+                .long("from-diagnostics")
+                .conflicts_with_all(&["open_line_number", "stdin"])
+                .help("Reads rustc or cargo output from stdin and links the code each '-->' points at"),
+        )
+        .arg(
+            Arg::with_name("permalink")
+                .long("permalink")
+                .help("Links to the current HEAD commit instead of the branch"),
+        )
+        .arg(
+            Arg::with_name("sourcegraph")
+                .long("sourcegraph")
+                .global(true)
+                .help("Links the repository, branches, files and commits on Sourcegraph"),
+        )
+        .arg(
+            Arg::with_name("raw")
+                .long("raw")
+                .help("Links to the raw content of files, e.g. to download them with curl"),
+        )
+        .arg(
+            Arg::with_name("push")
+                .long("push")
+                .global(true)
+                .help("Pushes the current branch before opening the repository or a file"),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .global(true)
+                .help("Refuses to link files with uncommitted changes or unpushed commits"),
+        )
+        .arg(
+            Arg::with_name("print")
+                .long("print")
+                .global(true)
+                .help("Prints the URL to stdout instead of opening it in the browser"),
+        )
+        .arg(
+            Arg::with_name("osc52")
+                .long("osc52")
+                .global(true)
+                .requires("copy")
+                .help("Copies through the terminal (OSC 52), which works over ssh"),
+        )
+        .arg(
+            Arg::with_name("browser")
+                .long("browser")
+                .global(true)
+                .takes_value(true)
+                .value_name("COMMAND")
+                .help("Opens the URL with this browser or command, e.g. 'firefox'"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .global(true)
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(Format::NAMES)
+                .help("Prints the link as markdown, html, org, json or a terminal hyperlink instead of opening it"),
+        )
+        .arg(
+            Arg::with_name("qr")
+                .long("qr")
+                .global(true)
+                .help("Prints the URL as a QR code, to open it on a phone, instead of opening it in the browser"),
+        )
+        .arg(
+            Arg::with_name("copy")
+                .long("copy")
+                .global(true)
+                .help("Copies the URL to the clipboard instead of opening it in the browser"),
+        )
+        .arg(
+            Arg::with_name("directory")
+                .short("C")
+                .value_name("PATH")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("Runs as if gitopen was started in PATH, like `git -C`"),
+        )
+        .arg(
+            Arg::with_name("remote")
+                .long("remote")
+                .value_name("NAME")
+                .takes_value(true)
+                .global(true)
+                .help("The remote to open, by default the current branch's remote or 'origin'"),
+        )
+        .arg(
+            Arg::with_name("upstream")
+                .long("upstream")
+                .global(true)
+                .conflicts_with("remote")
+                .help("Opens the 'upstream' remote, the project a fork was made from"),
+        )
+        .arg(
+            Arg::with_name("no_interactive")
+                .long("no-interactive")
+                .global(true)
+                .help("Never asks which remote to open, falls back to 'origin' instead"),
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .global(true)
+                .help("Prints the pushes and links gitopen would make instead of making them"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .multiple(true)
+                .global(true)
+                .help("Logs the git commands run and how the link was built, -vv their output too"),
+        )
+        .arg(
+            Arg::with_name("no_color")
+                .long("no-color")
+                .global(true)
+                .help("Prints without colors, which NO_COLOR also turns off"),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .global(true)
+                .conflicts_with_all(&["print", "qr", "format", "dry_run", "verbose"])
+                .help("Prints nothing, not even errors, for scripts that go by the exit code; never asks which remote to open"),
+        )
+        .subcommand(
+            SubCommand::with_name("commit")
+                .about("Opens the specified commit (any revision, e.g. a SHA, tag or HEAD~2), or compares a range like A..B")
+                .arg(
+                    Arg::with_name("REV")
+                        .help("Without a revision, asks which of the latest commits to open"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("branch")
+                .about("Opens the tree of the current branch, or of the given one")
+                .arg(Arg::with_name("NAME").help("The branch to open, e.g. 'feat/login'"))
+                .arg(
+                    Arg::with_name("pick")
+                        .short("i")
+                        .long("interactive")
+                        .conflicts_with("NAME")
+                        .help("Asks which of the local and remote branches to open"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("pr")
+                .about("Opens the pull request (merge request on GitLab) with the given number")
+                .arg(
+                    Arg::with_name("NUMBER")
+                        .help("Without a number, opens the pull request of the current branch"),
+                )
+                .subcommand(
+                    SubCommand::with_name("new")
+                        .about("Opens the page that creates a pull request from the current branch")
+                        .arg(
+                            Arg::with_name("title")
+                                .long("title")
+                                .takes_value(true)
+                                .help("Title of the pull request"),
+                        )
+                        .arg(
+                            Arg::with_name("body")
+                                .long("body")
+                                .takes_value(true)
+                                .help("Description of the pull request"),
+                        )
+                        .arg(
+                            Arg::with_name("base")
+                                .long("base")
+                                .value_name("BRANCH")
+                                .takes_value(true)
+                                .help("The branch to merge into, by default the default branch"),
+                        )
+                        .arg(
+                            Arg::with_name("fill")
+                                .long("fill")
+                                .help("Takes the title and body from the last commit's message"),
+                        )
+                        .arg(
+                            Arg::with_name("draft")
+                                .long("draft")
+                                .help("Opens the pull request as a draft"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("pr-for")
+                .about("Opens the pull request that introduced the given commit")
+                .arg(Arg::with_name("REV").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("change")
+                .about("Opens the Gerrit change of HEAD, or of the given commit, by its Change-Id")
+                .arg(Arg::with_name("REV").default_value("HEAD")),
+        )
+        .subcommand(
+            SubCommand::with_name("submodule")
+                .about("Opens the submodule at the commit the superproject records")
+                .arg(Arg::with_name("PATH").required(true)),
+        )
+        .subcommand(SubCommand::with_name("issues").about("Opens the issue tracker"))
+        .subcommand(
+            SubCommand::with_name("issue")
+                .about("Opens the issue with the given number")
+                .setting(AppSettings::SubcommandsNegateReqs)
+                .arg(Arg::with_name("NUMBER").required(true))
+                .subcommand(
+                    SubCommand::with_name("new")
+                        .about("Opens the new issue page, optionally prefilled")
+                        .arg(
+                            Arg::with_name("title")
+                                .long("title")
+                                .takes_value(true)
+                                .help("Title of the new issue"),
+                        )
+                        .arg(
+                            Arg::with_name("body")
+                                .long("body")
+                                .takes_value(true)
+                                .help("Body of the new issue"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("ci")
+                .about("Opens the CI results for the current branch and HEAD")
+                .arg(
+                    Arg::with_name("failed")
+                        .long("failed")
+                        .help("Opens the latest failed run of the branch (needs the api feature)"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("releases").about("Opens the releases page"))
+        .subcommand(
+            SubCommand::with_name("archive")
+                .about("Opens the download of the files at a branch, tag or commit, by default the current branch")
+                .arg(Arg::with_name("REF"))
+                .arg(
+                    // --format is taken by how links are printed.
+                    Arg::with_name("archive-format")
+                        .long("archive-format")
+                        .takes_value(true)
+                        .value_name("FORMAT")
+                        .possible_values(&archive_formats)
+                        .default_value("zip"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("release")
+                .about("Opens the release of a tag, by default the latest release")
+                .arg(Arg::with_name("TAG"))
+                .arg(
+                    Arg::with_name("containing")
+                        .long("containing")
+                        .takes_value(true)
+                        .value_name("COMMIT")
+                        .conflicts_with("TAG")
+                        .help("Opens the first release with the commit"),
+                ),
+        )
+        .subcommands(ProjectPage::ALL.iter().map(|page| {
+            SubCommand::with_name(page.name()).about(match page {
+                ProjectPage::Discussions => "Opens the discussions",
+                ProjectPage::Projects => "Opens the project boards",
+                ProjectPage::Security => "Opens the security overview",
+                ProjectPage::Insights => "Opens the activity insights",
+                ProjectPage::Contributors => "Opens the contributors and their commits",
+                ProjectPage::Graph => "Opens the commit graph of the branches",
+            })
+        }))
+        .subcommand(
+            SubCommand::with_name("settings")
+                .about("Opens the repository settings, or one section of them")
+                .arg(Arg::with_name("SECTION").possible_values(&settings_sections)),
+        )
+        .subcommand(
+            SubCommand::with_name("wiki")
+                .about("Opens the wiki, or the page with the given title")
+                .arg(Arg::with_name("PAGE").multiple(true)),
+        )
+        .subcommand(SubCommand::with_name("tags").about("Opens the tags page"))
+        .subcommand(
+            SubCommand::with_name("tag")
+                .about("Opens the release (or tag) page of the given tag")
+                .arg(Arg::with_name("TAG").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("blame")
+                .about("Opens the blame view of a file at a line")
+                .arg(
+                    Arg::with_name("PATH AND LINE")
+                        .required(true)
+                        .help("'<filepath>:<line-number>'"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("edit")
+                .about("Opens a file in the web editor, e.g. github.dev or the GitLab Web IDE")
+                .arg(
+                    Arg::with_name("PATH AND LINE")
+                        .required(true)
+                        .help("'<filepath>[:<line-number>]'"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("last-change")
+                .about("Opens the commit that last changed a line")
+                .arg(
+                    Arg::with_name("PATH")
+                        .required(true)
+                        .help("'<filepath>:<line-number>'"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("Opens the commit history of a file")
+                .arg(Arg::with_name("PATH").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("user")
+                .about("Opens the profile of a user")
+                .arg(Arg::with_name("NAME").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("org")
+                .about("Opens the page of an organization, by default the repository's owner")
+                .arg(Arg::with_name("NAME")),
+        )
+        .subcommand(
+            SubCommand::with_name("grep")
+                .about("Opens the provider's code search of the repository")
+                .arg(Arg::with_name("PATTERN").required(true))
+                .arg(Arg::with_name("PATH").help("Only searches below this path"))
+                .arg(
+                    Arg::with_name("current_branch")
+                        .long("current-branch")
+                        .help("Searches the current branch instead of the default branch"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("Opens the diff between two refs, by default the default branch and the current branch")
+                .arg(Arg::with_name("RANGE").help("'<base>..<head>'")),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Gets, sets and lists the settings of the config file and the repository")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("get")
+                        .about("Prints a setting, e.g. 'browser' or 'hosts.git.corp.com.commit'")
+                        .arg(Arg::with_name("KEY").required(true))
+                        .args(&scope_args),
+                )
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Sets a setting, in the config file unless --repo is given")
+                        .arg(Arg::with_name("KEY").required(true))
+                        .arg(Arg::with_name("VALUE").required(true))
+                        .args(&scope_args),
+                )
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("Prints the settings gitopen goes by, or those of one scope")
+                        .args(&scope_args),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Checks the repository, remote, provider and browser gitopen uses, and says how to fix what's wrong"),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("Prints the completions of a shell, e.g. 'gitopen completions bash >> ~/.bashrc'")
+                .arg(
+                    Arg::with_name("SHELL")
+                        .required(true)
+                        .possible_values(SHELLS),
+                ),
+        );
+    // The completions call `gitopen complete` for the branches, remotes and
+    // files to offer, and don't offer it themselves.
+    let matches = app
+        .clone()
+        .subcommand(
+            SubCommand::with_name("complete")
+                .setting(AppSettings::Hidden)
+                .about("Prints the branches, remotes or files the completions offer")
+                .arg(
+                    Arg::with_name("KIND")
+                        .required(true)
+                        .possible_values(&["branches", "remotes", "files"]),
+                ),
+        )
         .get_matches();
+    init_logging(matches.occurrences_of("verbose"));
+    set_quiet(matches.is_present("quiet"));
+    set_color(matches.is_present("no_color"));
+    if let Some(completions_matches) = matches.subcommand_matches("completions") {
+        let shell = completions_matches.value_of("SHELL").unwrap_or_default();
+        let shell_kind: Shell = shell.parse().map_err(|err: String| anyhow!(err))?;
+        let mut generated = Vec::new();
+        app.gen_completions_to("gitopen", shell_kind, &mut generated);
+        print!(
+            "{}",
+            with_dynamic_completions(shell, &String::from_utf8_lossy(&generated))
+        );
+        return Ok(());
+    }
+    // Each `-C` is relative to the one before, paths given later to the
+    // directory they lead to, the way git treats them.
+    for dir in matches.values_of("directory").into_iter().flatten() {
+        env::set_current_dir(dir).map_err(|err| anyhow!("Cannot change to '{}': {}", dir, err))?;
+    }
+    if let Some(complete_matches) = matches.subcommand_matches("complete") {
+        // Completing outside a repository offers nothing rather than errors.
+        let kind = complete_matches.value_of("KIND").unwrap_or_default();
+        for candidate in completion_candidates(&SystemGit, kind).unwrap_or_default() {
+            println!("{}", candidate);
+        }
+        return Ok(());
+    }
+    let system_git = SystemGit;
+    system_git.prefetch_config();
+    let dry_run = DryRun { git: &system_git };
+    let git: &dyn GitBackend = if matches.is_present("dry_run") {
+        &dry_run
+    } else {
+        &system_git
+    };
+    let remote = RemoteSelection {
+        name: if matches.is_present("upstream") {
+            Some("upstream")
+        } else {
+            matches.value_of("remote")
+        },
+        interactive: !matches.is_present("no_interactive") && !matches.is_present("quiet"),
+    };
+    if let Some(config_matches) = matches.subcommand_matches("config") {
+        let scope = |scope_matches: &clap::ArgMatches| {
+            if scope_matches.is_present("repo") {
+                Some(Scope::Repo)
+            } else if scope_matches.is_present("global") {
+                Some(Scope::Global)
+            } else {
+                None
+            }
+        };
+        return match config_matches.subcommand() {
+            ("get", Some(get_matches)) => get_setting(
+                git,
+                get_matches.value_of("KEY").unwrap_or_default(),
+                scope(get_matches),
+            ),
+            ("set", Some(set_matches)) => set_setting(
+                git,
+                set_matches.value_of("KEY").unwrap_or_default(),
+                set_matches.value_of("VALUE").unwrap_or_default(),
+                scope(set_matches).unwrap_or(Scope::Global),
+                matches.is_present("dry_run"),
+            ),
+            (_, list_matches) => list_settings(git, list_matches.and_then(scope)),
+        };
+    }
+    // Before the browser is looked up, which needs a config the doctor may
+    // find broken.
+    if matches.subcommand_matches("doctor").is_some() {
+        return run_doctor(git, remote, matches.value_of("browser"));
+    }
+    let opens_browser = !["print", "copy", "format", "qr"]
+        .iter()
+        .any(|arg| matches.is_present(arg));
+    let browser = match matches.value_of("browser") {
+        Some(browser) => Some(browser.to_string()),
+        None if opens_browser => get_browser(git)?,
+        None => None,
+    };
+    let format = match matches.value_of("format") {
+        Some(format) => Some(format.to_string()),
+        None => get_format(git)?,
+    };
+    let output = Output {
+        print: matches.is_present("print"),
+        copy: matches.is_present("copy"),
+        osc52: matches.is_present("osc52"),
+        format: Format::from_name(format.as_deref().unwrap_or("url"))?,
+        browser,
+        dry_run: matches.is_present("dry_run"),
+        qr: matches.is_present("qr"),
+    };
+    let link_options = LinkOptions {
+        permalink: matches.is_present("permalink"),
+        strict: matches.is_present("strict"),
+        push: matches.is_present("push"),
+        raw: matches.is_present("raw"),
+        sourcegraph: matches.is_present("sourcegraph"),
+        // Only files given without a line use it, which look it up.
+        ..LinkOptions::default()
+    };
     if matches.is_present("push_and_pr") {
-        push_and_open_pr()?;
+        let push_options = PushOptions {
+            force_with_lease: matches.is_present("force_with_lease"),
+            push_options: matches
+                .values_of("push_option")
+                .map(Iterator::collect)
+                .unwrap_or_default(),
+        };
+        push_and_open_pr(git, &push_options, remote, &output)?;
         Ok(())
     } else if matches.is_present("open_commit") {
         open_commit(
+            git,
             matches
                 .value_of("open_commit")
                 .ok_or_else(|| anyhow!("Must supply a commit SHA"))?,
+            link_options,
+            remote,
+            &output,
         )
     } else if matches.is_present("open_line_number") {
         open_at_line_number(
-            matches
+            git,
+            &[matches
                 .value_of("open_line_number")
-                .ok_or_else(|| anyhow!("Please supply '<filepath>:<line-number>'"))?,
+                .ok_or_else(|| anyhow!("Please supply '<filepath>:<line-number>'"))?],
+            link_options,
+            remote,
+            &output,
         )?;
         Ok(())
+    } else if matches.is_present("pick_file") {
+        let file = pick_file(git)?;
+        open_at_line_number(git, &[&file], link_options, remote, &output)
+    } else if let Some(commit_matches) = matches.subcommand_matches("commit") {
+        let rev = match commit_matches.value_of("REV") {
+            Some(rev) => rev.to_string(),
+            None => pick_commit(git, remote.interactive)?,
+        };
+        open_commit(git, &rev, link_options, remote, &output)
+    } else if let Some(branch_matches) = matches.subcommand_matches("branch") {
+        open_branch(
+            git,
+            branch_matches.value_of("NAME"),
+            branch_matches.is_present("pick"),
+            link_options,
+            remote,
+            &output,
+        )
+    } else if let Some(pr_matches) = matches.subcommand_matches("pr") {
+        if let Some(new_matches) = pr_matches.subcommand_matches("new") {
+            let options = NewPrOptions {
+                title: new_matches.value_of("title"),
+                body: new_matches.value_of("body"),
+                base: new_matches.value_of("base"),
+                fill: new_matches.is_present("fill"),
+                draft: new_matches.is_present("draft"),
+            };
+            return open_new_pr(git, options, remote, &output);
+        }
+        match pr_matches.value_of("NUMBER") {
+            Some(pr_number) => open_pr(git, pr_number, remote, &output),
+            None => open_branch_pr(git, remote, &output),
+        }
+    } else if let Some(pr_for_matches) = matches.subcommand_matches("pr-for") {
+        open_pr_for_commit(
+            git,
+            pr_for_matches
+                .value_of("REV")
+                .ok_or_else(|| anyhow!("Must supply a commit"))?,
+            remote,
+            &output,
+        )
+    } else if let Some(change_matches) = matches.subcommand_matches("change") {
+        open_change(
+            git,
+            change_matches.value_of("REV").unwrap_or("HEAD"),
+            remote,
+            &output,
+        )
+    } else if let Some(submodule_matches) = matches.subcommand_matches("submodule") {
+        open_submodule(
+            git,
+            submodule_matches
+                .value_of("PATH")
+                .ok_or_else(|| anyhow!("Must supply a submodule path"))?,
+            remote,
+            &output,
+        )
+    } else if matches.subcommand_matches("issues").is_some() {
+        open_issues(git, remote, &output)
+    } else if let Some(issue_matches) = matches.subcommand_matches("issue") {
+        if let Some(new_matches) = issue_matches.subcommand_matches("new") {
+            return open_new_issue(
+                git,
+                new_matches.value_of("title"),
+                new_matches.value_of("body"),
+                remote,
+                &output,
+            );
+        }
+        open_issue(
+            git,
+            issue_matches
+                .value_of("NUMBER")
+                .ok_or_else(|| anyhow!("Must supply an issue number"))?,
+            remote,
+            &output,
+        )
+    } else if let Some(ci_matches) = matches.subcommand_matches("ci") {
+        open_ci(git, ci_matches.is_present("failed"), remote, &output)
+    } else if matches.subcommand_matches("releases").is_some() {
+        open_releases(git, remote, &output)
+    } else if let Some(archive_matches) = matches.subcommand_matches("archive") {
+        open_archive(
+            git,
+            archive_matches.value_of("REF"),
+            ArchiveFormat::from_name(archive_matches.value_of("archive-format").unwrap_or("zip"))?,
+            link_options,
+            remote,
+            &output,
+        )
+    } else if let Some(release_matches) = matches.subcommand_matches("release") {
+        open_release(
+            git,
+            release_matches.value_of("TAG"),
+            release_matches.value_of("containing"),
+            remote,
+            &output,
+        )
+    } else if let Some(page) = ProjectPage::ALL
+        .iter()
+        .find(|page| matches.subcommand_matches(page.name()).is_some())
+    {
+        open_project_page(git, *page, remote, &output)
+    } else if let Some(settings_matches) = matches.subcommand_matches("settings") {
+        let section = settings_matches
+            .value_of("SECTION")
+            .map(SettingsSection::from_name)
+            .transpose()?;
+        open_settings(git, section, remote, &output)
+    } else if let Some(wiki_matches) = matches.subcommand_matches("wiki") {
+        let page = wiki_matches
+            .values_of("PAGE")
+            .map(|words| words.collect::<Vec<_>>().join(" "));
+        open_wiki(git, page.as_deref(), remote, &output)
+    } else if matches.subcommand_matches("tags").is_some() {
+        open_tags(git, remote, &output)
+    } else if let Some(tag_matches) = matches.subcommand_matches("tag") {
+        open_tag(
+            git,
+            tag_matches
+                .value_of("TAG")
+                .ok_or_else(|| anyhow!("Must supply a tag"))?,
+            remote,
+            &output,
+        )
+    } else if let Some(blame_matches) = matches.subcommand_matches("blame") {
+        open_blame(
+            git,
+            blame_matches
+                .value_of("PATH AND LINE")
+                .ok_or_else(|| anyhow!("Please supply '<filepath>:<line-number>'"))?,
+            link_options,
+            remote,
+            &output,
+        )
+    } else if let Some(edit_matches) = matches.subcommand_matches("edit") {
+        open_edit(
+            git,
+            edit_matches
+                .value_of("PATH AND LINE")
+                .ok_or_else(|| anyhow!("Please supply '<filepath>[:<line-number>]'"))?,
+            git.user_config()?.editor.as_deref(),
+            link_options,
+            remote,
+            &output,
+        )
+    } else if let Some(last_change_matches) = matches.subcommand_matches("last-change") {
+        open_last_change(
+            git,
+            last_change_matches
+                .value_of("PATH")
+                .ok_or_else(|| anyhow!("Must supply a path and line"))?,
+            link_options,
+            remote,
+            &output,
+        )
+    } else if let Some(history_matches) = matches.subcommand_matches("history") {
+        open_history(
+            git,
+            history_matches
+                .value_of("PATH")
+                .ok_or_else(|| anyhow!("Must supply a path"))?,
+            link_options,
+            remote,
+            &output,
+        )
+    } else if let Some(user_matches) = matches.subcommand_matches("user") {
+        open_user(
+            git,
+            user_matches
+                .value_of("NAME")
+                .ok_or_else(|| anyhow!("Must supply a user"))?,
+            remote,
+            &output,
+        )
+    } else if let Some(org_matches) = matches.subcommand_matches("org") {
+        open_org(git, org_matches.value_of("NAME"), remote, &output)
+    } else if let Some(grep_matches) = matches.subcommand_matches("grep") {
+        open_code_search(
+            git,
+            grep_matches
+                .value_of("PATTERN")
+                .ok_or_else(|| anyhow!("Must supply a pattern"))?,
+            grep_matches.value_of("PATH"),
+            grep_matches.is_present("current_branch"),
+            remote,
+            &output,
+        )
+    } else if let Some(compare_matches) = matches.subcommand_matches("compare") {
+        open_compare(git, compare_matches.value_of("RANGE"), remote, &output)
+    } else if let Some(paths) = matches.values_of("PATH") {
+        let paths: Vec<&str> = paths.collect();
+        let link_options = LinkOptions {
+            file_action: get_file_action(git)?,
+            ..link_options
+        };
+        open_at_line_number(git, &paths, link_options, remote, &output)
+    } else if matches.is_present("stdin") {
+        open_from_stdin(git, link_options, remote, &output)
+    } else if matches.is_present("from_diagnostics") {
+        open_from_diagnostics(git, link_options, remote, &output)
     } else {
-        open_repo()?;
-        Ok(())
+        open_default(git, link_options, remote, &output)
     }
 }
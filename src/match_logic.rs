@@ -1,19 +1,54 @@
+use crate::error::GitOpenError;
+use crate::providers::{GitRef, Provider};
 use anyhow::anyhow;
 use anyhow::Result as AnyhowResult;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use regex::Regex;
-use std::process::{Command, Stdio};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use tracing::trace;
+
+/// Everything but the unreserved characters of RFC 3986.
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
 
 #[derive(Debug, PartialEq)]
 pub struct FileAtLine<'a> {
     pub filepath: &'a str,
-    pub line_number: &'a str,
+    pub line_number: Option<&'a str>,
+    pub end_line_number: Option<&'a str>,
+    /// The column on `line_number`, as compilers point at it.
+    pub column: Option<&'a str>,
 }
 
 impl<'a> FileAtLine<'a> {
-    pub fn new(filepath: &'a str, line_number: &'a str) -> Self {
+    pub fn new(
+        filepath: &'a str,
+        line_number: Option<&'a str>,
+        end_line_number: Option<&'a str>,
+    ) -> Self {
         Self {
             filepath,
             line_number,
+            end_line_number,
+            column: None,
+        }
+    }
+}
+
+/// Writes the location the way it was given: `<path>[:<line>[-<end>]]`, or
+/// `<path>:<line>:<column>`.
+impl fmt::Display for FileAtLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.filepath)?;
+        match (self.line_number, self.end_line_number, self.column) {
+            (Some(line), Some(end_line), _) => write!(f, ":{}-{}", line, end_line),
+            (Some(line), None, Some(column)) => write!(f, ":{}:{}", line, column),
+            (Some(line), None, None) => write!(f, ":{}", line),
+            _ => Ok(()),
         }
     }
 }
@@ -32,74 +67,706 @@ fn remove_git_suffix(s: &str) -> &str {
     }
 }
 
+/// Splits a non-http remote into its protocol, host and repository path.
+/// Handles `<protocol>://[user@]host[:port]/path` for the ssh and git
+/// protocols as well as the scp-like `[user@]host:path`, which is ssh.
+fn split_remote(remote_url: &str) -> Option<(&str, &str, &str)> {
+    let url_re = Regex::new(
+        r"^(ssh|git|git\+ssh|ssh\+git)://(?:[^@/]+@)?(\[[^\]/]+\]|[^:/]+)(?::\d*)?/+(.+?)/*$",
+    )
+    .ok()?;
+    if let Some(parts) = url_re.captures(remote_url) {
+        let (protocol, host, path) = (
+            parts.get(1)?.as_str(),
+            parts.get(2)?.as_str(),
+            parts.get(3)?.as_str(),
+        );
+        trace!(protocol, host, path, "{} matched as a URL", remote_url);
+        return Some((protocol, host, path));
+    }
+    let scp_re = Regex::new(r"^(?:[^@/:]+@)?([\w\.-]+):/*(.+?)/*$").ok()?;
+    let parts = match scp_re.captures(remote_url) {
+        Some(parts) => parts,
+        None => {
+            trace!("{} matched neither a URL nor host:path", remote_url);
+            return None;
+        }
+    };
+    let (host, path) = (parts.get(1)?.as_str(), parts.get(2)?.as_str());
+    trace!(host, path, "{} matched as scp-like host:path", remote_url);
+    Some(("ssh", host, path))
+}
+
 pub fn parse_url_from_git(s: &str) -> AnyhowResult<String> {
-    let re = Regex::new(r"((git|ssh|http(s)?)|(git@[\w\.-]+))(:(//)?)([\w\.@:/\-~]+)(\.git)?(/)?")?;
-    let url_parts = re
-        .captures(s)
-        .ok_or_else(|| anyhow!("Git repository not found"))?;
-    if is_https(&url_parts[1]) {
-        return Ok(s.trim().to_string());
-    }
-    let domain_re = Regex::new(r".*@(.*)$")?;
-    let match_domain = domain_re
-        .captures(&url_parts[1])
-        .ok_or_else(|| anyhow!("Regex error capturing ssh domain"))?;
-
-    let result: String =
-        "https://".to_string() + &match_domain[1] + r"/" + remove_git_suffix(&url_parts[7]);
+    let s = s.trim();
+    if let Some(remote) = s.strip_prefix("codecommit:") {
+        return get_codecommit_url(remote);
+    }
+    if is_https(s) {
+        if get_host(s) == "dev.azure.com" {
+            return Ok(remove_azure_devops_user(s));
+        }
+        return Ok(s.to_string());
+    }
+    let (_, host, path) =
+        split_remote(s).ok_or_else(|| GitOpenError::UnparseableRemote { url: s.to_string() })?;
+
+    if host == "ssh.dev.azure.com" {
+        return get_azure_devops_url(path);
+    }
+
+    let result: String = "https://".to_string() + host + r"/" + remove_git_suffix(path);
     Ok(result)
 }
 
-pub fn get_commit_link(repo_url: String, commit_sha: &str) -> String {
-    repo_url + "/commit/" + commit_sha
+/// The host an ssh remote connects to, which may be an alias from
+/// `~/.ssh/config`. Returns `None` for http(s) and git protocol remotes.
+pub fn get_ssh_host(remote_url: &str) -> Option<&str> {
+    let remote_url = remote_url.trim();
+    if is_https(remote_url) || remote_url.starts_with("codecommit:") {
+        return None;
+    }
+    match split_remote(remote_url)? {
+        ("git", _, _) => None,
+        (_, host, _) => Some(host),
+    }
+}
+
+/// Azure DevOps https remotes embed the organization as a username
+/// (`https://org@dev.azure.com/...`), which the web UI doesn't want.
+fn remove_azure_devops_user(s: &str) -> String {
+    match (s.find("://"), s.find('@')) {
+        (Some(scheme_end), Some(at)) if at > scheme_end => {
+            s[..scheme_end + 3].to_string() + &s[at + 1..]
+        }
+        _ => s.to_string(),
+    }
 }
 
-pub fn parse_path_and_line_arg(arg: &str, split_char: char) -> AnyhowResult<FileAtLine<'_>> {
-    if arg.contains(split_char) {
-        let mut iterator = arg.split(split_char);
-        let file_at_line = FileAtLine::new(
-            iterator.next().ok_or_else(|| {
-                anyhow!(format!(
-                    "Error parsing input. Format is <path-to-file>{}<line-number>",
-                    split_char
-                ))
-            })?,
-            iterator.next().ok_or_else(|| {
-                anyhow!(format!(
-                    "Error parsing input. Format is <path-to-file>{}<line-number>",
-                    split_char
-                ))
-            })?,
-        );
-        return Ok(file_at_line);
-    }
-    Err(anyhow!(format!(
-        "Split character not found! Format is <path-to-file>{}<line-number>",
-        split_char
-    )))
-}
-
-fn get_current_branch_name() -> AnyhowResult<String> {
-    let git_branch = Command::new("git")
-        .args(["symbolic-ref", "--short", "HEAD"])
-        .stdout(Stdio::piped())
-        .output()?;
-    let stdout = String::from_utf8(git_branch.stdout)?.trim_end().to_string();
-
-    Ok(stdout)
-}
-
-pub fn get_line_number_link(repo_url: &str, path: &str, line_number: &str) -> AnyhowResult<String> {
-    let current_branch = get_current_branch_name()?;
+/// Azure DevOps ssh remotes look like `git@ssh.dev.azure.com:v3/org/project/repo`,
+/// while the web page lives at `https://dev.azure.com/org/project/_git/repo`.
+fn get_azure_devops_url(ssh_path: &str) -> AnyhowResult<String> {
+    let parts: Vec<&str> = remove_git_suffix(ssh_path).split('/').collect();
+    match parts.as_slice() {
+        ["v3", org, project, repo] => Ok(format!(
+            "https://dev.azure.com/{}/{}/_git/{}",
+            org, project, repo
+        )),
+        _ => Err(anyhow!(
+            "Unexpected Azure DevOps repository path: {}",
+            ssh_path
+        )),
+    }
+}
+
+/// git-remote-codecommit remotes look like `codecommit::<region>://[profile@]repo`,
+/// after the `codecommit:` that picks the helper, and stand for
+/// `https://git-codecommit.<region>.amazonaws.com/v1/repos/repo`. Without a
+/// region, `codecommit://repo`, it comes from the AWS profile.
+fn get_codecommit_url(remote: &str) -> AnyhowResult<String> {
+    let (region, repo) = match remote.strip_prefix("//") {
+        Some(repo) => ("", repo),
+        None => remote
+            .strip_prefix(':')
+            .and_then(|remote| remote.split_once("://"))
+            .ok_or_else(|| anyhow!("Unexpected CodeCommit remote: codecommit:{}", remote))?,
+    };
+    let repo = match repo.rfind('@') {
+        Some(idx) => &repo[idx + 1..],
+        None => repo,
+    };
+    if region.is_empty() {
+        return Err(anyhow!(
+            "The CodeCommit remote codecommit:{} doesn't name its region, use codecommit::<region>://{}",
+            remote,
+            repo
+        ));
+    }
+    if repo.is_empty() {
+        return Err(anyhow!(
+            "Unexpected CodeCommit remote: codecommit:{}",
+            remote
+        ));
+    }
     Ok(format!(
-        "{}/blob/{}/{}#L{}",
-        repo_url, current_branch, path, line_number,
+        "https://git-codecommit.{}.amazonaws.com/v1/repos/{}",
+        region, repo
     ))
 }
 
+/// Returns the host part of a web URL, without scheme, credentials or port.
+pub fn get_host(repo_url: &str) -> &str {
+    let without_scheme = match repo_url.find("://") {
+        Some(idx) => &repo_url[idx + 3..],
+        None => repo_url,
+    };
+    let authority = without_scheme.split('/').next().unwrap_or_default();
+    let host = match authority.rfind('@') {
+        Some(idx) => &authority[idx + 1..],
+        None => authority,
+    };
+    host.split(':').next().unwrap_or_default()
+}
+
+/// `repo_url` with its host, the part [`get_host`] returns, swapped for
+/// `host`.
+pub fn replace_host(repo_url: &str, host: &str) -> String {
+    let start = repo_url.find("://").map_or(0, |idx| idx + 3);
+    let authority_end = repo_url[start..]
+        .find('/')
+        .map_or(repo_url.len(), |idx| start + idx);
+    let host_start = repo_url[start..authority_end]
+        .rfind('@')
+        .map_or(start, |idx| start + idx + 1);
+    let host_end = repo_url[host_start..authority_end]
+        .find(':')
+        .map_or(authority_end, |idx| host_start + idx);
+    format!(
+        "{}{}{}",
+        &repo_url[..host_start],
+        host,
+        &repo_url[host_end..]
+    )
+}
+
+/// Returns the repository web URL with any trailing slash and `.git` suffix removed.
+pub fn get_repo_base(repo_url: &str) -> &str {
+    remove_git_suffix(repo_url.trim().trim_end_matches('/'))
+}
+
+/// Splits a repository web URL into its origin (`https://host`) and the
+/// project path, e.g. `group/subgroup/project`.
+pub fn split_repo_url(repo_url: &str) -> (&str, &str) {
+    let base = get_repo_base(repo_url);
+    let path_start = match base.find("://") {
+        Some(idx) => idx + 3,
+        None => 0,
+    };
+    match base[path_start..].find('/') {
+        Some(idx) => (&base[..path_start + idx], &base[path_start + idx + 1..]),
+        None => (base, ""),
+    }
+}
+
+/// The namespace a repository is in: its owner, or on GitLab the group
+/// with its subgroups.
+pub fn repo_owner(repo_url: &str) -> Option<&str> {
+    let (owner, _) = split_repo_url(repo_url).1.rsplit_once('/')?;
+    Some(owner).filter(|owner| !owner.is_empty())
+}
+
+/// Bitbucket Server clones over `https://host/scm/KEY/repo.git` but serves
+/// its web pages under `https://host/projects/KEY/repos/repo`.
+pub fn get_bitbucket_server_base(repo_url: &str) -> AnyhowResult<String> {
+    let (origin, project_path) = split_repo_url(repo_url);
+    let project_path = project_path.strip_prefix("scm/").unwrap_or(project_path);
+    let mut parts = project_path.split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(key), Some(repo), None) if !key.is_empty() && !repo.is_empty() => Ok(format!(
+            "{}/projects/{}/repos/{}",
+            origin,
+            key.to_uppercase(),
+            repo
+        )),
+        _ => Err(anyhow!(
+            "Unexpected Bitbucket Server repository path: {}",
+            project_path
+        )),
+    }
+}
+
+/// Returns the Azure DevOps project URL (`https://dev.azure.com/org/project`)
+/// for a repository URL, which is where project-wide pages like work items live.
+pub fn get_azure_devops_project_base(repo_url: &str) -> &str {
+    let base = get_repo_base(repo_url);
+    match base.find("/_git/") {
+        Some(idx) => &base[..idx],
+        None => base,
+    }
+}
+
+/// sourcehut keeps a repository's tickets on the todo service next to the git
+/// one, e.g. `https://todo.sr.ht/~user/repo` for `https://git.sr.ht/~user/repo`,
+/// in a tracker that is usually named after the repository.
+pub fn get_sourcehut_tracker_base(repo_url: &str) -> AnyhowResult<String> {
+    let (origin, project_path) = split_repo_url(repo_url);
+    if project_path.is_empty() {
+        return Err(anyhow!("Unexpected sourcehut repository URL: {}", repo_url));
+    }
+    match origin.find("://git.") {
+        Some(idx) => Ok(format!(
+            "{}://todo.{}/{}",
+            &origin[..idx],
+            &origin[idx + "://git.".len()..],
+            project_path
+        )),
+        None => Err(anyhow!(
+            "Can't tell the todo.sr.ht instance of {}",
+            get_host(repo_url)
+        )),
+    }
+}
+
+/// How a file in the working tree differs from what was committed.
+#[derive(Debug, PartialEq)]
+pub enum FileStatus {
+    Clean,
+    Modified,
+    Untracked,
+}
+
+/// Reads the status of a single file from `git status --porcelain -- <path>`.
+pub fn parse_file_status(porcelain: &str) -> FileStatus {
+    match porcelain.lines().next() {
+        None => FileStatus::Clean,
+        Some(line) if line.starts_with("??") => FileStatus::Untracked,
+        Some(_) => FileStatus::Modified,
+    }
+}
+
+/// Returns whether the input is a plain number, like a PR or issue number.
+pub fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Splits `<base>..<head>` (or `<base>...<head>`) into its two refs.
+pub fn parse_compare_range(arg: &str) -> AnyhowResult<(&str, &str)> {
+    let (base, head) = arg
+        .split_once("...")
+        .or_else(|| arg.split_once(".."))
+        .ok_or_else(|| anyhow!("Expected a range like <base>..<head>, got '{}'", arg))?;
+    if base.is_empty() || head.is_empty() {
+        return Err(anyhow!(
+            "Expected a range like <base>..<head>, got '{}'",
+            arg
+        ));
+    }
+    Ok((base, head))
+}
+
+/// Builds a `?key=value&...` query string with the values percent-encoded.
+/// Returns an empty string when there are no parameters.
+pub fn build_query_string(params: &[(&str, &str)]) -> String {
+    if params.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, utf8_percent_encode(value, QUERY_VALUE)))
+        .collect();
+    format!("?{}", pairs.join("&"))
+}
+
+/// Percent-encodes a single URL path segment, including any `/` in it.
+/// Used where a branch name has to stay one segment, as on Bitbucket.
+pub fn encode_segment(segment: &str) -> String {
+    utf8_percent_encode(segment, QUERY_VALUE).to_string()
+}
+
+/// Percent-encodes every segment of a file path or ref name but keeps the
+/// `/` separators, so `feat/a#b` and `docs/read me.md` stay readable paths.
+pub fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(encode_segment)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The slug wikis name a page by, from its title: words joined with `-`, so
+/// `Getting  started` is `Getting-started`. Case is kept, as GitHub, GitLab
+/// and Gitea do.
+pub fn wiki_slug(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+/// Strips the leading `/` or `./` users tend to type in front of repo paths,
+/// as well as trailing slashes of directories. `.` is the repository root.
+pub fn normalize_repo_path(path: &str) -> &str {
+    let path = path
+        .trim_start_matches("./")
+        .trim_start_matches('/')
+        .trim_end_matches('/');
+    if path == "." {
+        ""
+    } else {
+        path
+    }
+}
+
+/// Resolves `.` and `..` without touching the filesystem, so paths to files
+/// that only exist on the remote still work.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn to_slash_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Turns a path given on the command line (relative to `cwd`, or absolute)
+/// into a path relative to the repository root `toplevel`. Paths that end up
+/// outside the repository, like `/src/main.rs`, are taken as relative to the
+/// root, which is what they always meant to gitopen.
+pub fn resolve_repo_path(input: &str, cwd: &Path, toplevel: &Path) -> String {
+    let absolute = normalize_lexically(&cwd.join(input));
+    match absolute.strip_prefix(toplevel) {
+        Ok(relative) => to_slash_path(relative),
+        Err(_) => normalize_repo_path(input).to_string(),
+    }
+}
+
+/// GitLab puts every project page below `/-/`, so the project path (with
+/// its subgroups) never clashes with the blob route.
+pub fn get_gitlab_blob_link(repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+    format!(
+        "{}/-/blob/{}/{}",
+        get_repo_base(repo_url),
+        encode_path(git_ref.name()),
+        encode_path(normalize_repo_path(path))
+    )
+}
+
+pub fn get_commit_link(provider: &dyn Provider, repo_url: &str, commit_sha: &str) -> String {
+    provider.commit_url(repo_url, commit_sha)
+}
+
+/// Parses `<path>`, `<path>:<line>` as well as line ranges written as
+/// `<path>:<start>-<end>`, and the `<path>:<line>:<column>` of compiler
+/// messages. Anything else after a second split character is ignored, which
+/// lets `grep -n` output (`<path>:<line>:<content>`) through.
+pub fn parse_path_and_line_arg(arg: &str, split_char: char) -> AnyhowResult<FileAtLine<'_>> {
+    let format_error = || {
+        anyhow!(format!(
+            "Error parsing input. Format is <path-to-file>[{}<line-number>]",
+            split_char
+        ))
+    };
+    let mut iterator = arg.split(split_char);
+    let filepath = iterator.next().ok_or_else(format_error)?;
+    if filepath.is_empty() {
+        return Err(format_error());
+    }
+    let file_at_line = match iterator.next() {
+        None => FileAtLine::new(filepath, None, None),
+        Some("") => return Err(format_error()),
+        Some(lines) => match lines.split_once('-') {
+            Some((start, end)) => {
+                if !is_number(start) || !is_number(end) {
+                    return Err(anyhow!(
+                        "'{}' is not a line range, write it like 10-25",
+                        lines
+                    ));
+                }
+                if end.parse::<u64>()? < start.parse::<u64>()? {
+                    return Err(anyhow!("The line range {} ends before it starts", lines));
+                }
+                FileAtLine::new(filepath, Some(start), Some(end))
+            }
+            None if !is_number(lines) => {
+                return Err(anyhow!("'{}' is not a line number", lines));
+            }
+            None => FileAtLine {
+                column: iterator.next().filter(|column| is_number(column)),
+                ..FileAtLine::new(filepath, Some(lines), None)
+            },
+        },
+    };
+    Ok(file_at_line)
+}
+
+/// The `<path>:<line>:<column>` of a `--> src/main.rs:12:34` line, with
+/// which rustc and cargo point at the code an error or warning is about.
+pub fn diagnostic_location(line: &str) -> Option<&str> {
+    let location = line.trim_start().strip_prefix("--> ")?.trim();
+    Some(location).filter(|location| !location.is_empty())
+}
+
+/// The blob view of a file, anchored at its lines when there are any.
+pub fn get_line_number_link(
+    provider: &dyn Provider,
+    repo_url: &str,
+    git_ref: &GitRef,
+    file_at_line: &FileAtLine,
+) -> String {
+    let path = normalize_repo_path(file_at_line.filepath);
+    if let (Some(line_number), None, Some(column)) = (
+        file_at_line.line_number,
+        file_at_line.end_line_number,
+        file_at_line.column,
+    ) {
+        if let Some(anchor) = provider.line_column_anchor(line_number, column) {
+            return provider.blob_url(repo_url, git_ref, path) + &anchor;
+        }
+    }
+    match file_at_line.line_number {
+        Some(line_number) => provider.line_number_url(
+            repo_url,
+            git_ref,
+            path,
+            line_number,
+            file_at_line.end_line_number,
+        ),
+        None => provider.blob_url(repo_url, git_ref, path),
+    }
+}
+
+/// The raw content of a file. Lines can't be pointed at there, so they
+/// are left out.
+pub fn get_raw_link(
+    provider: &dyn Provider,
+    repo_url: &str,
+    git_ref: &GitRef,
+    file_at_line: &FileAtLine,
+) -> AnyhowResult<String> {
+    provider.raw_url(
+        repo_url,
+        git_ref,
+        normalize_repo_path(file_at_line.filepath),
+    )
+}
+
+pub fn get_blame_link(
+    provider: &dyn Provider,
+    repo_url: &str,
+    git_ref: &GitRef,
+    file_at_line: &FileAtLine,
+) -> AnyhowResult<String> {
+    let blame_url = provider.blame_url(
+        repo_url,
+        git_ref,
+        normalize_repo_path(file_at_line.filepath),
+    )?;
+    match file_at_line.line_number {
+        Some(line_number) => {
+            Ok(blame_url + &provider.line_anchor(line_number, file_at_line.end_line_number))
+        }
+        None => Ok(blame_url),
+    }
+}
+
+pub fn get_history_link(
+    provider: &dyn Provider,
+    repo_url: &str,
+    git_ref: &GitRef,
+    path: &str,
+) -> AnyhowResult<String> {
+    provider.history_url(repo_url, git_ref, normalize_repo_path(path))
+}
+
+/// The tree view of a directory.
+pub fn get_tree_link(
+    provider: &dyn Provider,
+    repo_url: &str,
+    git_ref: &GitRef,
+    path: &str,
+) -> String {
+    let path = normalize_repo_path(path);
+    let tree_url = provider.tree_url(repo_url, git_ref, path);
+    // The root of the tree has no path to end the URL with.
+    match path {
+        "" => tree_url.trim_end_matches('/').to_string(),
+        _ => tree_url,
+    }
+}
+
+/// Finds the link to open or create a pull request in the messages `git push`
+/// relays from the remote. GitHub, GitLab, Bitbucket and Gitea put it on the
+/// line after the one that mentions the pull (or merge) request; failing
+/// that, any URL the remote printed is taken.
+pub fn find_pr_url(push_output: &str) -> Option<&str> {
+    let remote_lines: Vec<&str> = push_output
+        .lines()
+        .filter_map(|line| line.strip_prefix("remote:"))
+        .map(str::trim)
+        .collect();
+    fn url_in(line: &str) -> Option<&str> {
+        line.split_whitespace()
+            .find(|word| word.starts_with("https://") || word.starts_with("http://"))
+    }
+    let mentions_request = |line: &str| {
+        let line = line.to_lowercase();
+        line.contains("pull request") || line.contains("merge request")
+    };
+    remote_lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| mentions_request(line))
+        .find_map(|(index, line)| {
+            url_in(line).or_else(|| remote_lines.get(index + 1).and_then(|next| url_in(next)))
+        })
+        .or_else(|| remote_lines.iter().find_map(|line| url_in(line)))
+}
+
+/// A submodule as `.gitmodules` declares it: where it is checked out in the
+/// superproject and the URL it is cloned from.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Submodule {
+    pub path: String,
+    pub url: String,
+}
+
+/// Reads the `submodule.<name>.path` and `submodule.<name>.url` lines that
+/// `git config --file .gitmodules --get-regexp` prints.
+pub fn parse_submodules(gitmodules: &str) -> Vec<Submodule> {
+    let mut submodules: Vec<(&str, Submodule)> = vec![];
+    for line in gitmodules.lines() {
+        let (key, value) = match line.split_once(' ') {
+            Some((key, value)) => (key, value.trim()),
+            None => continue,
+        };
+        let (name, field) = match key
+            .strip_prefix("submodule.")
+            .and_then(|key| key.rsplit_once('.'))
+        {
+            Some(name_and_field) => name_and_field,
+            None => continue,
+        };
+        let index = match submodules.iter().position(|(known, _)| *known == name) {
+            Some(index) => index,
+            None => {
+                submodules.push((name, Submodule::default()));
+                submodules.len() - 1
+            }
+        };
+        match field {
+            "path" => submodules[index].1.path = value.trim_end_matches('/').to_string(),
+            "url" => submodules[index].1.url = value.to_string(),
+            _ => {}
+        }
+    }
+    submodules
+        .into_iter()
+        .map(|(_, submodule)| submodule)
+        .filter(|submodule| !submodule.path.is_empty() && !submodule.url.is_empty())
+        .collect()
+}
+
+/// Resolves a submodule URL starting with `./` or `../` against the
+/// superproject's remote URL, the way git does. Each `../` drops a path
+/// segment, down to the host of scp-like URLs.
+pub fn resolve_submodule_url(remote_url: &str, url: &str) -> String {
+    if !url.starts_with("./") && !url.starts_with("../") {
+        return url.to_string();
+    }
+    let scheme_end = remote_url.find("://").map_or(0, |idx| idx + 3);
+    let mut base = remote_url.trim_end_matches('/');
+    let mut separator = '/';
+    let mut rest = url;
+    loop {
+        if let Some(after) = rest.strip_prefix("./") {
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("../") {
+            if let Some(idx) = base[scheme_end..].rfind(['/', ':']) {
+                separator = base[scheme_end + idx..].chars().next().unwrap_or('/');
+                base = &base[..scheme_end + idx];
+            }
+            rest = after;
+        } else {
+            break;
+        }
+    }
+    format!("{}{}{}", base, separator, rest)
+}
+
+/// Splits a commit message into its subject line and the body after the
+/// blank line, if there is one.
+pub fn split_commit_message(message: &str) -> (&str, Option<&str>) {
+    let message = message.trim();
+    match message.split_once('\n') {
+        Some((subject, body)) => (
+            subject.trim(),
+            Some(body.trim()).filter(|body| !body.is_empty()),
+        ),
+        None => (message, None),
+    }
+}
+
+/// The names of the branches to pick from, out of full ref names: the local
+/// branches, then those only on `remote_name`, without its `HEAD`.
+pub fn branch_choices(refs: &[String], remote_name: &str) -> Vec<String> {
+    let remote_prefix = format!("refs/remotes/{}/", remote_name);
+    let mut branches: Vec<String> = refs
+        .iter()
+        .filter_map(|name| name.strip_prefix("refs/heads/"))
+        .map(str::to_string)
+        .collect();
+    for name in refs
+        .iter()
+        .filter_map(|name| name.strip_prefix(remote_prefix.as_str()))
+    {
+        if name != "HEAD" && !branches.iter().any(|branch| branch == name) {
+            branches.push(name.to_string());
+        }
+    }
+    branches
+}
+
+/// The `Change-Id` trailer Gerrit's commit-msg hook adds to a commit
+/// message. Gerrit takes the last one when there are several.
+pub fn find_change_id(message: &str) -> Option<&str> {
+    message
+        .lines()
+        .rev()
+        .filter_map(|line| line.trim().strip_prefix("Change-Id:"))
+        .map(str::trim)
+        .find(|change_id| {
+            change_id.len() == 41
+                && change_id.starts_with('I')
+                && change_id[1..].chars().all(|c| c.is_ascii_hexdigit())
+        })
+}
+
+/// Says in plain words why `git push` failed, going by the messages it
+/// printed, or `None` when the reason isn't one of the common ones.
+pub fn explain_push_failure(push_output: &str) -> Option<&'static str> {
+    let push_output = push_output.to_lowercase();
+    let mentions = |needles: &[&str]| needles.iter().any(|needle| push_output.contains(needle));
+    if mentions(&["non-fast-forward", "fetch first", "stale info"]) {
+        Some("the remote branch has commits that are not in the local one, pull or rebase first")
+    } else if mentions(&["protected branch", "pre-receive hook declined"]) {
+        Some("the remote refused the branch, it may be protected")
+    } else if mentions(&[
+        "authentication failed",
+        "permission denied",
+        "could not read username",
+        "returned error: 403",
+        "access denied",
+    ]) {
+        Some("the remote did not accept your credentials, check your ssh key or token")
+    } else if mentions(&[
+        "could not resolve host",
+        "connection refused",
+        "connection timed out",
+        "network is unreachable",
+    ]) {
+        Some("the remote could not be reached")
+    } else if mentions(&[
+        "does not appear to be a git repository",
+        "repository not found",
+    ]) {
+        Some("the remote repository does not exist or you have no access to it")
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::providers::provider_for_url;
 
     #[test]
     fn test_github_parsing() {
@@ -127,7 +794,8 @@ mod tests {
         let git_repo = "git@git.foo.com:project/repo.git";
         let commit_sha = "998a1b33f600914";
         let git_url = parse_url_from_git(git_repo).unwrap();
-        let commit_link = get_commit_link(git_url, commit_sha);
+        let provider = provider_for_url(&git_url);
+        let commit_link = get_commit_link(provider.as_ref(), &git_url, commit_sha);
         assert_eq!(
             commit_link,
             "https://git.foo.com/project/repo/commit/998a1b33f600914"
@@ -140,13 +808,124 @@ mod tests {
 
         let happy_result = parse_path_and_line_arg(happy_case, ':').unwrap();
         assert_eq!(happy_result.filepath, "my-proj/src/var/main.rs");
-        assert_eq!(happy_result.line_number, "90");
+        assert_eq!(happy_result.line_number, Some("90"));
+    }
+
+    #[test]
+    fn test_parse_path_and_line_range_arg() {
+        let range_case = "src/main.rs:10-25";
+
+        let range_result = parse_path_and_line_arg(range_case, ':').unwrap();
+        assert_eq!(
+            range_result,
+            FileAtLine::new("src/main.rs", Some("10"), Some("25"))
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_line_range_arg() {
+        for (arg, error) in [
+            ("a.rs:10-", "'10-' is not a line range, write it like 10-25"),
+            ("a.rs:-5", "'-5' is not a line range, write it like 10-25"),
+            ("a.rs:a-b", "'a-b' is not a line range, write it like 10-25"),
+            ("a.rs:10-5", "The line range 10-5 ends before it starts"),
+        ] {
+            assert_eq!(
+                parse_path_and_line_arg(arg, ':').unwrap_err().to_string(),
+                error
+            );
+        }
+        assert_eq!(
+            parse_path_and_line_arg("a.rs:7-7", ':').unwrap(),
+            FileAtLine::new("a.rs", Some("7"), Some("7"))
+        );
+    }
+
+    #[test]
+    fn test_parse_grep_output_line() {
+        let grep_case = "src/actions.rs:42:    let re = Regex::new(r\"a:b\").unwrap();";
+
+        let grep_result = parse_path_and_line_arg(grep_case, ':').unwrap();
+        assert_eq!(
+            grep_result,
+            FileAtLine::new("src/actions.rs", Some("42"), None)
+        );
+    }
+
+    #[test]
+    fn test_parse_path_line_and_column_arg() {
+        let result = parse_path_and_line_arg("src/main.rs:10:5: error[E0308]", ':').unwrap();
+        assert_eq!(
+            result,
+            FileAtLine {
+                column: Some("5"),
+                ..FileAtLine::new("src/main.rs", Some("10"), None)
+            }
+        );
+        assert_eq!(result.to_string(), "src/main.rs:10:5");
+
+        let provider = provider_for_url("https://github.com/oren0e/gitopen");
+        assert_eq!(
+            get_line_number_link(
+                provider.as_ref(),
+                "https://github.com/oren0e/gitopen",
+                &GitRef::Branch("main"),
+                &result
+            ),
+            "https://github.com/oren0e/gitopen/blob/main/src/main.rs#L10C5"
+        );
+        let provider = provider_for_url("https://gitlab.com/oren0e/gitopen");
+        assert_eq!(
+            get_line_number_link(
+                provider.as_ref(),
+                "https://gitlab.com/oren0e/gitopen",
+                &GitRef::Branch("main"),
+                &result
+            ),
+            "https://gitlab.com/oren0e/gitopen/-/blob/main/src/main.rs#L10"
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_location() {
+        assert_eq!(
+            diagnostic_location("   --> src/actions.rs:12:34"),
+            Some("src/actions.rs:12:34")
+        );
+        assert_eq!(diagnostic_location("  --> "), None);
+        assert_eq!(diagnostic_location("error[E0308]: mismatched types"), None);
+        assert_eq!(diagnostic_location("12 |     let x = --> 3;"), None);
+    }
+
+    #[test]
+    fn test_parse_path_without_line_arg() {
+        let no_line_case = "my-proj/src/var/main.rs90";
+
+        let no_line_result = parse_path_and_line_arg(no_line_case, ':').unwrap();
+        assert_eq!(
+            no_line_result,
+            FileAtLine::new("my-proj/src/var/main.rs90", None, None)
+        );
+    }
+
+    #[test]
+    fn test_parse_path_with_invalid_line_arg() {
+        for (arg, error) in [
+            ("a.rs:abc", "'abc' is not a line number"),
+            ("a.rs:10a:5", "'10a' is not a line number"),
+            ("a.rs: 10", "' 10' is not a line number"),
+        ] {
+            assert_eq!(
+                parse_path_and_line_arg(arg, ':').unwrap_err().to_string(),
+                error
+            );
+        }
     }
 
     #[test]
-    #[should_panic(expected = "Split character not found! Format is <path-to-file>:<line-number>")]
+    #[should_panic(expected = "Error parsing input. Format is <path-to-file>[:<line-number>]")]
     fn test_parse_path_and_line_arg_failure() {
-        let sad_case = "my-proj/src/var/main.rs90";
+        let sad_case = "my-proj/src/var/main.rs:";
         let _sad_result = parse_path_and_line_arg(sad_case, ':').unwrap();
     }
 
@@ -166,4 +945,452 @@ mod tests {
             "https://git.food-supplier.com/project/repo_name"
         );
     }
+
+    #[test]
+    fn test_replace_host() {
+        // Not `httpgitlab.com://s/...`: only the host is an alias.
+        assert_eq!(
+            replace_host("https://s/grp/r", "gitlab.com"),
+            "https://gitlab.com/grp/r"
+        );
+        assert_eq!(
+            replace_host("https://github-work/github-work/repo", "github.com"),
+            "https://github.com/github-work/repo"
+        );
+        assert_eq!(
+            replace_host("https://user@git.foo.com:8443/project/repo", "git.bar.com"),
+            "https://user@git.bar.com:8443/project/repo"
+        );
+    }
+
+    #[test]
+    fn test_get_host() {
+        assert_eq!(get_host("https://github.com/oren0e/gitopen"), "github.com");
+        assert_eq!(
+            get_host("https://user@dev.azure.com/org/project/_git/repo"),
+            "dev.azure.com"
+        );
+        assert_eq!(
+            get_host("https://git.foo.com:8443/project/repo"),
+            "git.foo.com"
+        );
+    }
+
+    #[test]
+    fn test_gitlab_subgroup_parsing() {
+        let git_repo = "git@gitlab.com:group/subgroup/project.git";
+        let result_url = parse_url_from_git(git_repo).unwrap();
+        assert_eq!(result_url, "https://gitlab.com/group/subgroup/project");
+    }
+
+    #[test]
+    fn test_gitlab_blob_link() {
+        let link = get_gitlab_blob_link(
+            "https://gitlab.com/group/subgroup/project",
+            &GitRef::Branch("main"),
+            "src/main.rs",
+        );
+        assert_eq!(
+            link,
+            "https://gitlab.com/group/subgroup/project/-/blob/main/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_self_hosted_gitlab_blob_link() {
+        let git_url = parse_url_from_git("git@gitlab.company.com:team/tools/repo.git").unwrap();
+        let provider = provider_for_url(&git_url);
+        assert_eq!(provider.name(), "gitlab");
+        assert_eq!(
+            provider.line_number_url(&git_url, &GitRef::Branch("dev"), "README.md", "3", None),
+            "https://gitlab.company.com/team/tools/repo/-/blob/dev/README.md#L3"
+        );
+    }
+
+    #[test]
+    fn test_permalink() {
+        let git_url = parse_url_from_git("git@github.com:oren0e/gitopen.git").unwrap();
+        let provider = provider_for_url(&git_url);
+        assert_eq!(
+            get_line_number_link(
+                provider.as_ref(),
+                &git_url,
+                &GitRef::Commit("998a1b3"),
+                &FileAtLine::new("/src/main.rs", Some("3"), None)
+            ),
+            "https://github.com/oren0e/gitopen/blob/998a1b3/src/main.rs#L3"
+        );
+    }
+
+    #[test]
+    fn test_parse_compare_range() {
+        assert_eq!(
+            parse_compare_range("main..feature").unwrap(),
+            ("main", "feature")
+        );
+        assert_eq!(
+            parse_compare_range("v1.0.0...v1.1.0").unwrap(),
+            ("v1.0.0", "v1.1.0")
+        );
+        assert!(parse_compare_range("main").is_err());
+        assert!(parse_compare_range("..feature").is_err());
+    }
+
+    #[test]
+    fn test_normalize_repo_path() {
+        assert_eq!(normalize_repo_path("./src/main.rs"), "src/main.rs");
+        assert_eq!(normalize_repo_path("/src/match_logic/"), "src/match_logic");
+        assert_eq!(normalize_repo_path("."), "");
+        assert_eq!(normalize_repo_path("./"), "");
+    }
+
+    #[test]
+    fn test_resolve_repo_path() {
+        let toplevel = Path::new("/home/me/gitopen");
+        let src = Path::new("/home/me/gitopen/src");
+        assert_eq!(resolve_repo_path("main.rs", src, toplevel), "src/main.rs");
+        assert_eq!(resolve_repo_path("./main.rs", src, toplevel), "src/main.rs");
+        assert_eq!(
+            resolve_repo_path("../README.md", src, toplevel),
+            "README.md"
+        );
+        assert_eq!(resolve_repo_path(".", src, toplevel), "src");
+        assert_eq!(resolve_repo_path("..", src, toplevel), "");
+        assert_eq!(
+            resolve_repo_path("/home/me/gitopen/src/actions.rs", toplevel, toplevel),
+            "src/actions.rs"
+        );
+        assert_eq!(
+            resolve_repo_path("/src/main.rs", src, toplevel),
+            "src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_encode_path() {
+        assert_eq!(encode_path("src/main.rs"), "src/main.rs");
+        assert_eq!(
+            encode_path("feature/foo-bar_1.2~3"),
+            "feature/foo-bar_1.2~3"
+        );
+        assert_eq!(encode_path("my dir/a#b?c.rs"), "my%20dir/a%23b%3Fc.rs");
+        assert_eq!(encode_path("日本/ä.md"), "%E6%97%A5%E6%9C%AC/%C3%A4.md");
+        assert_eq!(encode_segment("feature/foo"), "feature%2Ffoo");
+    }
+
+    #[test]
+    fn test_wiki_slug() {
+        assert_eq!(wiki_slug("Home"), "Home");
+        assert_eq!(wiki_slug(" Getting  started "), "Getting-started");
+        assert_eq!(wiki_slug("docs/How to #1?"), "docs/How-to-#1?");
+    }
+
+    #[test]
+    fn test_get_ssh_host() {
+        assert_eq!(
+            get_ssh_host("git@github-work:oren0e/gitopen.git\n"),
+            Some("github-work")
+        );
+        assert_eq!(
+            get_ssh_host("ssh://git@github-work/oren0e/gitopen.git"),
+            Some("github-work")
+        );
+        assert_eq!(get_ssh_host("https://github.com/oren0e/gitopen"), None);
+    }
+
+    #[test]
+    fn test_parse_file_status() {
+        assert_eq!(parse_file_status(""), FileStatus::Clean);
+        assert_eq!(parse_file_status(" M src/main.rs\n"), FileStatus::Modified);
+        assert_eq!(parse_file_status("A  src/new.rs\n"), FileStatus::Modified);
+        assert_eq!(parse_file_status("?? notes.txt\n"), FileStatus::Untracked);
+    }
+
+    #[test]
+    fn test_is_number() {
+        assert!(is_number("123"));
+        assert!(!is_number(""));
+        assert!(!is_number("#123"));
+        assert!(!is_number("12a"));
+    }
+
+    #[test]
+    fn test_build_query_string() {
+        assert_eq!(build_query_string(&[]), "");
+        assert_eq!(
+            build_query_string(&[("title", "Crash on start"), ("body", "a&b=c\n#1 ü")]),
+            "?title=Crash%20on%20start&body=a%26b%3Dc%0A%231%20%C3%BC"
+        );
+    }
+
+    #[test]
+    fn test_split_repo_url() {
+        assert_eq!(
+            split_repo_url("https://gitlab.com/group/subgroup/project.git"),
+            ("https://gitlab.com", "group/subgroup/project")
+        );
+        assert_eq!(
+            split_repo_url("https://github.com"),
+            ("https://github.com", "")
+        );
+    }
+
+    #[test]
+    fn test_repo_owner() {
+        assert_eq!(
+            repo_owner("https://github.com/oren0e/gitopen"),
+            Some("oren0e")
+        );
+        assert_eq!(
+            repo_owner("https://gitlab.com/group/subgroup/project.git"),
+            Some("group/subgroup")
+        );
+        assert_eq!(repo_owner("https://git.sr.ht/~sir/repo"), Some("~sir"));
+        assert_eq!(repo_owner("https://example.com/project"), None);
+    }
+
+    #[test]
+    fn test_bitbucket_server_base() {
+        assert_eq!(
+            get_bitbucket_server_base("https://bitbucket.company.com/scm/proj/repo.git").unwrap(),
+            "https://bitbucket.company.com/projects/PROJ/repos/repo"
+        );
+        assert_eq!(
+            get_bitbucket_server_base("https://bitbucket.company.com/PROJ/repo").unwrap(),
+            "https://bitbucket.company.com/projects/PROJ/repos/repo"
+        );
+        assert!(get_bitbucket_server_base("https://bitbucket.company.com/scm/proj").is_err());
+    }
+
+    #[test]
+    fn test_sourcehut_tracker_base() {
+        assert_eq!(
+            get_sourcehut_tracker_base("https://git.sr.ht/~user/repo").unwrap(),
+            "https://todo.sr.ht/~user/repo"
+        );
+        assert_eq!(
+            get_sourcehut_tracker_base("https://git.example.org/~user/repo/").unwrap(),
+            "https://todo.example.org/~user/repo"
+        );
+        assert!(get_sourcehut_tracker_base("https://sr.example.org/~user/repo").is_err());
+        assert!(get_sourcehut_tracker_base("https://git.sr.ht").is_err());
+    }
+
+    #[test]
+    fn test_ssh_url_parsing() {
+        assert_eq!(
+            parse_url_from_git("ssh://git@git.example.com:2222/team/repo.git").unwrap(),
+            "https://git.example.com/team/repo"
+        );
+        assert_eq!(
+            parse_url_from_git("ssh://git.example.com/team/repo.git/").unwrap(),
+            "https://git.example.com/team/repo"
+        );
+        assert_eq!(
+            parse_url_from_git("git+ssh://git@github.com/oren0e/gitopen").unwrap(),
+            "https://github.com/oren0e/gitopen"
+        );
+        assert_eq!(
+            parse_url_from_git("ssh://git@ssh.dev.azure.com:22/v3/org/project/repo").unwrap(),
+            "https://dev.azure.com/org/project/_git/repo"
+        );
+    }
+
+    #[test]
+    fn test_git_protocol_parsing() {
+        assert_eq!(
+            parse_url_from_git("git://git.kernel.org/pub/scm/git/git.git").unwrap(),
+            "https://git.kernel.org/pub/scm/git/git"
+        );
+        assert_eq!(
+            parse_url_from_git("git://example.com:9418/repo.git").unwrap(),
+            "https://example.com/repo"
+        );
+        assert_eq!(get_ssh_host("git://example.com/repo.git"), None);
+    }
+
+    #[test]
+    fn test_scp_like_parsing_without_user() {
+        assert_eq!(
+            parse_url_from_git("github.com:oren0e/gitopen.git").unwrap(),
+            "https://github.com/oren0e/gitopen"
+        );
+        assert!(parse_url_from_git("").is_err());
+        assert!(parse_url_from_git("../local/repo").is_err());
+    }
+
+    #[test]
+    fn test_codecommit_parsing() {
+        let expected = "https://git-codecommit.us-east-1.amazonaws.com/v1/repos/my-repo";
+        assert_eq!(
+            parse_url_from_git("codecommit::us-east-1://my-repo").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_url_from_git("codecommit::us-east-1://work@my-repo").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_url_from_git("ssh://git-codecommit.us-east-1.amazonaws.com/v1/repos/my-repo")
+                .unwrap(),
+            expected
+        );
+        assert_eq!(parse_url_from_git(expected).unwrap(), expected);
+        assert!(parse_url_from_git("codecommit://my-repo").is_err());
+        assert_eq!(get_ssh_host("codecommit::us-east-1://my-repo"), None);
+    }
+
+    #[test]
+    fn test_azure_devops_ssh_parsing() {
+        let git_repo = "git@ssh.dev.azure.com:v3/org/project/repo";
+        let result_url = parse_url_from_git(git_repo).unwrap();
+        assert_eq!(result_url, "https://dev.azure.com/org/project/_git/repo");
+    }
+
+    #[test]
+    fn test_azure_devops_https_parsing() {
+        let git_repo = "https://org@dev.azure.com/org/project/_git/repo";
+        let result_url = parse_url_from_git(git_repo).unwrap();
+        assert_eq!(result_url, "https://dev.azure.com/org/project/_git/repo");
+    }
+
+    #[test]
+    fn test_parse_submodules() {
+        let gitmodules = "submodule.libs/json.path libs/json\nsubmodule.libs/json.url https://github.com/nlohmann/json.git\nsubmodule.v1.2.path vendor/old/\nsubmodule.v1.2.url ../old.git\nsubmodule.broken.path nowhere\n";
+        assert_eq!(
+            parse_submodules(gitmodules),
+            [
+                Submodule {
+                    path: "libs/json".to_string(),
+                    url: "https://github.com/nlohmann/json.git".to_string(),
+                },
+                Submodule {
+                    path: "vendor/old".to_string(),
+                    url: "../old.git".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_submodule_url() {
+        assert_eq!(
+            resolve_submodule_url("git@github.com:org/super.git", "../lib.git"),
+            "git@github.com:org/lib.git"
+        );
+        assert_eq!(
+            resolve_submodule_url("git@github.com:org/super.git", "../../other/lib.git"),
+            "git@github.com:other/lib.git"
+        );
+        assert_eq!(
+            resolve_submodule_url("https://gitlab.com/group/super/", "./../lib"),
+            "https://gitlab.com/group/lib"
+        );
+        assert_eq!(
+            resolve_submodule_url("https://gitlab.com/group/super", "git@host:x/y.git"),
+            "git@host:x/y.git"
+        );
+    }
+
+    #[test]
+    fn test_split_commit_message() {
+        assert_eq!(
+            split_commit_message("Fix the parser\n\nIt choked on tabs.\nFixes #3\n"),
+            ("Fix the parser", Some("It choked on tabs.\nFixes #3"))
+        );
+        assert_eq!(
+            split_commit_message("Fix the parser\n"),
+            ("Fix the parser", None)
+        );
+    }
+
+    #[test]
+    fn test_branch_choices() {
+        let refs: Vec<String> = [
+            "refs/heads/main",
+            "refs/heads/feat/x",
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+            "refs/remotes/origin/release/1.x",
+            "refs/remotes/fork/wip",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+        assert_eq!(
+            branch_choices(&refs, "origin"),
+            ["main", "feat/x", "release/1.x"]
+        );
+        assert_eq!(branch_choices(&refs, "fork"), ["main", "feat/x", "wip"]);
+    }
+
+    #[test]
+    fn test_find_change_id() {
+        let change_id = "I8473b95934b5732ac55d26311a706c9c2bde9940";
+        assert_eq!(
+            find_change_id(&format!(
+                "Fix the build\n\nChange-Id: in the body is not a trailer\n\nBug: 12\nChange-Id: {}\n",
+                change_id
+            )),
+            Some(change_id)
+        );
+        assert_eq!(find_change_id("Fix the build\n\nChange-Id: I123\n"), None);
+        assert_eq!(find_change_id("Fix the build"), None);
+    }
+
+    #[test]
+    fn test_explain_push_failure() {
+        let rejected = "To github.com:org/repo.git\n ! [rejected]        feat -> feat (fetch first)\nerror: failed to push some refs to 'github.com:org/repo.git'\n";
+        assert_eq!(
+            explain_push_failure(rejected),
+            Some(
+                "the remote branch has commits that are not in the local one, pull or rebase first"
+            )
+        );
+        let auth = "remote: Invalid username or password.\nfatal: Authentication failed for 'https://github.com/org/repo.git/'\n";
+        assert_eq!(
+            explain_push_failure(auth),
+            Some("the remote did not accept your credentials, check your ssh key or token")
+        );
+        let ssh = "git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository.\n";
+        assert_eq!(explain_push_failure(ssh), explain_push_failure(auth));
+        assert_eq!(
+            explain_push_failure("error: src refspec feat does not match any\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_pr_url() {
+        let github = "remote:\nremote: Create a pull request for 'feat' on GitHub by visiting:\nremote:      https://github.com/org/repo/pull/new/feat\nremote:\nTo github.com:org/repo.git\n";
+        assert_eq!(
+            find_pr_url(github),
+            Some("https://github.com/org/repo/pull/new/feat")
+        );
+        let gitlab = "remote:\nremote: To create a merge request for feat, visit:\nremote:   https://gitlab.com/group/project/-/merge_requests/new?merge_request%5Bsource_branch%5D=feat\nremote:\n";
+        assert_eq!(
+            find_pr_url(gitlab),
+            Some("https://gitlab.com/group/project/-/merge_requests/new?merge_request%5Bsource_branch%5D=feat")
+        );
+        let gitlab_existing = "remote: View merge request for feat:\nremote:   https://gitlab.com/group/project/-/merge_requests/12\n";
+        assert_eq!(
+            find_pr_url(gitlab_existing),
+            Some("https://gitlab.com/group/project/-/merge_requests/12")
+        );
+        let bitbucket = "remote: Create pull request for feat:\nremote:   https://bitbucket.org/team/repo/pull-requests/new?source=feat&t=1\n";
+        assert_eq!(
+            find_pr_url(bitbucket),
+            Some("https://bitbucket.org/team/repo/pull-requests/new?source=feat&t=1")
+        );
+        let gitea = "remote: . Processing 1 references\nremote: Visit https://gitea.com/docs to learn more\nremote: Create a new pull request for 'feat':\nremote:   https://gitea.com/org/repo/compare/main...feat\n";
+        assert_eq!(
+            find_pr_url(gitea),
+            Some("https://gitea.com/org/repo/compare/main...feat")
+        );
+        assert_eq!(
+            find_pr_url("remote: See https://example.com/hook\n"),
+            Some("https://example.com/hook")
+        );
+        assert_eq!(find_pr_url("Everything up-to-date\n"), None);
+    }
 }
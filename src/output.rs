@@ -0,0 +1,768 @@
+use crate::error::GitOpenError;
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::debug;
+
+/// Whether gitopen says nothing but its exit code, for `--quiet`.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Whether what gitopen prints to a terminal may be colored, which
+/// `--no-color` and `NO_COLOR` turn off.
+static COLOR: AtomicBool = AtomicBool::new(true);
+
+pub fn set_color(no_color_flag: bool) {
+    COLOR.store(
+        color_wanted(no_color_flag, env::var_os("NO_COLOR")),
+        Ordering::Relaxed,
+    );
+}
+
+/// Any non-empty `NO_COLOR` turns colors off, as https://no-color.org has it.
+fn color_wanted(no_color_flag: bool, no_color_var: Option<std::ffi::OsString>) -> bool {
+    !no_color_flag && no_color_var.is_none_or(|value| value.is_empty())
+}
+
+/// `text` in the SGR `style`, e.g. `1;31` for bold red, when it goes to a
+/// terminal and colors weren't turned off.
+pub(crate) fn paint(text: &str, style: &str, to_terminal: bool) -> String {
+    if to_terminal && COLOR.load(Ordering::Relaxed) {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// What a message gitopen prints to stderr is, which its prefix says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label {
+    Error,
+    Warning,
+    Note,
+    Hint,
+}
+
+impl Label {
+    fn prefix(self) -> &'static str {
+        match self {
+            Label::Error => "error",
+            Label::Warning => "warning",
+            Label::Note => "note",
+            Label::Hint => "hint",
+        }
+    }
+
+    fn style(self) -> &'static str {
+        match self {
+            Label::Error => "1;31",
+            Label::Warning => "1;33",
+            Label::Note | Label::Hint => "1;36",
+        }
+    }
+}
+
+/// Prints `message` to stderr after its label, e.g. `warning: ...`, unless
+/// `--quiet`.
+pub fn report(label: Label, message: &str) {
+    if is_quiet() {
+        return;
+    }
+    let prefix = paint(label.prefix(), label.style(), io::stderr().is_terminal());
+    eprintln!("{}: {}", prefix, message);
+}
+
+/// Says what a step of gitopen did, e.g. `Pushed 'feat' to 'origin'`, to
+/// someone watching. Scripts reading stderr don't get it.
+pub(crate) fn progress(verb: &str, message: &str) {
+    if is_quiet() || !io::stderr().is_terminal() {
+        return;
+    }
+    eprintln!("{} {}", paint(verb, "1;32", true), message);
+}
+
+/// How a link is written when it is printed or copied rather than opened.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Url,
+    Markdown,
+    Html,
+    Org,
+    Json,
+    /// An OSC 8 terminal hyperlink, clickable in terminals that support it
+    /// even when gitopen runs on another machine over ssh.
+    Hyperlink,
+}
+
+/// A link an action built and what it points at, which the text formats
+/// and `--format json` describe it by.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Link {
+    pub url: String,
+    #[serde(skip)]
+    pub label: String,
+    pub provider: Option<String>,
+    pub repo: Option<String>,
+    pub branch: Option<String>,
+    pub commit: Option<String>,
+    pub path: Option<String>,
+    pub line: Option<u32>,
+    pub end_line: Option<u32>,
+}
+
+impl Link {
+    /// A link that is only known by its URL, shown as `label`.
+    pub fn new(url: &str, label: &str) -> Link {
+        Link {
+            url: url.to_string(),
+            label: label.to_string(),
+            ..Link::default()
+        }
+    }
+}
+
+impl Format {
+    pub const NAMES: &'static [&'static str] =
+        &["url", "markdown", "html", "org", "json", "hyperlink"];
+
+    pub fn from_name(name: &str) -> AnyhowResult<Format> {
+        match name {
+            "url" => Ok(Format::Url),
+            "markdown" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            "org" => Ok(Format::Org),
+            "json" => Ok(Format::Json),
+            "hyperlink" => Ok(Format::Hyperlink),
+            _ => Err(anyhow!(
+                "Unknown format '{}', the formats are: {}",
+                name,
+                Format::NAMES.join(", ")
+            )),
+        }
+    }
+
+    pub fn render(self, link: &Link) -> AnyhowResult<String> {
+        let (label, url) = (link.label.as_str(), link.url.as_str());
+        Ok(match self {
+            Format::Url => url.to_string(),
+            Format::Markdown => format!(
+                "[{}]({})",
+                label.replace('[', "\\[").replace(']', "\\]"),
+                url.replace('(', "%28").replace(')', "%29")
+            ),
+            Format::Html => format!(
+                "<a href=\"{}\">{}</a>",
+                escape_html(url),
+                escape_html(label)
+            ),
+            Format::Org => format!("[[{}][{}]]", url, label.replace(['[', ']'], "")),
+            Format::Json => serde_json::to_string(link)?,
+            // Control characters would end the escape sequence early.
+            Format::Hyperlink => format!(
+                "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\",
+                url.replace(char::is_control, ""),
+                label.replace(char::is_control, "")
+            ),
+        })
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Decides what happens with a generated URL once an action has built it.
+/// Any `format` but `Url` is printed, as a browser can't open it.
+#[derive(Debug, Default)]
+pub struct Output {
+    pub print: bool,
+    pub copy: bool,
+    /// Copies through the terminal with an OSC 52 escape sequence, which
+    /// reaches the local clipboard from an ssh session.
+    pub osc52: bool,
+    pub format: Format,
+    /// The command to open URLs with instead of the system's default browser.
+    pub browser: Option<String>,
+    /// Says what would be copied or opened instead of doing it.
+    pub dry_run: bool,
+    /// Prints the URL as a QR code to open it on a phone, instead of
+    /// opening it.
+    pub qr: bool,
+    /// Tests collect the URLs here instead of opening them.
+    #[cfg(test)]
+    pub(crate) delivered: std::cell::RefCell<Vec<String>>,
+}
+
+impl Output {
+    pub fn deliver(&self, url: &str) -> AnyhowResult<()> {
+        self.deliver_link(&Link::new(url, url))
+    }
+
+    /// Like `deliver`, with the formats describing the link by what it
+    /// points at, e.g. `src/main.rs:10`.
+    pub fn deliver_link(&self, link: &Link) -> AnyhowResult<()> {
+        #[cfg(test)]
+        self.delivered.borrow_mut().push(link.url.clone());
+        debug!(url = link.url.as_str(), "built the link to {}", link.label);
+        let text = self.format.render(link)?;
+        let print = self.print || (self.format != Format::Url && !self.copy);
+        if self.copy {
+            // The clipboard can't hold a hyperlink, only its URL.
+            let copied = match self.format {
+                Format::Hyperlink => &link.url,
+                _ => &text,
+            };
+            if self.dry_run {
+                println!("would copy: {}", copied);
+            } else if self.osc52 {
+                copy_with_osc52(copied)?;
+                progress("Copied", &link.url);
+            } else {
+                copy_to_clipboard(copied)?;
+                progress("Copied", &link.url);
+            }
+        }
+        if self.qr {
+            print!("{}", qr_code(&link.url)?);
+        }
+        if print || self.qr {
+            // Underlined for people reading it, and as it was for scripts.
+            match self.format {
+                Format::Url => println!("{}", paint(&text, "4", io::stdout().is_terminal())),
+                _ => println!("{}", text),
+            }
+        }
+        let print = print || self.qr;
+        if !print && !self.copy && self.dry_run {
+            match &self.browser {
+                Some(browser) => println!("would open with {}: {}", browser, link.url),
+                None => println!("would open: {}", link.url),
+            }
+        } else if !print && !self.copy && !cfg!(test) {
+            let opened = match (&self.browser, env::var("BROWSER")) {
+                (Some(browser), _) => {
+                    open_with(browser, &link.url)?;
+                    true
+                }
+                (None, Ok(browsers)) if !browsers.trim().is_empty() => {
+                    open_with_browser_var(&browsers, &link.url)?;
+                    true
+                }
+                (None, _) => open_in_browser(&link.url)?,
+            };
+            if opened {
+                progress("Opened", &link.url);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `url` as a QR code of unicode half blocks, two rows of modules to a line.
+/// The light modules are the ones drawn, for the dark background most
+/// terminals have.
+fn qr_code(url: &str) -> AnyhowResult<String> {
+    let code = QrCode::new(url.as_bytes())
+        .map_err(|err| anyhow!("Could not make a QR code of {}: {}", url, err))?;
+    let rendered = code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build();
+    Ok(rendered + "\n")
+}
+
+/// Splits a browser command like `chromium --incognito` into its program and
+/// arguments. The URL takes the place of a `%s`, or else comes last, and
+/// `%%` stands for a literal `%`.
+fn browser_command(browser: &str, url: &str) -> Option<(String, Vec<String>)> {
+    let mut has_placeholder = false;
+    let mut words = browser.split_whitespace().map(|word| {
+        let parts: Vec<String> = word
+            .split("%%")
+            .map(|part| {
+                has_placeholder |= part.contains("%s");
+                part.replace("%s", url)
+            })
+            .collect();
+        parts.join("%")
+    });
+    let program = words.next()?;
+    let mut args: Vec<String> = words.collect();
+    if !has_placeholder {
+        args.push(url.to_string());
+    }
+    Some((program, args))
+}
+
+fn open_with(browser: &str, url: &str) -> AnyhowResult<()> {
+    let (program, args) =
+        browser_command(browser, url).ok_or_else(|| GitOpenError::BrowserFailed {
+            browser: format!("'{}'", browser),
+            reason: "the browser command is empty".to_string(),
+        })?;
+    Command::new(&program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .spawn()
+        .map_err(|err| GitOpenError::BrowserFailed {
+            browser: format!("'{}'", program),
+            reason: err.to_string(),
+        })?;
+    Ok(())
+}
+
+/// Tries the `:`-separated commands of `$BROWSER` in turn until one of them
+/// succeeds. Like other tools, gitopen waits for them, as they are often
+/// terminal browsers like `w3m`.
+fn open_with_browser_var(browsers: &str, url: &str) -> AnyhowResult<()> {
+    for browser in browsers.split(':') {
+        let (program, args) = match browser_command(browser, url) {
+            Some(command) => command,
+            None => continue,
+        };
+        let status = Command::new(&program).args(&args).status();
+        if matches!(status, Ok(status) if status.success()) {
+            return Ok(());
+        }
+    }
+    Err(GitOpenError::BrowserFailed {
+        browser: format!("$BROWSER ('{}')", browsers),
+        reason: format!("none of its browsers could open {}", url),
+    }
+    .into())
+}
+
+/// Why no browser can be opened here, if that's the case: CI, containers
+/// and ssh sessions without a display. `$BROWSER` names one that can.
+fn headless_reason(
+    var: impl Fn(&str) -> Option<String>,
+    in_container: bool,
+) -> Option<&'static str> {
+    let is_set = |name: &str| var(name).filter(|value| !value.is_empty()).is_some();
+    if is_set("BROWSER") {
+        return None;
+    }
+    if is_set("CI") {
+        return Some("running in CI");
+    }
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        return None;
+    }
+    if is_set("DISPLAY") || is_set("WAYLAND_DISPLAY") {
+        return None;
+    }
+    if in_container {
+        return Some("running in a container");
+    }
+    if is_set("SSH_CONNECTION") {
+        return Some("running over ssh without a display");
+    }
+    Some("there is no display")
+}
+
+/// Why URLs can't be opened here, if that's the case: the configured
+/// `browser`, or else those of `$BROWSER`, aren't installed, or there is no
+/// default browser to fall back to.
+pub(crate) fn browser_problem(browser: Option<&str>) -> Option<String> {
+    if let Some(browser) = browser {
+        return match browser_command(browser, "") {
+            Some((program, _)) if is_on_path(&program) => None,
+            Some((program, _)) => Some(format!("the browser '{}' is not installed", program)),
+            None => Some("the browser command is empty".to_string()),
+        };
+    }
+    match env::var("BROWSER") {
+        Ok(browsers) if !browsers.trim().is_empty() => {
+            let installed = browsers
+                .split(':')
+                .filter_map(|browser| browser_command(browser, ""))
+                .any(|(program, _)| is_on_path(&program));
+            if installed {
+                None
+            } else {
+                Some(format!(
+                    "none of the browsers in $BROWSER ('{}') is installed",
+                    browsers
+                ))
+            }
+        }
+        _ if is_wsl() => None,
+        _ => headless_reason(|name| env::var(name).ok(), in_container()).map(str::to_string),
+    }
+}
+
+/// Whether `program` can be run, as a path or found on the `PATH`.
+fn is_on_path(program: &str) -> bool {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.is_file();
+    }
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir| {
+            dir.join(program).is_file() || dir.join(format!("{}.exe", program)).is_file()
+        })
+    })
+}
+
+fn in_container() -> bool {
+    Path::new("/.dockerenv").exists() || Path::new("/run/.containerenv").exists()
+}
+
+/// Opens the URL, or prints it with a note when there is no browser to open
+/// it in, and says which it did. Either way the link is delivered, so it
+/// isn't an error, unless `--quiet` keeps it from being printed.
+fn open_in_browser(url: &str) -> AnyhowResult<bool> {
+    if is_wsl() && open_in_windows(url) {
+        return Ok(true);
+    }
+    let reason = match headless_reason(|name| env::var(name).ok(), in_container()) {
+        Some(reason) => reason.to_string(),
+        None => match webbrowser::open(url) {
+            Ok(()) => return Ok(true),
+            Err(err) => format!("could not open a browser ({})", err),
+        },
+    };
+    if is_quiet() {
+        return Err(GitOpenError::BrowserFailed {
+            browser: "the default browser".to_string(),
+            reason,
+        }
+        .into());
+    }
+    note!("{}, printing the URL instead of opening it", reason);
+    println!("{}", url);
+    Ok(false)
+}
+
+/// WSL kernels say so in their version, e.g. `5.15.90.1-microsoft-standard-WSL2`.
+fn is_wsl_kernel(proc_version: &str) -> bool {
+    proc_version.to_lowercase().contains("microsoft")
+}
+
+fn is_wsl() -> bool {
+    cfg!(target_os = "linux")
+        && (env::var_os("WSL_DISTRO_NAME").is_some()
+            || fs::read_to_string("/proc/version").is_ok_and(|version| is_wsl_kernel(&version)))
+}
+
+/// The ways to reach the Windows side's default browser from WSL: `wslview`
+/// from wslu, and else `start`, for which cmd.exe needs `&` escaped.
+fn windows_open_commands(url: &str) -> Vec<(&'static str, Vec<String>)> {
+    vec![
+        ("wslview", vec![url.to_string()]),
+        (
+            "cmd.exe",
+            vec![
+                "/C".to_string(),
+                "start".to_string(),
+                "\"\"".to_string(),
+                url.replace('&', "^&"),
+            ],
+        ),
+    ]
+}
+
+fn open_in_windows(url: &str) -> bool {
+    windows_open_commands(url).iter().any(|(program, args)| {
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    })
+}
+
+/// Terminals drop longer OSC 52 sequences, xterm for one limits them to
+/// 100000 bytes.
+const OSC52_MAX_ENCODED_LEN: usize = 100_000;
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// The OSC 52 sequence that sets the clipboard to `text`. tmux only passes
+/// it on to the outer terminal when wrapped in its own escape sequence.
+fn osc52_sequence(text: &str, in_tmux: bool) -> AnyhowResult<String> {
+    let encoded = base64_encode(text.as_bytes());
+    if encoded.len() > OSC52_MAX_ENCODED_LEN {
+        return Err(anyhow!(
+            "The text is too long to copy through the terminal ({} bytes)",
+            text.len()
+        ));
+    }
+    let sequence = format!("\x1b]52;c;{}\x07", encoded);
+    if in_tmux {
+        return Ok(format!(
+            "\x1bPtmux;{}\x1b\\",
+            sequence.replace('\x1b', "\x1b\x1b")
+        ));
+    }
+    Ok(sequence)
+}
+
+/// Writes the OSC 52 sequence to the terminal rather than stdout, so it
+/// doesn't end up in a pipe along with `--print`.
+fn copy_with_osc52(text: &str) -> AnyhowResult<()> {
+    let sequence = osc52_sequence(text, env::var_os("TMUX").is_some())?;
+    match fs::OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(mut tty) => tty.write_all(sequence.as_bytes())?,
+        Err(_) => io::stderr().write_all(sequence.as_bytes())?,
+    }
+    Ok(())
+}
+
+fn clipboard_commands() -> Vec<(&'static str, Vec<&'static str>)> {
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", vec![])]
+    } else if cfg!(target_os = "windows") {
+        vec![("clip", vec![])]
+    } else {
+        let mut commands = vec![
+            ("xclip", vec!["-selection", "clipboard"]),
+            ("xsel", vec!["--clipboard", "--input"]),
+        ];
+        if env::var_os("WAYLAND_DISPLAY").is_some() {
+            commands.insert(0, ("wl-copy", vec![]));
+        }
+        commands
+    }
+}
+
+/// Hands the text to the platform's clipboard utility. These utilities keep
+/// serving the clipboard after gitopen exits, which a library can't do on X11.
+fn copy_to_clipboard(text: &str) -> AnyhowResult<()> {
+    let commands = clipboard_commands();
+    for (program, args) in &commands {
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    Err(anyhow!(
+        "Could not copy to clipboard, tried: {}",
+        commands
+            .iter()
+            .map(|(program, _)| *program)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_formats() {
+        let url = "https://github.com/oren0e/gitopen/blob/master/src/main.rs#L10";
+        let render =
+            |format: Format, label: &str, url: &str| format.render(&Link::new(url, label)).unwrap();
+        assert_eq!(render(Format::Url, "src/main.rs:10", url), url);
+        assert_eq!(
+            render(Format::Markdown, "src/main.rs:10", url),
+            format!("[src/main.rs:10]({})", url)
+        );
+        assert_eq!(
+            render(Format::Html, "a<b>.rs", "https://x/?a=1&b=2"),
+            "<a href=\"https://x/?a=1&amp;b=2\">a&lt;b&gt;.rs</a>"
+        );
+        assert_eq!(
+            render(Format::Org, "src/main.rs:10", url),
+            format!("[[{}][src/main.rs:10]]", url)
+        );
+        assert_eq!(
+            render(Format::Markdown, "[wip]", "https://x/(1)"),
+            "[\\[wip\\]](https://x/%281%29)"
+        );
+        assert_eq!(
+            render(Format::Hyperlink, "src/main.rs:10", url),
+            format!("\x1b]8;;{}\x1b\\src/main.rs:10\x1b]8;;\x1b\\", url)
+        );
+        assert!(Format::from_name("rst").is_err());
+    }
+
+    #[test]
+    fn test_browser_command() {
+        let url = "https://github.com/oren0e/gitopen";
+        assert_eq!(
+            browser_command("chromium --incognito", url),
+            Some((
+                "chromium".to_string(),
+                vec!["--incognito".to_string(), url.to_string()]
+            ))
+        );
+        assert_eq!(
+            browser_command("open -a Safari %s --args", url),
+            Some((
+                "open".to_string(),
+                vec![
+                    "-a".to_string(),
+                    "Safari".to_string(),
+                    url.to_string(),
+                    "--args".to_string()
+                ]
+            ))
+        );
+        assert_eq!(
+            browser_command("echo 100%% %s", url),
+            Some((
+                "echo".to_string(),
+                vec!["100%".to_string(), url.to_string()]
+            ))
+        );
+        assert_eq!(
+            browser_command("echo %%s", url),
+            Some(("echo".to_string(), vec!["%s".to_string(), url.to_string()]))
+        );
+        assert_eq!(browser_command("  ", url), None);
+    }
+
+    #[test]
+    fn test_headless_reason() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert_eq!(
+            headless_reason(env(&[("CI", "true"), ("DISPLAY", ":0")]), false),
+            Some("running in CI")
+        );
+        assert_eq!(
+            headless_reason(env(&[("CI", "true"), ("BROWSER", "w3m")]), true),
+            None
+        );
+        if cfg!(not(any(target_os = "macos", target_os = "windows"))) {
+            assert_eq!(headless_reason(env(&[("DISPLAY", ":0")]), false), None);
+            assert_eq!(
+                headless_reason(env(&[("SSH_CONNECTION", "10.0.0.1 22")]), false),
+                Some("running over ssh without a display")
+            );
+            assert_eq!(
+                headless_reason(env(&[("DISPLAY", "")]), true),
+                Some("running in a container")
+            );
+        }
+    }
+
+    #[test]
+    fn test_wsl() {
+        assert!(is_wsl_kernel(
+            "Linux version 5.15.90.1-microsoft-standard-WSL2 (oe-user@oe-host)"
+        ));
+        assert!(is_wsl_kernel("Linux version 4.4.0-19041-Microsoft"));
+        assert!(!is_wsl_kernel("Linux version 6.1.0-18-amd64"));
+        let (program, args) = &windows_open_commands("https://x/?a=1&b=2")[1];
+        assert_eq!(*program, "cmd.exe");
+        assert_eq!(args[3], "https://x/?a=1^&b=2");
+    }
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(
+            osc52_sequence("https://x", false).unwrap(),
+            "\x1b]52;c;aHR0cHM6Ly94\x07"
+        );
+        assert_eq!(
+            osc52_sequence("https://x", true).unwrap(),
+            "\x1bPtmux;\x1b\x1b]52;c;aHR0cHM6Ly94\x07\x1b\\"
+        );
+        assert!(osc52_sequence(&"x".repeat(80_000), false).is_err());
+    }
+
+    #[test]
+    fn test_colors() {
+        assert!(color_wanted(false, None));
+        assert!(color_wanted(false, Some("".into())));
+        assert!(!color_wanted(false, Some("1".into())));
+        assert!(!color_wanted(true, None));
+        assert_eq!(paint("error", "1;31", true), "\x1b[1;31merror\x1b[0m");
+        assert_eq!(paint("error", "1;31", false), "error");
+    }
+
+    #[test]
+    fn test_qr_code() {
+        let code = qr_code("https://github.com/oren0e/gitopen").unwrap();
+        let lines: Vec<&str> = code.lines().collect();
+        // A version 3 code is 29 modules wide, with 4 of quiet zone around.
+        assert_eq!(lines[0].chars().count(), 37);
+        assert_eq!(lines.len(), 19);
+        assert!(lines.iter().all(|line| line.chars().count() == 37));
+        // The quiet zone is drawn as the light modules are.
+        assert!(lines[0].chars().all(|c| c == '\u{2588}'));
+        assert!(qr_code(&"x".repeat(8_000)).is_err());
+    }
+
+    #[test]
+    fn test_render_json() {
+        let link = Link {
+            provider: Some("github".to_string()),
+            repo: Some("oren0e/gitopen".to_string()),
+            branch: Some("master".to_string()),
+            path: Some("src/main.rs".to_string()),
+            line: Some(10),
+            ..Link::new(
+                "https://github.com/oren0e/gitopen/blob/master/src/main.rs#L10",
+                "src/main.rs:10",
+            )
+        };
+        assert_eq!(
+            Format::Json.render(&link).unwrap(),
+            r#"{"url":"https://github.com/oren0e/gitopen/blob/master/src/main.rs#L10","provider":"github","repo":"oren0e/gitopen","branch":"master","commit":null,"path":"src/main.rs","line":10,"end_line":null}"#
+        );
+    }
+}
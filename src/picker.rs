@@ -0,0 +1,162 @@
+//! Lets the user pick one of a list of choices on the terminal: in fzf when
+//! it's installed, or else from a numbered list that typing narrows down.
+
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+/// How many choices the numbered list shows at a time.
+const SHOWN_CHOICES: usize = 20;
+
+/// Whether the characters of `query` appear in `choice` in order, ignoring
+/// case, the way fuzzy finders match.
+pub fn fuzzy_matches(query: &str, choice: &str) -> bool {
+    let mut choice_chars = choice.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .all(|c| choice_chars.any(|choice_char| choice_char == c))
+}
+
+/// Asks which of the choices to take, or `None` when the user gave up.
+/// fzf shows the output of the `preview` command for the highlighted
+/// choice, which it puts in place of `{}`.
+pub fn pick<'a>(
+    prompt: &str,
+    choices: &'a [String],
+    preview: Option<&str>,
+) -> AnyhowResult<Option<&'a str>> {
+    match pick_with_fzf(prompt, choices, preview) {
+        Some(picked) => picked,
+        None => pick_from_list(prompt, choices, &mut io::stdin().lock(), &mut io::stderr()),
+    }
+}
+
+/// Runs fzf on the choices, or returns `None` when it isn't installed.
+fn pick_with_fzf<'a>(
+    prompt: &str,
+    choices: &'a [String],
+    preview: Option<&str>,
+) -> Option<AnyhowResult<Option<&'a str>>> {
+    let mut fzf = Command::new("fzf");
+    fzf.args([
+        "--no-multi",
+        "--no-sort",
+        "--prompt",
+        &format!("{} ", prompt),
+    ]);
+    if let Some(preview) = preview {
+        fzf.args(["--preview", preview]);
+    }
+    let child = fzf.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) if err.kind() == ErrorKind::NotFound => return None,
+        Err(err) => return Some(Err(anyhow!("Could not run fzf: {}", err))),
+    };
+    let picked = (|| {
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(choices.join("\n").as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        // fzf exits with 1 when nothing matched and 130 when interrupted.
+        let picked = String::from_utf8_lossy(&output.stdout);
+        Ok(choices
+            .iter()
+            .find(|choice| output.status.success() && choice.as_str() == picked.trim_end())
+            .map(String::as_str))
+    })();
+    Some(picked)
+}
+
+/// Asks a question on the terminal and returns the trimmed answer, empty
+/// at the end of the input.
+pub fn ask(prompt: &str) -> AnyhowResult<String> {
+    eprint!("{} ", prompt);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+/// Shows the choices matching what was typed so far, numbered, until the
+/// user types a number. An empty answer takes the first choice shown, the
+/// end of the input gives up.
+fn pick_from_list<'a>(
+    prompt: &str,
+    choices: &'a [String],
+    input: &mut dyn BufRead,
+    terminal: &mut dyn Write,
+) -> AnyhowResult<Option<&'a str>> {
+    let mut query = String::new();
+    loop {
+        let matching: Vec<&String> = choices
+            .iter()
+            .filter(|choice| fuzzy_matches(&query, choice))
+            .collect();
+        let shown = matching.len().min(SHOWN_CHOICES);
+        for (index, choice) in matching.iter().take(shown).enumerate() {
+            writeln!(terminal, "  {}) {}", index + 1, choice)?;
+        }
+        if matching.len() > shown {
+            writeln!(
+                terminal,
+                "  ... {} more, type to narrow them down",
+                matching.len() - shown
+            )?;
+        }
+        write!(terminal, "{} [1] ", prompt)?;
+        terminal.flush()?;
+
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+        match answer.trim() {
+            "" => return Ok(matching.first().map(|choice| choice.as_str())),
+            answer => match answer.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= shown => {
+                    return Ok(Some(matching[number - 1].as_str()))
+                }
+                _ if choices.iter().any(|choice| fuzzy_matches(answer, choice)) => {
+                    query = answer.to_string()
+                }
+                _ => writeln!(terminal, "Nothing matches '{}'", answer)?,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_matches() {
+        assert!(fuzzy_matches("fxpr", "1a2b3c4 Fix the PR link"));
+        assert!(fuzzy_matches("", "anything"));
+        assert!(fuzzy_matches("fix pr", "1a2b3c4 Fix the PR link"));
+        assert!(!fuzzy_matches("rpf", "1a2b3c4 Fix the PR link"));
+    }
+
+    #[test]
+    fn test_pick_from_list() {
+        let choices: Vec<String> = ["1a2b3c4 Fix the PR link", "5d6e7f8 Add wiki pages"]
+            .iter()
+            .map(|choice| choice.to_string())
+            .collect();
+        let mut terminal = Vec::new();
+        let picked = pick_from_list("Which?", &choices, &mut &b"wiki\n1\n"[..], &mut terminal);
+        assert_eq!(picked.unwrap(), Some("5d6e7f8 Add wiki pages"));
+        let shown = String::from_utf8(terminal).unwrap();
+        assert!(shown.contains("  2) 5d6e7f8 Add wiki pages"));
+        assert!(shown.ends_with("  1) 5d6e7f8 Add wiki pages\nWhich? [1] "));
+
+        let picked = pick_from_list("Which?", &choices, &mut &b"\n"[..], &mut Vec::new());
+        assert_eq!(picked.unwrap(), Some("1a2b3c4 Fix the PR link"));
+        let picked = pick_from_list("Which?", &choices, &mut &b"zzz\n3\n"[..], &mut Vec::new());
+        assert_eq!(picked.unwrap(), None);
+    }
+}
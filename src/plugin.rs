@@ -0,0 +1,366 @@
+//! Providers implemented by `gitopen-<name>` executables, for code browsers
+//! only a company itself knows. The plugin gets a JSON description of the
+//! page on stdin and prints its URL:
+//!
+//! ```json
+//! {"action": "file", "url": "https://code.corp.com/team/repo", "host": "code.corp.com",
+//!  "repo": "team/repo", "branch": "main", "sha": null, "path": "src/lib.rs",
+//!  "line": 10, "end_line": 12, "number": null}
+//! ```
+//!
+//! Printing nothing leaves the page to the provider the host would get
+//! otherwise, which also builds the pages plugins aren't asked for.
+
+use crate::match_logic::{get_host, split_repo_url};
+use crate::providers::{
+    ArchiveFormat, CodeSearch, GitRef, NewPullRequest, ProjectPage, Provider, SettingsSection,
+};
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+/// The page a plugin is asked for, with the fields that don't apply to it
+/// left `null`.
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Request<'a> {
+    action: &'a str,
+    url: &'a str,
+    host: &'a str,
+    repo: &'a str,
+    branch: Option<&'a str>,
+    sha: Option<&'a str>,
+    path: Option<&'a str>,
+    line: Option<u32>,
+    end_line: Option<u32>,
+    number: Option<&'a str>,
+}
+
+impl<'a> Request<'a> {
+    fn new(action: &'a str, repo_url: &'a str) -> Request<'a> {
+        Request {
+            action,
+            url: repo_url,
+            host: get_host(repo_url),
+            repo: split_repo_url(repo_url).1,
+            ..Request::default()
+        }
+    }
+
+    fn at(self, git_ref: &'a GitRef, path: &'a str) -> Request<'a> {
+        let (branch, sha) = match git_ref {
+            GitRef::Branch(branch) => (Some(*branch), None),
+            GitRef::Commit(sha) => (None, Some(*sha)),
+        };
+        Request {
+            branch,
+            sha,
+            path: Some(path),
+            ..self
+        }
+    }
+}
+
+pub struct Plugin {
+    program: String,
+    fallback: Box<dyn Provider>,
+}
+
+impl Plugin {
+    /// The plugin `gitopen-<name>`, found on the `PATH` like git and cargo
+    /// find theirs.
+    pub fn new(name: &str, fallback: Box<dyn Provider>) -> Plugin {
+        Plugin {
+            program: format!("gitopen-{}", name),
+            fallback,
+        }
+    }
+
+    /// The URL the plugin prints for the request, or `None` when it printed
+    /// none.
+    fn run(&self, request: &Request) -> AnyhowResult<Option<String>> {
+        debug!(?request, "asking {}", self.program);
+        let mut child = Command::new(&self.program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| anyhow!("Could not run {}: {}", self.program, err))?;
+        // Plugins that don't need the request may exit without reading it.
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(serde_json::to_string(request)?.as_bytes());
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow!("{} failed with {}", self.program, output.status));
+        }
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(|_| anyhow!("{} printed a URL that isn't UTF-8", self.program))?;
+        Ok(stdout
+            .lines()
+            .next()
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(str::to_string))
+    }
+
+    /// The plugin's URL, or else the fallback's. A broken plugin doesn't
+    /// stop gitopen from opening the page the fallback has.
+    fn url_or(&self, request: Request, fallback: impl FnOnce() -> String) -> String {
+        match self.run(&request) {
+            Ok(Some(url)) => url,
+            Ok(None) => fallback(),
+            Err(err) => {
+                warning!("{}, using the {} URL instead", err, self.fallback.name());
+                fallback()
+            }
+        }
+    }
+}
+
+impl Provider for Plugin {
+    fn name(&self) -> &'static str {
+        self.fallback.name()
+    }
+
+    fn repo_url(&self, repo_url: &str) -> String {
+        self.url_or(Request::new("repo", repo_url), || {
+            self.fallback.repo_url(repo_url)
+        })
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        let request = Request {
+            sha: Some(commit_sha),
+            ..Request::new("commit", repo_url)
+        };
+        self.url_or(request, || self.fallback.commit_url(repo_url, commit_sha))
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.url_or(Request::new("file", repo_url).at(git_ref, path), || {
+            self.fallback.blob_url(repo_url, git_ref, path)
+        })
+    }
+
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        self.fallback.line_anchor(line_number, end_line_number)
+    }
+
+    fn line_number_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        line_number: &str,
+        end_line_number: Option<&str>,
+    ) -> String {
+        let request = Request {
+            line: line_number.parse().ok(),
+            end_line: end_line_number.and_then(|end| end.parse().ok()),
+            ..Request::new("file", repo_url).at(git_ref, path)
+        };
+        self.url_or(request, || {
+            self.fallback
+                .line_number_url(repo_url, git_ref, path, line_number, end_line_number)
+        })
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.url_or(Request::new("tree", repo_url).at(git_ref, path), || {
+            self.fallback.tree_url(repo_url, git_ref, path)
+        })
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        let request = Request {
+            number: Some(pr_number),
+            ..Request::new("pr", repo_url)
+        };
+        match self.run(&request)? {
+            Some(url) => Ok(url),
+            None => self.fallback.pr_url(repo_url, pr_number),
+        }
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        match self.run(&Request::new("raw", repo_url).at(git_ref, path))? {
+            Some(url) => Ok(url),
+            None => self.fallback.raw_url(repo_url, git_ref, path),
+        }
+    }
+
+    fn change_url(&self, repo_url: &str, change_id: &str) -> AnyhowResult<String> {
+        self.fallback.change_url(repo_url, change_id)
+    }
+
+    fn edit_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        line_number: Option<&str>,
+    ) -> AnyhowResult<String> {
+        self.fallback.edit_url(repo_url, git_ref, path, line_number)
+    }
+
+    fn branch_prs_url(&self, repo_url: &str, branch: &str) -> AnyhowResult<String> {
+        self.fallback.branch_prs_url(repo_url, branch)
+    }
+
+    fn commit_prs_url(&self, repo_url: &str, commit_sha: &str) -> AnyhowResult<String> {
+        self.fallback.commit_prs_url(repo_url, commit_sha)
+    }
+
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.fallback.issues_url(repo_url)
+    }
+
+    fn issue_url(&self, repo_url: &str, issue_number: &str) -> AnyhowResult<String> {
+        self.fallback.issue_url(repo_url, issue_number)
+    }
+
+    fn new_issue_url(
+        &self,
+        repo_url: &str,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> AnyhowResult<String> {
+        self.fallback.new_issue_url(repo_url, title, body)
+    }
+
+    fn ci_url(&self, repo_url: &str, branch: &str, commit_sha: &str) -> AnyhowResult<String> {
+        self.fallback.ci_url(repo_url, branch, commit_sha)
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        match self.run(&Request::new("blame", repo_url).at(git_ref, path))? {
+            Some(url) => Ok(url),
+            None => self.fallback.blame_url(repo_url, git_ref, path),
+        }
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        match self.run(&Request::new("history", repo_url).at(git_ref, path))? {
+            Some(url) => Ok(url),
+            None => self.fallback.history_url(repo_url, git_ref, path),
+        }
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        self.fallback.compare_url(repo_url, base, head)
+    }
+
+    fn code_search_url(&self, repo_url: &str, search: &CodeSearch) -> AnyhowResult<String> {
+        self.fallback.code_search_url(repo_url, search)
+    }
+
+    fn new_pr_url(&self, repo_url: &str, new_pr: &NewPullRequest) -> AnyhowResult<String> {
+        self.fallback.new_pr_url(repo_url, new_pr)
+    }
+
+    fn releases_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.fallback.releases_url(repo_url)
+    }
+
+    fn user_url(&self, repo_url: &str, user: &str) -> AnyhowResult<String> {
+        self.fallback.user_url(repo_url, user)
+    }
+
+    fn org_url(&self, repo_url: &str, org: &str) -> AnyhowResult<String> {
+        self.fallback.org_url(repo_url, org)
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.fallback.tags_url(repo_url)
+    }
+
+    fn archive_url(
+        &self,
+        repo_url: &str,
+        git_ref: &str,
+        format: ArchiveFormat,
+    ) -> AnyhowResult<String> {
+        self.fallback.archive_url(repo_url, git_ref, format)
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        self.fallback.tag_url(repo_url, tag)
+    }
+
+    fn release_url(&self, repo_url: &str, tag: Option<&str>) -> AnyhowResult<String> {
+        self.fallback.release_url(repo_url, tag)
+    }
+
+    fn wiki_url(&self, repo_url: &str, page: Option<&str>) -> AnyhowResult<String> {
+        self.fallback.wiki_url(repo_url, page)
+    }
+
+    fn project_page_url(&self, repo_url: &str, page: ProjectPage) -> AnyhowResult<String> {
+        self.fallback.project_page_url(repo_url, page)
+    }
+
+    fn settings_url(
+        &self,
+        repo_url: &str,
+        section: Option<SettingsSection>,
+    ) -> AnyhowResult<String> {
+        self.fallback.settings_url(repo_url, section)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::GitHub;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_request() {
+        let main = GitRef::Branch("main");
+        let request = Request {
+            line: Some(10),
+            ..Request::new("file", "https://code.corp.com/team/repo").at(&main, "src/lib.rs")
+        };
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"action":"file","url":"https://code.corp.com/team/repo","host":"code.corp.com","repo":"team/repo","branch":"main","sha":null,"path":"src/lib.rs","line":10,"end_line":null,"number":null}"#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_plugin_urls() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Links files, and leaves everything else to the fallback.
+        let script = env::temp_dir().join(format!("gitopen-test-plugin-{}", std::process::id()));
+        fs::write(
+            &script,
+            "#!/bin/sh\nread request\ncase \"$request\" in\n  *'\"action\":\"file\"'*) echo https://browse.corp/file ;;\nesac\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let plugin = Plugin {
+            program: script.to_str().unwrap().to_string(),
+            fallback: Box::new(GitHub),
+        };
+        let repo_url = "https://code.corp.com/team/repo";
+        assert_eq!(
+            plugin.line_number_url(repo_url, &GitRef::Branch("main"), "a.rs", "3", None),
+            "https://browse.corp/file"
+        );
+        assert_eq!(
+            plugin.commit_url(repo_url, "abc123"),
+            "https://code.corp.com/team/repo/commit/abc123"
+        );
+        assert_eq!(plugin.name(), "github");
+        fs::remove_file(&script).unwrap();
+
+        let missing = Plugin::new("does-not-exist", Box::new(GitHub));
+        assert_eq!(missing.repo_url(repo_url), repo_url);
+        assert!(missing.pr_url(repo_url, "1").is_err());
+    }
+}
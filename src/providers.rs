@@ -0,0 +1,3714 @@
+use crate::config::{Config, RepoConfig, UrlTemplates};
+use crate::error::GitOpenError;
+use crate::match_logic::{
+    build_query_string, encode_path, encode_segment, get_azure_devops_project_base,
+    get_bitbucket_server_base, get_gitlab_blob_link, get_host, get_repo_base,
+    get_sourcehut_tracker_base, split_repo_url, wiki_slug,
+};
+use crate::plugin::Plugin;
+use anyhow::anyhow;
+use anyhow::Result as AnyhowResult;
+
+/// The ref a file URL points at. Permalinks use a commit so the link keeps
+/// showing the same content after the branch moves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitRef<'a> {
+    Branch(&'a str),
+    Commit(&'a str),
+}
+
+impl<'a> GitRef<'a> {
+    pub fn name(&self) -> &'a str {
+        match self {
+            GitRef::Branch(name) | GitRef::Commit(name) => name,
+        }
+    }
+}
+
+/// The formats `gitopen archive` downloads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 2] = [ArchiveFormat::Zip, ArchiveFormat::TarGz];
+
+    /// The format's file extension, which also names it on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::TarGz => "tar.gz",
+        }
+    }
+
+    pub fn from_name(name: &str) -> AnyhowResult<ArchiveFormat> {
+        ArchiveFormat::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == name)
+            .ok_or_else(|| anyhow!("Unknown archive format '{}', expected zip or tar.gz", name))
+    }
+}
+
+/// A branch or tag by its own name, e.g. `main` for `refs/heads/main`.
+fn short_ref(git_ref: &str) -> &str {
+    git_ref
+        .strip_prefix("refs/heads/")
+        .or_else(|| git_ref.strip_prefix("refs/tags/"))
+        .unwrap_or(git_ref)
+}
+
+fn unsupported(provider: &str, repo_url: &str, feature: &str) -> anyhow::Error {
+    GitOpenError::UnsupportedProvider {
+        host: get_host(repo_url).to_string(),
+        provider: provider.to_string(),
+        feature: feature.to_string(),
+    }
+    .into()
+}
+
+/// Collects the optional query parameters that were actually given.
+fn present_params<'a>(params: &[(&'a str, Option<&'a str>)]) -> Vec<(&'a str, &'a str)> {
+    params
+        .iter()
+        .filter_map(|(key, value)| value.map(|value| (*key, value)))
+        .collect()
+}
+
+/// The project-wide pages besides code, issues and pull requests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectPage {
+    Discussions,
+    /// Project boards, GitLab's issue boards.
+    Projects,
+    Security,
+    /// Activity and traffic statistics.
+    Insights,
+    /// Who committed how much.
+    Contributors,
+    /// The commit graph of the branches and forks.
+    Graph,
+}
+
+impl ProjectPage {
+    pub const ALL: [ProjectPage; 6] = [
+        ProjectPage::Discussions,
+        ProjectPage::Projects,
+        ProjectPage::Security,
+        ProjectPage::Insights,
+        ProjectPage::Contributors,
+        ProjectPage::Graph,
+    ];
+
+    /// The page's subcommand, which also names it in errors.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProjectPage::Discussions => "discussions",
+            ProjectPage::Projects => "projects",
+            ProjectPage::Security => "security",
+            ProjectPage::Insights => "insights",
+            ProjectPage::Contributors => "contributors",
+            ProjectPage::Graph => "graph",
+        }
+    }
+}
+
+/// A section of a repository's settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingsSection {
+    /// Branch protection rules.
+    Branches,
+    /// CI secrets and variables.
+    Secrets,
+    Webhooks,
+    /// Members and their access.
+    Collaborators,
+    DeployKeys,
+}
+
+impl SettingsSection {
+    pub const ALL: [SettingsSection; 5] = [
+        SettingsSection::Branches,
+        SettingsSection::Secrets,
+        SettingsSection::Webhooks,
+        SettingsSection::Collaborators,
+        SettingsSection::DeployKeys,
+    ];
+
+    /// The name the section is given on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SettingsSection::Branches => "branches",
+            SettingsSection::Secrets => "secrets",
+            SettingsSection::Webhooks => "webhooks",
+            SettingsSection::Collaborators => "collaborators",
+            SettingsSection::DeployKeys => "deploy-keys",
+        }
+    }
+
+    pub fn from_name(name: &str) -> AnyhowResult<SettingsSection> {
+        SettingsSection::ALL
+            .iter()
+            .copied()
+            .find(|section| section.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = SettingsSection::ALL.iter().map(|s| s.name()).collect();
+                anyhow!(
+                    "Unknown settings section '{}', expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// What `gitopen grep` searches for: `pattern` taken literally, on
+/// `branch` and below `path` when they are given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CodeSearch<'a> {
+    pub pattern: &'a str,
+    pub branch: Option<&'a str>,
+    pub path: Option<&'a str>,
+}
+
+/// A pull request to open the creation page of: `head` is the branch with
+/// the changes, the title and body prefill the form where the provider
+/// takes them from the URL. A `draft` is opened as a work in progress.
+/// `head_owner` is the namespace of the fork `head` is on, when it's not
+/// on the repository the pull request is for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NewPullRequest<'a> {
+    pub base: &'a str,
+    pub head: &'a str,
+    pub head_owner: Option<&'a str>,
+    pub title: Option<&'a str>,
+    pub body: Option<&'a str>,
+    pub draft: bool,
+}
+
+impl NewPullRequest<'_> {
+    /// The title, marked with `prefix` for providers that tell drafts by
+    /// their title. Drafts without a title are named after the branch.
+    fn draft_title(&self, prefix: &str) -> Option<String> {
+        if self.draft {
+            Some(format!("{}{}", prefix, self.title.unwrap_or(self.head)))
+        } else {
+            self.title.map(str::to_string)
+        }
+    }
+
+    /// The compare page of the two branches, in the `base...owner:head`
+    /// form GitHub and Gitea take when `head` is on a fork.
+    fn fork_compare_url(&self, repo_url: &str) -> String {
+        let head = match self.head_owner {
+            Some(owner) => format!("{}:{}", encode_segment(owner), encode_path(self.head)),
+            None => encode_path(self.head),
+        };
+        format!(
+            "{}/compare/{}...{}",
+            get_repo_base(repo_url),
+            encode_path(self.base),
+            head
+        )
+    }
+}
+
+/// A git hosting service. Each provider knows how to build the web URLs
+/// for its own page layout, given the repository's base web URL.
+pub trait Provider {
+    fn name(&self) -> &'static str;
+    /// The repository's home page.
+    fn repo_url(&self, repo_url: &str) -> String {
+        get_repo_base(repo_url).to_string()
+    }
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String;
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String;
+    /// The suffix appended to a blob URL to highlight a line or a line range.
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String;
+    /// The suffix pointing at a column of a line, for providers that can.
+    /// The others get the line's anchor.
+    fn line_column_anchor(&self, _line_number: &str, _column: &str) -> Option<String> {
+        None
+    }
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String;
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String>;
+
+    /// The file's bare content, to download rather than view.
+    fn raw_url(&self, repo_url: &str, _git_ref: &GitRef, _path: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "raw file URLs"))
+    }
+
+    /// The Gerrit change with the given `Change-Id`.
+    fn change_url(&self, repo_url: &str, _change_id: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "changes"))
+    }
+
+    /// The file in the provider's web editor, at the line where it can.
+    fn edit_url(
+        &self,
+        repo_url: &str,
+        _git_ref: &GitRef,
+        _path: &str,
+        _line_number: Option<&str>,
+    ) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "web editors"))
+    }
+
+    /// The pull requests opened from `branch`, to find the one already open
+    /// for it.
+    fn branch_prs_url(&self, repo_url: &str, _branch: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "pull request searches"))
+    }
+
+    /// The pull requests that contain the commit, to find the one that
+    /// introduced it.
+    fn commit_prs_url(&self, repo_url: &str, _commit_sha: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "pull request searches"))
+    }
+
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "issues"))
+    }
+
+    fn issue_url(&self, repo_url: &str, _issue_number: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "issues"))
+    }
+
+    fn new_issue_url(
+        &self,
+        repo_url: &str,
+        _title: Option<&str>,
+        _body: Option<&str>,
+    ) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "issues"))
+    }
+
+    /// The CI results for the given branch and its HEAD commit.
+    fn ci_url(&self, repo_url: &str, _branch: &str, _commit_sha: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "CI pages"))
+    }
+
+    /// The blame view of a file. Line anchors are the same as for blobs.
+    fn blame_url(&self, repo_url: &str, _git_ref: &GitRef, _path: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "blame pages"))
+    }
+
+    /// The list of commits that touched a file.
+    fn history_url(&self, repo_url: &str, _git_ref: &GitRef, _path: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "file history pages"))
+    }
+
+    /// The diff between two refs, `head` being the one with the changes.
+    fn compare_url(&self, repo_url: &str, _base: &str, _head: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "compare pages"))
+    }
+
+    /// The code search of the repository, narrowed to a branch or a path
+    /// where the search asks for them.
+    fn code_search_url(&self, repo_url: &str, _search: &CodeSearch) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "code search"))
+    }
+
+    /// The page that opens a pull request, by default the compare page, which
+    /// offers to.
+    fn new_pr_url(&self, repo_url: &str, new_pr: &NewPullRequest) -> AnyhowResult<String> {
+        self.compare_url(repo_url, new_pr.base, new_pr.head)
+    }
+
+    fn releases_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "releases"))
+    }
+
+    /// The profile of a user, with the repositories they own.
+    fn user_url(&self, repo_url: &str, _user: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "user pages"))
+    }
+
+    /// The page of an organization (or group, or workspace) and its
+    /// repositories.
+    fn org_url(&self, repo_url: &str, _org: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "organization pages"))
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "tag pages"))
+    }
+
+    /// The download of the files at `git_ref`, which is a full ref name like
+    /// `refs/heads/main` or `refs/tags/v1.0`, or a commit SHA.
+    fn archive_url(
+        &self,
+        repo_url: &str,
+        _git_ref: &str,
+        _format: ArchiveFormat,
+    ) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "archive downloads"))
+    }
+
+    fn tag_url(&self, repo_url: &str, _tag: &str) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "tag pages"))
+    }
+
+    /// The release of `tag`, or else the latest release. Where releases
+    /// aren't pages of their own, the tag's page and the list of releases.
+    fn release_url(&self, repo_url: &str, tag: Option<&str>) -> AnyhowResult<String> {
+        match tag {
+            Some(tag) => self.tag_url(repo_url, tag),
+            None => self.releases_url(repo_url),
+        }
+    }
+
+    /// The wiki's home, or the page with the given title.
+    fn wiki_url(&self, repo_url: &str, _page: Option<&str>) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "wikis"))
+    }
+
+    fn project_page_url(&self, repo_url: &str, page: ProjectPage) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, page.name()))
+    }
+
+    /// The repository's settings, or one section of them.
+    fn settings_url(
+        &self,
+        repo_url: &str,
+        _section: Option<SettingsSection>,
+    ) -> AnyhowResult<String> {
+        Err(unsupported(self.name(), repo_url, "settings pages"))
+    }
+
+    fn line_number_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        line_number: &str,
+        end_line_number: Option<&str>,
+    ) -> String {
+        format!(
+            "{}{}",
+            self.blob_url(repo_url, git_ref, path),
+            self.line_anchor(line_number, end_line_number)
+        )
+    }
+}
+
+pub struct GitHub;
+pub struct GitLab;
+pub struct Bitbucket;
+pub struct BitbucketServer;
+pub struct Gitea;
+pub struct SourceHut;
+pub struct AzureDevOps;
+pub struct Gerrit;
+pub struct Cgit;
+pub struct Gitweb;
+pub struct CodeCommit;
+
+impl Provider for GitHub {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        format!("{}/commit/{}", get_repo_base(repo_url), commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        format!(
+            "{}/blob/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        )
+    }
+
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        match end_line_number {
+            Some(end) => format!("#L{}-L{}", line_number, end),
+            None => format!("#L{}", line_number),
+        }
+    }
+
+    fn line_column_anchor(&self, line_number: &str, column: &str) -> Option<String> {
+        Some(format!("#L{}C{}", line_number, column))
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        format!(
+            "{}/tree/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        )
+    }
+
+    /// github.com serves raw files from their own host, GitHub Enterprise
+    /// redirects `/raw/` to it.
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        let base = get_repo_base(repo_url);
+        let base = match get_host(repo_url) {
+            "github.com" => base.replacen("://github.com/", "://raw.githubusercontent.com/", 1),
+            _ => format!("{}/raw", base),
+        };
+        Ok(format!(
+            "{}/{}/{}",
+            base,
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    /// github.dev opens github.com repositories in VS Code for the web,
+    /// GitHub Enterprise only has the single file editor.
+    fn edit_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        line_number: Option<&str>,
+    ) -> AnyhowResult<String> {
+        let base = get_repo_base(repo_url);
+        let (base, kind) = match get_host(repo_url) {
+            "github.com" => (base.replacen("://github.com/", "://github.dev/", 1), "blob"),
+            _ => (base.to_string(), "edit"),
+        };
+        let anchor = line_number.map_or(String::new(), |line| format!("#L{}", line));
+        Ok(format!(
+            "{}/{}/{}/{}{}",
+            base,
+            kind,
+            encode_path(git_ref.name()),
+            encode_path(path),
+            anchor
+        ))
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/pull/{}", get_repo_base(repo_url), pr_number))
+    }
+
+    fn branch_prs_url(&self, repo_url: &str, branch: &str) -> AnyhowResult<String> {
+        let query = format!("is:pr head:{}", branch);
+        Ok(format!(
+            "{}/pulls{}",
+            get_repo_base(repo_url),
+            build_query_string(&[("q", &query)])
+        ))
+    }
+
+    fn commit_prs_url(&self, repo_url: &str, commit_sha: &str) -> AnyhowResult<String> {
+        let query = format!("is:pr {}", commit_sha);
+        Ok(format!(
+            "{}/pulls{}",
+            get_repo_base(repo_url),
+            build_query_string(&[("q", &query)])
+        ))
+    }
+
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/issues", get_repo_base(repo_url)))
+    }
+
+    fn issue_url(&self, repo_url: &str, issue_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/issues/{}",
+            get_repo_base(repo_url),
+            issue_number
+        ))
+    }
+
+    fn new_issue_url(
+        &self,
+        repo_url: &str,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/issues/new{}",
+            get_repo_base(repo_url),
+            build_query_string(&present_params(&[("title", title), ("body", body)]))
+        ))
+    }
+
+    fn ci_url(&self, repo_url: &str, _branch: &str, commit_sha: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/commit/{}/checks",
+            get_repo_base(repo_url),
+            commit_sha
+        ))
+    }
+
+    fn releases_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/releases", get_repo_base(repo_url)))
+    }
+
+    fn user_url(&self, repo_url: &str, user: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/{}",
+            split_repo_url(repo_url).0,
+            encode_segment(user)
+        ))
+    }
+
+    fn org_url(&self, repo_url: &str, org: &str) -> AnyhowResult<String> {
+        self.user_url(repo_url, org)
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/tags", get_repo_base(repo_url)))
+    }
+
+    fn archive_url(
+        &self,
+        repo_url: &str,
+        git_ref: &str,
+        format: ArchiveFormat,
+    ) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/archive/{}.{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref),
+            format.name()
+        ))
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/releases/tag/{}",
+            get_repo_base(repo_url),
+            encode_path(tag)
+        ))
+    }
+
+    fn release_url(&self, repo_url: &str, tag: Option<&str>) -> AnyhowResult<String> {
+        match tag {
+            Some(tag) => self.tag_url(repo_url, tag),
+            None => Ok(format!("{}/releases/latest", get_repo_base(repo_url))),
+        }
+    }
+
+    fn wiki_url(&self, repo_url: &str, page: Option<&str>) -> AnyhowResult<String> {
+        Ok(match page {
+            Some(page) => format!(
+                "{}/wiki/{}",
+                get_repo_base(repo_url),
+                encode_segment(&wiki_slug(page))
+            ),
+            None => format!("{}/wiki", get_repo_base(repo_url)),
+        })
+    }
+
+    fn project_page_url(&self, repo_url: &str, page: ProjectPage) -> AnyhowResult<String> {
+        let path = match page {
+            ProjectPage::Discussions => "discussions",
+            ProjectPage::Projects => "projects",
+            ProjectPage::Security => "security",
+            ProjectPage::Insights => "pulse",
+            ProjectPage::Contributors => "graphs/contributors",
+            ProjectPage::Graph => "network",
+        };
+        Ok(format!("{}/{}", get_repo_base(repo_url), path))
+    }
+
+    fn settings_url(
+        &self,
+        repo_url: &str,
+        section: Option<SettingsSection>,
+    ) -> AnyhowResult<String> {
+        let path = match section {
+            None => "",
+            Some(SettingsSection::Branches) => "/branches",
+            Some(SettingsSection::Secrets) => "/secrets/actions",
+            Some(SettingsSection::Webhooks) => "/hooks",
+            Some(SettingsSection::Collaborators) => "/access",
+            Some(SettingsSection::DeployKeys) => "/keys",
+        };
+        Ok(format!("{}/settings{}", get_repo_base(repo_url), path))
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/blame/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/commits/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/compare/{}...{}",
+            get_repo_base(repo_url),
+            encode_path(base),
+            encode_path(head)
+        ))
+    }
+
+    /// GitHub's code search only has the default branch.
+    fn code_search_url(&self, repo_url: &str, search: &CodeSearch) -> AnyhowResult<String> {
+        if search.branch.is_some() {
+            return Err(unsupported(
+                self.name(),
+                repo_url,
+                "code searches of a branch",
+            ));
+        }
+        let (origin, repo) = split_repo_url(repo_url);
+        let query = match search.path {
+            Some(path) => format!("repo:{} path:{} {}", repo, path, search.pattern),
+            None => format!("repo:{} {}", repo, search.pattern),
+        };
+        Ok(format!(
+            "{}/search{}",
+            origin,
+            build_query_string(&[("q", &query), ("type", "code")])
+        ))
+    }
+
+    fn new_pr_url(&self, repo_url: &str, new_pr: &NewPullRequest) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}{}",
+            new_pr.fork_compare_url(repo_url),
+            build_query_string(&present_params(&[
+                ("quick_pull", Some("1")),
+                ("draft", Some("1").filter(|_| new_pr.draft)),
+                ("title", new_pr.title),
+                ("body", new_pr.body)
+            ]))
+        ))
+    }
+}
+
+impl Provider for GitLab {
+    fn name(&self) -> &'static str {
+        "gitlab"
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        format!("{}/-/commit/{}", get_repo_base(repo_url), commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        get_gitlab_blob_link(repo_url, git_ref, path)
+    }
+
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        match end_line_number {
+            Some(end) => format!("#L{}-{}", line_number, end),
+            None => format!("#L{}", line_number),
+        }
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        format!(
+            "{}/-/tree/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        )
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/raw/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    /// The Web IDE takes the project path after its own route.
+    fn edit_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        _line_number: Option<&str>,
+    ) -> AnyhowResult<String> {
+        let (origin, project) = split_repo_url(repo_url);
+        Ok(format!(
+            "{}/-/ide/project/{}/edit/{}/-/{}",
+            origin,
+            project,
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/merge_requests/{}",
+            get_repo_base(repo_url),
+            pr_number
+        ))
+    }
+
+    fn branch_prs_url(&self, repo_url: &str, branch: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/merge_requests{}",
+            get_repo_base(repo_url),
+            build_query_string(&[("source_branch", branch)])
+        ))
+    }
+
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/-/issues", get_repo_base(repo_url)))
+    }
+
+    fn issue_url(&self, repo_url: &str, issue_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/issues/{}",
+            get_repo_base(repo_url),
+            issue_number
+        ))
+    }
+
+    fn new_issue_url(
+        &self,
+        repo_url: &str,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/issues/new{}",
+            get_repo_base(repo_url),
+            build_query_string(&present_params(&[
+                ("issue[title]", title),
+                ("issue[description]", body)
+            ]))
+        ))
+    }
+
+    fn ci_url(&self, repo_url: &str, branch: &str, _commit_sha: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/pipelines{}",
+            get_repo_base(repo_url),
+            build_query_string(&[("ref", branch)])
+        ))
+    }
+
+    fn releases_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/-/releases", get_repo_base(repo_url)))
+    }
+
+    fn user_url(&self, repo_url: &str, user: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/{}",
+            split_repo_url(repo_url).0,
+            encode_segment(user)
+        ))
+    }
+
+    /// Groups can have subgroups, `org` is their whole path.
+    fn org_url(&self, repo_url: &str, org: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/{}",
+            split_repo_url(repo_url).0,
+            encode_path(org)
+        ))
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/-/tags", get_repo_base(repo_url)))
+    }
+
+    /// The file is named `<project>-<ref>`, with the `/`s of the ref as `-`.
+    fn archive_url(
+        &self,
+        repo_url: &str,
+        git_ref: &str,
+        format: ArchiveFormat,
+    ) -> AnyhowResult<String> {
+        let short = short_ref(git_ref);
+        let project = split_repo_url(repo_url)
+            .1
+            .rsplit('/')
+            .next()
+            .unwrap_or_default();
+        Ok(format!(
+            "{}/-/archive/{}/{}-{}.{}",
+            get_repo_base(repo_url),
+            encode_path(short),
+            encode_segment(project),
+            encode_segment(&short.replace('/', "-")),
+            format.name()
+        ))
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/tags/{}",
+            get_repo_base(repo_url),
+            encode_path(tag)
+        ))
+    }
+
+    fn release_url(&self, repo_url: &str, tag: Option<&str>) -> AnyhowResult<String> {
+        match tag {
+            Some(tag) => Ok(format!(
+                "{}/-/releases/{}",
+                get_repo_base(repo_url),
+                encode_segment(tag)
+            )),
+            None => Ok(format!(
+                "{}/-/releases/permalink/latest",
+                get_repo_base(repo_url)
+            )),
+        }
+    }
+
+    /// Nested pages keep their `/`, as GitLab wikis have directories.
+    fn wiki_url(&self, repo_url: &str, page: Option<&str>) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/wikis/{}",
+            get_repo_base(repo_url),
+            encode_path(&wiki_slug(page.unwrap_or("home")))
+        ))
+    }
+
+    fn project_page_url(&self, repo_url: &str, page: ProjectPage) -> AnyhowResult<String> {
+        let path = match page {
+            ProjectPage::Discussions => {
+                return Err(unsupported(self.name(), repo_url, page.name()))
+            }
+            ProjectPage::Projects => "boards",
+            ProjectPage::Security => "security/dashboard",
+            ProjectPage::Insights => "value_stream_analytics",
+            // Both graphs are of a ref, HEAD being the default branch.
+            ProjectPage::Contributors => "graphs/HEAD",
+            ProjectPage::Graph => "network/HEAD",
+        };
+        Ok(format!("{}/-/{}", get_repo_base(repo_url), path))
+    }
+
+    /// Most sections are parts of a longer page, which the anchor expands.
+    fn settings_url(
+        &self,
+        repo_url: &str,
+        section: Option<SettingsSection>,
+    ) -> AnyhowResult<String> {
+        let path = match section {
+            None => "edit",
+            Some(SettingsSection::Branches) => {
+                "-/settings/repository#js-protected-branches-settings"
+            }
+            Some(SettingsSection::Secrets) => "-/settings/ci_cd#js-cicd-variables-settings",
+            Some(SettingsSection::Webhooks) => "-/hooks",
+            Some(SettingsSection::Collaborators) => "-/project_members",
+            Some(SettingsSection::DeployKeys) => "-/settings/repository#js-deploy-keys-settings",
+        };
+        Ok(format!("{}/{}", get_repo_base(repo_url), path))
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/blame/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/commits/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/compare/{}...{}",
+            get_repo_base(repo_url),
+            encode_path(base),
+            encode_path(head)
+        ))
+    }
+
+    fn code_search_url(&self, repo_url: &str, search: &CodeSearch) -> AnyhowResult<String> {
+        let query = match search.path {
+            Some(path) => format!("{} path:{}", search.pattern, path),
+            None => search.pattern.to_string(),
+        };
+        let params = present_params(&[
+            ("search", Some(&query)),
+            ("scope", Some("blobs")),
+            ("repository_ref", search.branch),
+        ]);
+        Ok(format!(
+            "{}/-/search{}",
+            get_repo_base(repo_url),
+            build_query_string(&params)
+        ))
+    }
+
+    fn new_pr_url(&self, repo_url: &str, new_pr: &NewPullRequest) -> AnyhowResult<String> {
+        let title = new_pr.draft_title("Draft: ");
+        Ok(format!(
+            "{}/-/merge_requests/new{}",
+            get_repo_base(repo_url),
+            build_query_string(&present_params(&[
+                ("merge_request[source_branch]", Some(new_pr.head)),
+                ("merge_request[target_branch]", Some(new_pr.base)),
+                ("merge_request[title]", title.as_deref()),
+                ("merge_request[description]", new_pr.body)
+            ]))
+        ))
+    }
+}
+
+impl Provider for Bitbucket {
+    fn name(&self) -> &'static str {
+        "bitbucket"
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        format!("{}/commits/{}", get_repo_base(repo_url), commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.tree_url(repo_url, git_ref, path)
+    }
+
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        match end_line_number {
+            Some(end) => format!("#lines-{}:{}", line_number, end),
+            None => format!("#lines-{}", line_number),
+        }
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        format!(
+            "{}/src/{}/{}",
+            get_repo_base(repo_url),
+            encode_segment(git_ref.name()),
+            encode_path(path)
+        )
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/raw/{}/{}",
+            get_repo_base(repo_url),
+            encode_segment(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    fn edit_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        _line_number: Option<&str>,
+    ) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}?mode=edit",
+            self.blob_url(repo_url, git_ref, path)
+        ))
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/pull-requests/{}",
+            get_repo_base(repo_url),
+            pr_number
+        ))
+    }
+
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/issues", get_repo_base(repo_url)))
+    }
+
+    fn issue_url(&self, repo_url: &str, issue_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/issues/{}",
+            get_repo_base(repo_url),
+            issue_number
+        ))
+    }
+
+    // Bitbucket's issue form can't be prefilled through the URL.
+    fn new_issue_url(
+        &self,
+        repo_url: &str,
+        _title: Option<&str>,
+        _body: Option<&str>,
+    ) -> AnyhowResult<String> {
+        Ok(format!("{}/issues/new", get_repo_base(repo_url)))
+    }
+
+    fn ci_url(&self, repo_url: &str, _branch: &str, _commit_sha: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/pipelines", get_repo_base(repo_url)))
+    }
+
+    fn archive_url(
+        &self,
+        repo_url: &str,
+        git_ref: &str,
+        format: ArchiveFormat,
+    ) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/get/{}.{}",
+            get_repo_base(repo_url),
+            encode_segment(short_ref(git_ref)),
+            format.name()
+        ))
+    }
+
+    /// The workspace, which is what owns repositories on Bitbucket.
+    fn org_url(&self, repo_url: &str, org: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/{}/",
+            split_repo_url(repo_url).0,
+            encode_segment(org)
+        ))
+    }
+
+    fn settings_url(
+        &self,
+        repo_url: &str,
+        section: Option<SettingsSection>,
+    ) -> AnyhowResult<String> {
+        let path = match section {
+            None => "",
+            Some(SettingsSection::Branches) => "/branch-restrictions",
+            Some(SettingsSection::Secrets) => "/pipelines/repository-variables",
+            Some(SettingsSection::Webhooks) => "/webhooks",
+            Some(SettingsSection::Collaborators) => "/permissions",
+            Some(SettingsSection::DeployKeys) => "/access-keys",
+        };
+        Ok(format!("{}/admin{}", get_repo_base(repo_url), path))
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/annotate/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    // Bitbucket lists the source first and separates the refs with a carriage return.
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/branches/compare/{}%0D{}",
+            get_repo_base(repo_url),
+            encode_segment(head),
+            encode_segment(base)
+        ))
+    }
+
+    fn new_pr_url(&self, repo_url: &str, new_pr: &NewPullRequest) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/pull-requests/new{}",
+            get_repo_base(repo_url),
+            build_query_string(&[("source", new_pr.head), ("dest", new_pr.base)])
+        ))
+    }
+}
+
+// Bitbucket Server URLs are rebuilt from the clone URL on every call, so a
+// path that does not look like `KEY/repo` falls back to the clone URL itself.
+impl Provider for BitbucketServer {
+    fn name(&self) -> &'static str {
+        "bitbucket-server"
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        let base = get_bitbucket_server_base(repo_url)
+            .unwrap_or_else(|_| get_repo_base(repo_url).to_string());
+        format!("{}/commits/{}", base, commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.tree_url(repo_url, git_ref, path)
+    }
+
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        match end_line_number {
+            Some(end) => format!("#{}-{}", line_number, end),
+            None => format!("#{}", line_number),
+        }
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        let base = get_bitbucket_server_base(repo_url)
+            .unwrap_or_else(|_| get_repo_base(repo_url).to_string());
+        match git_ref {
+            GitRef::Branch(branch) => format!(
+                "{}/browse/{}?at=refs/heads/{}",
+                base,
+                encode_path(path),
+                encode_path(branch)
+            ),
+            GitRef::Commit(sha) => format!("{}/browse/{}?at={}", base, encode_path(path), sha),
+        }
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(self
+            .tree_url(repo_url, git_ref, path)
+            .replacen("/browse/", "/raw/", 1))
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/pull-requests/{}",
+            get_bitbucket_server_base(repo_url)?,
+            pr_number
+        ))
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/compare/commits{}",
+            get_bitbucket_server_base(repo_url)?,
+            build_query_string(&[("sourceBranch", head), ("targetBranch", base)])
+        ))
+    }
+}
+
+impl Provider for Gitea {
+    fn name(&self) -> &'static str {
+        "gitea"
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        format!("{}/commit/{}", get_repo_base(repo_url), commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.tree_url(repo_url, git_ref, path)
+    }
+
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        match end_line_number {
+            Some(end) => format!("#L{}-L{}", line_number, end),
+            None => format!("#L{}", line_number),
+        }
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        let (kind, name) = match git_ref {
+            GitRef::Branch(branch) => ("branch", branch),
+            GitRef::Commit(sha) => ("commit", sha),
+        };
+        format!(
+            "{}/src/{}/{}/{}",
+            get_repo_base(repo_url),
+            kind,
+            encode_path(name),
+            encode_path(path)
+        )
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(self
+            .tree_url(repo_url, git_ref, path)
+            .replacen("/src/", "/raw/", 1))
+    }
+
+    fn edit_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        _line_number: Option<&str>,
+    ) -> AnyhowResult<String> {
+        match git_ref {
+            GitRef::Branch(branch) => Ok(format!(
+                "{}/_edit/{}/{}",
+                get_repo_base(repo_url),
+                encode_path(branch),
+                encode_path(path)
+            )),
+            GitRef::Commit(_) => Err(anyhow!("{} only edits files on a branch", self.name())),
+        }
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/pulls/{}", get_repo_base(repo_url), pr_number))
+    }
+
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/issues", get_repo_base(repo_url)))
+    }
+
+    fn issue_url(&self, repo_url: &str, issue_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/issues/{}",
+            get_repo_base(repo_url),
+            issue_number
+        ))
+    }
+
+    fn new_issue_url(
+        &self,
+        repo_url: &str,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/issues/new{}",
+            get_repo_base(repo_url),
+            build_query_string(&present_params(&[("title", title), ("body", body)]))
+        ))
+    }
+
+    fn ci_url(&self, repo_url: &str, _branch: &str, _commit_sha: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/actions", get_repo_base(repo_url)))
+    }
+
+    fn releases_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/releases", get_repo_base(repo_url)))
+    }
+
+    fn user_url(&self, repo_url: &str, user: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/{}",
+            split_repo_url(repo_url).0,
+            encode_segment(user)
+        ))
+    }
+
+    fn org_url(&self, repo_url: &str, org: &str) -> AnyhowResult<String> {
+        self.user_url(repo_url, org)
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/tags", get_repo_base(repo_url)))
+    }
+
+    fn archive_url(
+        &self,
+        repo_url: &str,
+        git_ref: &str,
+        format: ArchiveFormat,
+    ) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/archive/{}.{}",
+            get_repo_base(repo_url),
+            encode_path(short_ref(git_ref)),
+            format.name()
+        ))
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/releases/tag/{}",
+            get_repo_base(repo_url),
+            encode_path(tag)
+        ))
+    }
+
+    fn release_url(&self, repo_url: &str, tag: Option<&str>) -> AnyhowResult<String> {
+        match tag {
+            Some(tag) => self.tag_url(repo_url, tag),
+            None => Ok(format!("{}/releases/latest", get_repo_base(repo_url))),
+        }
+    }
+
+    fn wiki_url(&self, repo_url: &str, page: Option<&str>) -> AnyhowResult<String> {
+        Ok(match page {
+            Some(page) => format!(
+                "{}/wiki/{}",
+                get_repo_base(repo_url),
+                encode_segment(&wiki_slug(page))
+            ),
+            None => format!("{}/wiki", get_repo_base(repo_url)),
+        })
+    }
+
+    fn project_page_url(&self, repo_url: &str, page: ProjectPage) -> AnyhowResult<String> {
+        let path = match page {
+            ProjectPage::Projects => "projects",
+            ProjectPage::Insights => "activity",
+            ProjectPage::Contributors => "activity/contributors",
+            ProjectPage::Graph => "graph",
+            _ => return Err(unsupported(self.name(), repo_url, page.name())),
+        };
+        Ok(format!("{}/{}", get_repo_base(repo_url), path))
+    }
+
+    fn settings_url(
+        &self,
+        repo_url: &str,
+        section: Option<SettingsSection>,
+    ) -> AnyhowResult<String> {
+        let path = match section {
+            None => "",
+            Some(SettingsSection::Branches) => "/branches",
+            Some(SettingsSection::Secrets) => "/actions/secrets",
+            Some(SettingsSection::Webhooks) => "/hooks",
+            Some(SettingsSection::Collaborators) => "/collaboration",
+            Some(SettingsSection::DeployKeys) => "/keys",
+        };
+        Ok(format!("{}/settings{}", get_repo_base(repo_url), path))
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        let (kind, name) = match git_ref {
+            GitRef::Branch(branch) => ("branch", branch),
+            GitRef::Commit(sha) => ("commit", sha),
+        };
+        Ok(format!(
+            "{}/blame/{}/{}/{}",
+            get_repo_base(repo_url),
+            kind,
+            encode_path(name),
+            encode_path(path)
+        ))
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        let (kind, name) = match git_ref {
+            GitRef::Branch(branch) => ("branch", branch),
+            GitRef::Commit(sha) => ("commit", sha),
+        };
+        Ok(format!(
+            "{}/commits/{}/{}/{}",
+            get_repo_base(repo_url),
+            kind,
+            encode_path(name),
+            encode_path(path)
+        ))
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/compare/{}...{}",
+            get_repo_base(repo_url),
+            encode_path(base),
+            encode_path(head)
+        ))
+    }
+
+    /// Gitea searches the default branch as a whole.
+    fn code_search_url(&self, repo_url: &str, search: &CodeSearch) -> AnyhowResult<String> {
+        if search.branch.is_some() || search.path.is_some() {
+            return Err(unsupported(
+                self.name(),
+                repo_url,
+                "code searches of a branch or a path",
+            ));
+        }
+        Ok(format!(
+            "{}/search{}",
+            get_repo_base(repo_url),
+            build_query_string(&[("q", search.pattern)])
+        ))
+    }
+
+    fn new_pr_url(&self, repo_url: &str, new_pr: &NewPullRequest) -> AnyhowResult<String> {
+        let title = new_pr.draft_title("WIP: ");
+        Ok(format!(
+            "{}{}",
+            new_pr.fork_compare_url(repo_url),
+            build_query_string(&present_params(&[
+                ("expand", Some("1")),
+                ("title", title.as_deref()),
+                ("body", new_pr.body)
+            ]))
+        ))
+    }
+}
+
+impl Provider for SourceHut {
+    fn name(&self) -> &'static str {
+        "sourcehut"
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        format!("{}/commit/{}", get_repo_base(repo_url), commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.tree_url(repo_url, git_ref, path)
+    }
+
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        match end_line_number {
+            Some(end) => format!("#L{}-{}", line_number, end),
+            None => format!("#L{}", line_number),
+        }
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        format!(
+            "{}/tree/{}/item/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        )
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/blob/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    fn pr_url(&self, _repo_url: &str, _pr_number: &str) -> AnyhowResult<String> {
+        Err(anyhow!(
+            "sourcehut has no pull requests, patches are sent to mailing lists"
+        ))
+    }
+
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        get_sourcehut_tracker_base(repo_url)
+    }
+
+    fn issue_url(&self, repo_url: &str, issue_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/{}",
+            get_sourcehut_tracker_base(repo_url)?,
+            issue_number
+        ))
+    }
+
+    // The tracker's page has the form for a new ticket, which can't be
+    // filled in from the URL.
+    fn new_issue_url(
+        &self,
+        repo_url: &str,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> AnyhowResult<String> {
+        if title.is_some() || body.is_some() {
+            return Err(anyhow!(
+                "todo.sr.ht can't fill in the title or body of new tickets"
+            ));
+        }
+        get_sourcehut_tracker_base(repo_url)
+    }
+
+    // sourcehut attaches release notes and artifacts to the tags themselves.
+    fn releases_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.tags_url(repo_url)
+    }
+
+    /// Users and organizations are both `~name`s.
+    fn user_url(&self, repo_url: &str, user: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/~{}",
+            split_repo_url(repo_url).0,
+            encode_segment(user.trim_start_matches('~'))
+        ))
+    }
+
+    fn org_url(&self, repo_url: &str, org: &str) -> AnyhowResult<String> {
+        self.user_url(repo_url, org)
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/refs", get_repo_base(repo_url)))
+    }
+
+    /// Only tarballs can be downloaded.
+    fn archive_url(
+        &self,
+        repo_url: &str,
+        git_ref: &str,
+        format: ArchiveFormat,
+    ) -> AnyhowResult<String> {
+        if format != ArchiveFormat::TarGz {
+            return Err(unsupported(self.name(), repo_url, "zip archives"));
+        }
+        Ok(format!(
+            "{}/archive/{}.tar.gz",
+            get_repo_base(repo_url),
+            encode_path(short_ref(git_ref))
+        ))
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/refs/{}",
+            get_repo_base(repo_url),
+            encode_path(tag)
+        ))
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/blame/{}/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/log/{}/item/{}",
+            get_repo_base(repo_url),
+            encode_path(git_ref.name()),
+            encode_path(path)
+        ))
+    }
+}
+
+impl Provider for AzureDevOps {
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        format!("{}/commit/{}", get_repo_base(repo_url), commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.tree_url(repo_url, git_ref, path)
+    }
+
+    // Azure DevOps selects lines through query parameters, and only
+    // highlights when both the start and end columns are given.
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        format!(
+            "&line={}&lineEnd={}&lineStartColumn=1&lineEndColumn=1&lineStyle=plain&_a=contents",
+            line_number,
+            end_line_number.unwrap_or(line_number)
+        )
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        let version = match git_ref {
+            GitRef::Branch(branch) => format!("GB{}", encode_segment(branch)),
+            GitRef::Commit(sha) => format!("GC{}", sha),
+        };
+        format!(
+            "{}?path=/{}&version={}",
+            get_repo_base(repo_url),
+            encode_path(path),
+            version
+        )
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/pullrequest/{}",
+            get_repo_base(repo_url),
+            pr_number
+        ))
+    }
+
+    // Work items belong to the project, not to a single repository.
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/_workitems",
+            get_azure_devops_project_base(repo_url)
+        ))
+    }
+
+    fn issue_url(&self, repo_url: &str, issue_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/_workitems/edit/{}",
+            get_azure_devops_project_base(repo_url),
+            issue_number
+        ))
+    }
+
+    fn ci_url(&self, repo_url: &str, _branch: &str, _commit_sha: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/_build",
+            get_azure_devops_project_base(repo_url)
+        ))
+    }
+
+    fn project_page_url(&self, repo_url: &str, page: ProjectPage) -> AnyhowResult<String> {
+        match page {
+            ProjectPage::Projects => Ok(format!(
+                "{}/_boards",
+                get_azure_devops_project_base(repo_url)
+            )),
+            _ => Err(unsupported(self.name(), repo_url, page.name())),
+        }
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/tags", get_repo_base(repo_url)))
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}?version=GT{}",
+            get_repo_base(repo_url),
+            encode_segment(tag)
+        ))
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}&_a=history",
+            self.tree_url(repo_url, git_ref, path)
+        ))
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/branchCompare?baseVersion=GB{}&targetVersion=GB{}",
+            get_repo_base(repo_url),
+            encode_path(base),
+            encode_path(head)
+        ))
+    }
+}
+
+/// Gerrit serves code through Gitiles, under `/plugins/gitiles/` of its own
+/// host, except on googlesource.com, where Gitiles has the host and the
+/// reviews are on `<name>-review.googlesource.com`.
+impl Gerrit {
+    /// The project path, without the `a/` that authenticated http remotes
+    /// put in front of it.
+    fn project(repo_url: &str) -> &str {
+        let project = split_repo_url(repo_url).1;
+        project.strip_prefix("a/").unwrap_or(project)
+    }
+
+    fn gitiles_base(repo_url: &str) -> String {
+        let origin = split_repo_url(repo_url).0;
+        if get_host(repo_url).ends_with(".googlesource.com") {
+            format!("{}/{}", origin, Gerrit::project(repo_url))
+        } else {
+            format!("{}/plugins/gitiles/{}", origin, Gerrit::project(repo_url))
+        }
+    }
+
+    fn review_origin(repo_url: &str) -> String {
+        let origin = split_repo_url(repo_url).0;
+        match get_host(repo_url).strip_suffix(".googlesource.com") {
+            Some(name) if !name.ends_with("-review") => {
+                origin.replacen(name, &format!("{}-review", name), 1)
+            }
+            _ => origin.to_string(),
+        }
+    }
+
+    /// Gitiles takes full ref names, so a branch with a `/` stays unambiguous.
+    fn gitiles_ref(git_ref: &GitRef) -> String {
+        match git_ref {
+            GitRef::Branch(branch) => format!("refs/heads/{}", encode_path(branch)),
+            GitRef::Commit(sha) => sha.to_string(),
+        }
+    }
+
+    fn gitiles_url(repo_url: &str, view: &str, git_ref: &GitRef, path: &str) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            Gerrit::gitiles_base(repo_url),
+            view,
+            Gerrit::gitiles_ref(git_ref),
+            encode_path(path)
+        )
+    }
+}
+
+impl Provider for Gerrit {
+    fn name(&self) -> &'static str {
+        "gerrit"
+    }
+
+    fn repo_url(&self, repo_url: &str) -> String {
+        Gerrit::gitiles_base(repo_url)
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        format!("{}/+/{}", Gerrit::gitiles_base(repo_url), commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        Gerrit::gitiles_url(repo_url, "+", git_ref, path)
+    }
+
+    // Gitiles only anchors single lines.
+    fn line_anchor(&self, line_number: &str, _end_line_number: Option<&str>) -> String {
+        format!("#{}", line_number)
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        Gerrit::gitiles_url(repo_url, "+", git_ref, path)
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/c/{}/+/{}",
+            Gerrit::review_origin(repo_url),
+            Gerrit::project(repo_url),
+            pr_number
+        ))
+    }
+
+    fn change_url(&self, repo_url: &str, change_id: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/q/{}",
+            Gerrit::review_origin(repo_url),
+            encode_segment(change_id)
+        ))
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(Gerrit::gitiles_url(repo_url, "+blame", git_ref, path))
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(Gerrit::gitiles_url(repo_url, "+log", git_ref, path))
+    }
+}
+
+/// cgit, which puts the page after the repository and the ref in the query,
+/// e.g. `/tree/<path>?h=<branch>`. Repositories keep the `.git` suffix
+/// their name has on the server.
+impl Cgit {
+    fn base(repo_url: &str) -> &str {
+        repo_url.trim().trim_end_matches('/')
+    }
+
+    fn ref_query(git_ref: &GitRef) -> String {
+        match git_ref {
+            GitRef::Branch(branch) => build_query_string(&[("h", branch)]),
+            GitRef::Commit(sha) => build_query_string(&[("id", sha)]),
+        }
+    }
+
+    fn page_url(repo_url: &str, page: &str, git_ref: &GitRef, path: &str) -> String {
+        format!(
+            "{}/{}/{}{}",
+            Cgit::base(repo_url),
+            page,
+            encode_path(path),
+            Cgit::ref_query(git_ref)
+        )
+    }
+}
+
+impl Provider for Cgit {
+    fn name(&self) -> &'static str {
+        "cgit"
+    }
+
+    fn repo_url(&self, repo_url: &str) -> String {
+        Cgit::base(repo_url).to_string()
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        format!("{}/commit/?id={}", Cgit::base(repo_url), commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.tree_url(repo_url, git_ref, path)
+    }
+
+    // cgit only anchors single lines.
+    fn line_anchor(&self, line_number: &str, _end_line_number: Option<&str>) -> String {
+        format!("#n{}", line_number)
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        Cgit::page_url(repo_url, "tree", git_ref, path)
+    }
+
+    fn pr_url(&self, _repo_url: &str, _pr_number: &str) -> AnyhowResult<String> {
+        Err(anyhow!("cgit has no pull requests"))
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(Cgit::page_url(repo_url, "plain", git_ref, path))
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(Cgit::page_url(repo_url, "blame", git_ref, path))
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(Cgit::page_url(repo_url, "log", git_ref, path))
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/diff/{}",
+            Cgit::base(repo_url),
+            build_query_string(&[("id", head), ("id2", base)])
+        ))
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(format!("{}/refs/", Cgit::base(repo_url)))
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/tag/{}",
+            Cgit::base(repo_url),
+            build_query_string(&[("h", tag)])
+        ))
+    }
+}
+
+/// gitweb, which takes everything in `;`-separated query parameters, with
+/// the project named by its path below the host, e.g.
+/// `/?p=team/project.git;a=blob;f=<path>;hb=<ref>`.
+impl Gitweb {
+    fn page_url(repo_url: &str, params: &[(&str, &str)]) -> String {
+        let (origin, project) = split_repo_url(repo_url.trim().trim_end_matches('/'));
+        let project = match project.ends_with(".git") {
+            true => project.to_string(),
+            false => format!("{}.git", project),
+        };
+        let params: Vec<String> = [("p", project.as_str())]
+            .iter()
+            .chain(params)
+            .map(|(key, value)| format!("{}={}", key, encode_path(value)))
+            .collect();
+        format!("{}/?{}", origin, params.join(";"))
+    }
+
+    fn file_url(repo_url: &str, action: &str, git_ref: &GitRef, path: &str) -> String {
+        let hash_base = match git_ref {
+            GitRef::Branch(branch) => format!("refs/heads/{}", branch),
+            GitRef::Commit(sha) => sha.to_string(),
+        };
+        let mut params = vec![("a", action)];
+        if !path.is_empty() {
+            params.push(("f", path));
+        }
+        params.push(("hb", &hash_base));
+        Gitweb::page_url(repo_url, &params)
+    }
+}
+
+impl Provider for Gitweb {
+    fn name(&self) -> &'static str {
+        "gitweb"
+    }
+
+    fn repo_url(&self, repo_url: &str) -> String {
+        Gitweb::page_url(repo_url, &[("a", "summary")])
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        Gitweb::page_url(repo_url, &[("a", "commit"), ("h", commit_sha)])
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        Gitweb::file_url(repo_url, "blob", git_ref, path)
+    }
+
+    // gitweb only anchors single lines.
+    fn line_anchor(&self, line_number: &str, _end_line_number: Option<&str>) -> String {
+        format!("#l{}", line_number)
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        Gitweb::file_url(repo_url, "tree", git_ref, path)
+    }
+
+    fn pr_url(&self, _repo_url: &str, _pr_number: &str) -> AnyhowResult<String> {
+        Err(anyhow!("gitweb has no pull requests"))
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(Gitweb::file_url(repo_url, "blob_plain", git_ref, path))
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(Gitweb::file_url(repo_url, "blame", git_ref, path))
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(Gitweb::file_url(repo_url, "history", git_ref, path))
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        Ok(Gitweb::page_url(
+            repo_url,
+            &[("a", "commitdiff"), ("h", head), ("hp", base)],
+        ))
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        Ok(Gitweb::page_url(repo_url, &[("a", "tags")]))
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        Ok(Gitweb::page_url(repo_url, &[("a", "tag"), ("h", tag)]))
+    }
+}
+
+/// AWS CodeCommit serves no pages of its own: repositories are browsed in
+/// the AWS console of the region in the git host,
+/// `git-codecommit.<region>.amazonaws.com`, which also takes the region and
+/// the highlighted lines in the query.
+impl CodeCommit {
+    fn region(repo_url: &str) -> &str {
+        let host = get_host(repo_url);
+        let region = host.strip_prefix("git-codecommit.").unwrap_or(host);
+        region.split('.').next().unwrap_or_default()
+    }
+
+    fn page_url(repo_url: &str, page: &str) -> String {
+        let repo = split_repo_url(repo_url).1;
+        let repo = repo.strip_prefix("v1/repos/").unwrap_or(repo);
+        let region = CodeCommit::region(repo_url);
+        format!(
+            "https://{}.console.aws.amazon.com/codesuite/codecommit/repositories/{}/{}{}",
+            region,
+            encode_segment(repo),
+            page,
+            build_query_string(&[("region", region)])
+        )
+    }
+
+    fn browse_path(git_ref: &GitRef, path: &str) -> String {
+        let git_ref = match git_ref {
+            GitRef::Branch(branch) => format!("refs/heads/{}", encode_path(branch)),
+            GitRef::Commit(sha) => sha.to_string(),
+        };
+        if path.is_empty() {
+            format!("browse/{}", git_ref)
+        } else {
+            format!("browse/{}/--/{}", git_ref, encode_path(path))
+        }
+    }
+}
+
+impl Provider for CodeCommit {
+    fn name(&self) -> &'static str {
+        "codecommit"
+    }
+
+    fn repo_url(&self, repo_url: &str) -> String {
+        CodeCommit::page_url(repo_url, "browse")
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        CodeCommit::page_url(repo_url, &format!("commit/{}", commit_sha))
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        CodeCommit::page_url(repo_url, &CodeCommit::browse_path(git_ref, path))
+    }
+
+    // Appended to the query that already names the region.
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        format!(
+            "&lines={}-{}",
+            line_number,
+            end_line_number.unwrap_or(line_number)
+        )
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.blob_url(repo_url, git_ref, path)
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        Ok(CodeCommit::page_url(
+            repo_url,
+            &format!("pull-requests/{}/details", pr_number),
+        ))
+    }
+}
+
+/// Picks the provider from the host of the repository URL. Unknown hosts
+/// fall back to GitHub-style URLs, which is what gitopen always generated.
+pub fn provider_for_url(repo_url: &str) -> Box<dyn Provider> {
+    detect_provider(repo_url).unwrap_or_else(|| Box::new(GitHub))
+}
+
+/// The provider the host of the repository URL gives away, if it does.
+pub fn detect_provider(repo_url: &str) -> Option<Box<dyn Provider>> {
+    let host = get_host(repo_url);
+    let provider: Box<dyn Provider> = if host.contains("gitlab") {
+        Box::new(GitLab)
+    } else if host == "bitbucket.org" {
+        Box::new(Bitbucket)
+    } else if host.contains("bitbucket") || split_repo_url(repo_url).1.starts_with("scm/") {
+        Box::new(BitbucketServer)
+    } else if host.contains("gitea") || host.contains("forgejo") || host == "codeberg.org" {
+        Box::new(Gitea)
+    } else if host.ends_with("sr.ht") {
+        Box::new(SourceHut)
+    } else if host == "dev.azure.com" || host.ends_with("visualstudio.com") {
+        Box::new(AzureDevOps)
+    } else if host.contains("gerrit") || host.ends_with(".googlesource.com") {
+        Box::new(Gerrit)
+    } else if host.starts_with("git-codecommit.") && host.contains(".amazonaws.com") {
+        Box::new(CodeCommit)
+    } else if host.contains("github") {
+        Box::new(GitHub)
+    } else {
+        return None;
+    };
+    Some(provider)
+}
+
+/// A host with URL templates from the config file. Pages without a template
+/// are built by the provider the host would get otherwise.
+pub struct Templated {
+    templates: UrlTemplates,
+    fallback: Box<dyn Provider>,
+}
+
+impl Templated {
+    fn fill(&self, template: &str, repo_url: &str, values: &[(&str, &str)]) -> String {
+        let mut url = template
+            .replace("{host}", get_host(repo_url))
+            .replace("{repo}", split_repo_url(repo_url).1);
+        for (placeholder, value) in values {
+            url = url.replace(&format!("{{{}}}", placeholder), value);
+        }
+        url
+    }
+
+    fn fill_file(&self, template: &str, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        self.fill(
+            template,
+            repo_url,
+            &[
+                ("branch", &encode_path(git_ref.name())),
+                ("sha", git_ref.name()),
+                ("path", &encode_path(path)),
+            ],
+        )
+    }
+}
+
+impl Provider for Templated {
+    fn name(&self) -> &'static str {
+        self.fallback.name()
+    }
+
+    fn repo_url(&self, repo_url: &str) -> String {
+        match &self.templates.repo {
+            Some(template) => self.fill(template, repo_url, &[]),
+            None => self.fallback.repo_url(repo_url),
+        }
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        match &self.templates.commit {
+            Some(template) => self.fill(template, repo_url, &[("sha", commit_sha)]),
+            None => self.fallback.commit_url(repo_url, commit_sha),
+        }
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        match &self.templates.file {
+            Some(template) => self.fill_file(template, repo_url, git_ref, path),
+            None => self.fallback.blob_url(repo_url, git_ref, path),
+        }
+    }
+
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        self.fallback.line_anchor(line_number, end_line_number)
+    }
+
+    /// A `line` template has no column to fill in.
+    fn line_column_anchor(&self, line_number: &str, column: &str) -> Option<String> {
+        match &self.templates.line {
+            Some(_) => None,
+            None => self.fallback.line_column_anchor(line_number, column),
+        }
+    }
+
+    fn line_number_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        line_number: &str,
+        end_line_number: Option<&str>,
+    ) -> String {
+        match &self.templates.line {
+            Some(template) => self.fill(
+                &self.fill_file(template, repo_url, git_ref, path),
+                repo_url,
+                &[
+                    ("line", line_number),
+                    ("end_line", end_line_number.unwrap_or(line_number)),
+                ],
+            ),
+            None => format!(
+                "{}{}",
+                self.blob_url(repo_url, git_ref, path),
+                self.line_anchor(line_number, end_line_number)
+            ),
+        }
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        match &self.templates.tree {
+            Some(template) => self.fill_file(template, repo_url, git_ref, path),
+            None => self.fallback.tree_url(repo_url, git_ref, path),
+        }
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        self.fallback.raw_url(repo_url, git_ref, path)
+    }
+
+    fn change_url(&self, repo_url: &str, change_id: &str) -> AnyhowResult<String> {
+        self.fallback.change_url(repo_url, change_id)
+    }
+
+    fn edit_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        line_number: Option<&str>,
+    ) -> AnyhowResult<String> {
+        self.fallback.edit_url(repo_url, git_ref, path, line_number)
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        self.fallback.pr_url(repo_url, pr_number)
+    }
+
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.fallback.issues_url(repo_url)
+    }
+
+    fn issue_url(&self, repo_url: &str, issue_number: &str) -> AnyhowResult<String> {
+        self.fallback.issue_url(repo_url, issue_number)
+    }
+
+    fn new_issue_url(
+        &self,
+        repo_url: &str,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> AnyhowResult<String> {
+        self.fallback.new_issue_url(repo_url, title, body)
+    }
+
+    fn ci_url(&self, repo_url: &str, branch: &str, commit_sha: &str) -> AnyhowResult<String> {
+        self.fallback.ci_url(repo_url, branch, commit_sha)
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        self.fallback.blame_url(repo_url, git_ref, path)
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        self.fallback.history_url(repo_url, git_ref, path)
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        self.fallback.compare_url(repo_url, base, head)
+    }
+
+    fn code_search_url(&self, repo_url: &str, search: &CodeSearch) -> AnyhowResult<String> {
+        self.fallback.code_search_url(repo_url, search)
+    }
+
+    fn branch_prs_url(&self, repo_url: &str, branch: &str) -> AnyhowResult<String> {
+        self.fallback.branch_prs_url(repo_url, branch)
+    }
+
+    fn commit_prs_url(&self, repo_url: &str, commit_sha: &str) -> AnyhowResult<String> {
+        self.fallback.commit_prs_url(repo_url, commit_sha)
+    }
+
+    fn new_pr_url(&self, repo_url: &str, new_pr: &NewPullRequest) -> AnyhowResult<String> {
+        self.fallback.new_pr_url(repo_url, new_pr)
+    }
+
+    fn releases_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.fallback.releases_url(repo_url)
+    }
+
+    fn user_url(&self, repo_url: &str, user: &str) -> AnyhowResult<String> {
+        self.fallback.user_url(repo_url, user)
+    }
+
+    fn org_url(&self, repo_url: &str, org: &str) -> AnyhowResult<String> {
+        self.fallback.org_url(repo_url, org)
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.fallback.tags_url(repo_url)
+    }
+
+    fn archive_url(
+        &self,
+        repo_url: &str,
+        git_ref: &str,
+        format: ArchiveFormat,
+    ) -> AnyhowResult<String> {
+        self.fallback.archive_url(repo_url, git_ref, format)
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        self.fallback.tag_url(repo_url, tag)
+    }
+
+    fn release_url(&self, repo_url: &str, tag: Option<&str>) -> AnyhowResult<String> {
+        self.fallback.release_url(repo_url, tag)
+    }
+
+    fn wiki_url(&self, repo_url: &str, page: Option<&str>) -> AnyhowResult<String> {
+        self.fallback.wiki_url(repo_url, page)
+    }
+
+    fn project_page_url(&self, repo_url: &str, page: ProjectPage) -> AnyhowResult<String> {
+        self.fallback.project_page_url(repo_url, page)
+    }
+
+    fn settings_url(
+        &self,
+        repo_url: &str,
+        section: Option<SettingsSection>,
+    ) -> AnyhowResult<String> {
+        self.fallback.settings_url(repo_url, section)
+    }
+}
+
+/// Code links on a Sourcegraph instance, which names repositories by their
+/// host and path, e.g. `github.com/o/r`. Everything that isn't code, like
+/// pull requests and issues, stays on the provider the host would get.
+pub struct Sourcegraph {
+    instance: String,
+    fallback: Box<dyn Provider>,
+}
+
+impl Sourcegraph {
+    pub fn new(instance: &str, fallback: Box<dyn Provider>) -> Sourcegraph {
+        Sourcegraph {
+            instance: instance.trim_end_matches('/').to_string(),
+            fallback,
+        }
+    }
+
+    fn at_ref(&self, repo_url: &str, git_ref: &GitRef) -> String {
+        format!(
+            "{}@{}",
+            self.repo_url(repo_url),
+            encode_path(git_ref.name())
+        )
+    }
+}
+
+impl Provider for Sourcegraph {
+    fn name(&self) -> &'static str {
+        "sourcegraph"
+    }
+
+    fn repo_url(&self, repo_url: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            self.instance,
+            get_host(repo_url),
+            split_repo_url(repo_url).1
+        )
+    }
+
+    fn commit_url(&self, repo_url: &str, commit_sha: &str) -> String {
+        format!("{}/-/commit/{}", self.repo_url(repo_url), commit_sha)
+    }
+
+    fn blob_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        format!(
+            "{}/-/blob/{}",
+            self.at_ref(repo_url, git_ref),
+            encode_path(path)
+        )
+    }
+
+    fn line_anchor(&self, line_number: &str, end_line_number: Option<&str>) -> String {
+        match end_line_number {
+            Some(end) => format!("?L{}-{}", line_number, end),
+            None => format!("?L{}", line_number),
+        }
+    }
+
+    fn line_column_anchor(&self, line_number: &str, column: &str) -> Option<String> {
+        Some(format!("?L{}:{}", line_number, column))
+    }
+
+    fn tree_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> String {
+        match path {
+            "" => self.at_ref(repo_url, git_ref),
+            _ => format!(
+                "{}/-/tree/{}",
+                self.at_ref(repo_url, git_ref),
+                encode_path(path)
+            ),
+        }
+    }
+
+    fn pr_url(&self, repo_url: &str, pr_number: &str) -> AnyhowResult<String> {
+        self.fallback.pr_url(repo_url, pr_number)
+    }
+
+    fn raw_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        self.fallback.raw_url(repo_url, git_ref, path)
+    }
+
+    fn change_url(&self, repo_url: &str, change_id: &str) -> AnyhowResult<String> {
+        self.fallback.change_url(repo_url, change_id)
+    }
+
+    fn edit_url(
+        &self,
+        repo_url: &str,
+        git_ref: &GitRef,
+        path: &str,
+        line_number: Option<&str>,
+    ) -> AnyhowResult<String> {
+        self.fallback.edit_url(repo_url, git_ref, path, line_number)
+    }
+
+    fn branch_prs_url(&self, repo_url: &str, branch: &str) -> AnyhowResult<String> {
+        self.fallback.branch_prs_url(repo_url, branch)
+    }
+
+    fn commit_prs_url(&self, repo_url: &str, commit_sha: &str) -> AnyhowResult<String> {
+        self.fallback.commit_prs_url(repo_url, commit_sha)
+    }
+
+    fn issues_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.fallback.issues_url(repo_url)
+    }
+
+    fn issue_url(&self, repo_url: &str, issue_number: &str) -> AnyhowResult<String> {
+        self.fallback.issue_url(repo_url, issue_number)
+    }
+
+    fn new_issue_url(
+        &self,
+        repo_url: &str,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> AnyhowResult<String> {
+        self.fallback.new_issue_url(repo_url, title, body)
+    }
+
+    fn ci_url(&self, repo_url: &str, branch: &str, commit_sha: &str) -> AnyhowResult<String> {
+        self.fallback.ci_url(repo_url, branch, commit_sha)
+    }
+
+    fn blame_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/blame/{}",
+            self.at_ref(repo_url, git_ref),
+            encode_path(path)
+        ))
+    }
+
+    fn history_url(&self, repo_url: &str, git_ref: &GitRef, path: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/commits/{}",
+            self.at_ref(repo_url, git_ref),
+            encode_path(path)
+        ))
+    }
+
+    fn compare_url(&self, repo_url: &str, base: &str, head: &str) -> AnyhowResult<String> {
+        Ok(format!(
+            "{}/-/compare/{}...{}",
+            self.repo_url(repo_url),
+            encode_path(base),
+            encode_path(head)
+        ))
+    }
+
+    fn code_search_url(&self, repo_url: &str, search: &CodeSearch) -> AnyhowResult<String> {
+        let mut repo = format!(
+            "repo:^{}/{}$",
+            regex::escape(get_host(repo_url)),
+            regex::escape(split_repo_url(repo_url).1)
+        );
+        if let Some(branch) = search.branch {
+            repo = format!("{}@{}", repo, branch);
+        }
+        let query = match search.path {
+            Some(path) => format!("{} file:^{} {}", repo, regex::escape(path), search.pattern),
+            None => format!("{} {}", repo, search.pattern),
+        };
+        Ok(format!(
+            "{}/search{}",
+            self.instance,
+            build_query_string(&[("q", &query), ("patternType", "literal")])
+        ))
+    }
+
+    fn new_pr_url(&self, repo_url: &str, new_pr: &NewPullRequest) -> AnyhowResult<String> {
+        self.fallback.new_pr_url(repo_url, new_pr)
+    }
+
+    fn releases_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.fallback.releases_url(repo_url)
+    }
+
+    fn user_url(&self, repo_url: &str, user: &str) -> AnyhowResult<String> {
+        self.fallback.user_url(repo_url, user)
+    }
+
+    fn org_url(&self, repo_url: &str, org: &str) -> AnyhowResult<String> {
+        self.fallback.org_url(repo_url, org)
+    }
+
+    fn tags_url(&self, repo_url: &str) -> AnyhowResult<String> {
+        self.fallback.tags_url(repo_url)
+    }
+
+    fn archive_url(
+        &self,
+        repo_url: &str,
+        git_ref: &str,
+        format: ArchiveFormat,
+    ) -> AnyhowResult<String> {
+        self.fallback.archive_url(repo_url, git_ref, format)
+    }
+
+    fn tag_url(&self, repo_url: &str, tag: &str) -> AnyhowResult<String> {
+        self.fallback.tag_url(repo_url, tag)
+    }
+
+    fn release_url(&self, repo_url: &str, tag: Option<&str>) -> AnyhowResult<String> {
+        self.fallback.release_url(repo_url, tag)
+    }
+
+    fn wiki_url(&self, repo_url: &str, page: Option<&str>) -> AnyhowResult<String> {
+        self.fallback.wiki_url(repo_url, page)
+    }
+
+    fn project_page_url(&self, repo_url: &str, page: ProjectPage) -> AnyhowResult<String> {
+        self.fallback.project_page_url(repo_url, page)
+    }
+
+    fn settings_url(
+        &self,
+        repo_url: &str,
+        section: Option<SettingsSection>,
+    ) -> AnyhowResult<String> {
+        self.fallback.settings_url(repo_url, section)
+    }
+}
+
+/// Looks a provider up by the name it reports, for configs that override
+/// the detection.
+pub fn provider_by_name(name: &str) -> AnyhowResult<Box<dyn Provider>> {
+    let provider: Box<dyn Provider> = match name {
+        "github" => Box::new(GitHub),
+        "gitlab" => Box::new(GitLab),
+        "bitbucket" => Box::new(Bitbucket),
+        "bitbucket-server" => Box::new(BitbucketServer),
+        // Forgejo, which Codeberg runs, is a fork of Gitea with the same URLs.
+        "gitea" | "forgejo" => Box::new(Gitea),
+        "sourcehut" => Box::new(SourceHut),
+        "azure" => Box::new(AzureDevOps),
+        "gerrit" => Box::new(Gerrit),
+        "cgit" => Box::new(Cgit),
+        "gitweb" => Box::new(Gitweb),
+        "codecommit" => Box::new(CodeCommit),
+        _ => return Err(anyhow!("Unknown provider '{}'", name)),
+    };
+    Ok(provider)
+}
+
+/// Like `provider_for_url`, but applies the provider and URL templates of
+/// the repository's config and then the templates configured for its host,
+/// which make way for the host's plugin.
+pub fn provider_for_config(
+    repo_url: &str,
+    config: &Config,
+    repo_config: &RepoConfig,
+) -> AnyhowResult<Box<dyn Provider>> {
+    let fallback = match repo_config
+        .provider
+        .as_deref()
+        .or_else(|| config.provider_for_host(get_host(repo_url)))
+    {
+        Some(name) => provider_by_name(name)?,
+        None => provider_for_url(repo_url),
+    };
+    let templates = match (
+        &repo_config.templates,
+        config.templates_for_host(get_host(repo_url)),
+    ) {
+        (Some(repo_templates), Some(host_templates)) => repo_templates.or(host_templates),
+        (Some(templates), None) | (None, Some(templates)) => templates.clone(),
+        (None, None) => return Ok(with_plugin(fallback, repo_url, config)),
+    };
+    let templated = Box::new(Templated {
+        templates,
+        fallback,
+    });
+    Ok(with_plugin(templated, repo_url, config))
+}
+
+fn with_plugin(provider: Box<dyn Provider>, repo_url: &str, config: &Config) -> Box<dyn Provider> {
+    match config.plugin_for_host(get_host(repo_url)) {
+        Some(name) => Box::new(Plugin::new(name, provider)),
+        None => provider,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_detection() {
+        assert_eq!(
+            provider_for_url("https://github.com/oren0e/gitopen").name(),
+            "github"
+        );
+        assert_eq!(
+            provider_for_url("https://gitlab.com/group/project").name(),
+            "gitlab"
+        );
+        assert_eq!(
+            provider_for_url("https://bitbucket.org/team/repo").name(),
+            "bitbucket"
+        );
+        assert_eq!(
+            provider_for_url("https://codeberg.org/user/repo").name(),
+            "gitea"
+        );
+        assert_eq!(
+            provider_for_url("https://forgejo.corp.com/user/repo").name(),
+            "gitea"
+        );
+        assert_eq!(
+            provider_for_url("https://git.sr.ht/~user/repo").name(),
+            "sourcehut"
+        );
+        assert_eq!(
+            provider_for_url("https://dev.azure.com/org/project/_git/repo").name(),
+            "azure"
+        );
+        assert_eq!(
+            provider_for_url("https://android.googlesource.com/platform/build").name(),
+            "gerrit"
+        );
+        assert_eq!(
+            provider_for_url("https://git-codecommit.eu-west-1.amazonaws.com/v1/repos/r").name(),
+            "codecommit"
+        );
+        assert_eq!(
+            provider_for_url("https://git.foo.com/project/repo").name(),
+            "github"
+        );
+        assert!(detect_provider("https://git.foo.com/project/repo").is_none());
+        assert!(detect_provider("https://github.corp.com/o/r").is_some());
+    }
+
+    #[test]
+    fn test_gerrit_urls() {
+        let repo_url = "https://gerrit.corp.com/a/tools/build";
+        assert_eq!(
+            Gerrit.repo_url(repo_url),
+            "https://gerrit.corp.com/plugins/gitiles/tools/build"
+        );
+        assert_eq!(
+            Gerrit.line_number_url(repo_url, &GitRef::Branch("release/1.x"), "src/a.c", "3", Some("5")),
+            "https://gerrit.corp.com/plugins/gitiles/tools/build/+/refs/heads/release/1.x/src/a.c#3"
+        );
+        assert_eq!(
+            Gerrit.pr_url(repo_url, "1234").unwrap(),
+            "https://gerrit.corp.com/c/tools/build/+/1234"
+        );
+        let change_id = "I8473b95934b5732ac55d26311a706c9c2bde9940";
+        assert_eq!(
+            Gerrit.change_url(repo_url, change_id).unwrap(),
+            format!("https://gerrit.corp.com/q/{}", change_id)
+        );
+        let repo_url = "https://android.googlesource.com/platform/build";
+        assert_eq!(
+            Gerrit.commit_url(repo_url, "abc123"),
+            "https://android.googlesource.com/platform/build/+/abc123"
+        );
+        assert_eq!(
+            Gerrit.change_url(repo_url, change_id).unwrap(),
+            format!("https://android-review.googlesource.com/q/{}", change_id)
+        );
+        assert!(GitHub
+            .change_url("https://github.com/o/r", change_id)
+            .is_err());
+    }
+
+    #[test]
+    fn test_line_number_urls() {
+        assert_eq!(
+            GitHub.line_number_url(
+                "https://github.com/o/r",
+                &GitRef::Branch("main"),
+                "src/main.rs",
+                "10",
+                None
+            ),
+            "https://github.com/o/r/blob/main/src/main.rs#L10"
+        );
+        assert_eq!(
+            GitLab.line_number_url(
+                "https://gitlab.com/g/p",
+                &GitRef::Branch("main"),
+                "src/main.rs",
+                "10",
+                None
+            ),
+            "https://gitlab.com/g/p/-/blob/main/src/main.rs#L10"
+        );
+        assert_eq!(
+            Bitbucket.line_number_url(
+                "https://bitbucket.org/t/r",
+                &GitRef::Branch("main"),
+                "src/main.rs",
+                "10",
+                None
+            ),
+            "https://bitbucket.org/t/r/src/main/src/main.rs#lines-10"
+        );
+        assert_eq!(
+            Gitea.line_number_url(
+                "https://codeberg.org/u/r",
+                &GitRef::Branch("main"),
+                "src/main.rs",
+                "10",
+                None
+            ),
+            "https://codeberg.org/u/r/src/branch/main/src/main.rs#L10"
+        );
+        assert_eq!(
+            SourceHut.line_number_url(
+                "https://git.sr.ht/~u/r",
+                &GitRef::Branch("main"),
+                "src/main.rs",
+                "10",
+                None
+            ),
+            "https://git.sr.ht/~u/r/tree/main/item/src/main.rs#L10"
+        );
+    }
+
+    #[test]
+    fn test_bitbucket_urls() {
+        let cloud = provider_for_url("https://bitbucket.org/team/repo.git");
+        assert_eq!(
+            cloud.commit_url("https://bitbucket.org/team/repo.git", "998a1b3"),
+            "https://bitbucket.org/team/repo/commits/998a1b3"
+        );
+
+        let server_url = "https://bitbucket.company.com/scm/proj/repo.git";
+        let server = provider_for_url(server_url);
+        assert_eq!(server.name(), "bitbucket-server");
+        assert_eq!(
+            server.commit_url(server_url, "998a1b3"),
+            "https://bitbucket.company.com/projects/PROJ/repos/repo/commits/998a1b3"
+        );
+        assert_eq!(
+            server.line_number_url(server_url, &GitRef::Branch("main"), "src/main.rs", "10", None),
+            "https://bitbucket.company.com/projects/PROJ/repos/repo/browse/src/main.rs?at=refs/heads/main#10"
+        );
+    }
+
+    #[test]
+    fn test_azure_devops_urls() {
+        let repo_url = "https://dev.azure.com/org/project/_git/repo";
+        assert_eq!(
+            AzureDevOps.commit_url(repo_url, "998a1b3"),
+            "https://dev.azure.com/org/project/_git/repo/commit/998a1b3"
+        );
+        assert_eq!(
+            AzureDevOps.line_number_url(
+                repo_url,
+                &GitRef::Branch("main"),
+                "src/main.rs",
+                "10",
+                None
+            ),
+            "https://dev.azure.com/org/project/_git/repo?path=/src/main.rs&version=GBmain\
+             &line=10&lineEnd=10&lineStartColumn=1&lineEndColumn=1&lineStyle=plain&_a=contents"
+        );
+    }
+
+    #[test]
+    fn test_line_range_anchors() {
+        let branch = GitRef::Branch("main");
+        assert_eq!(
+            GitHub.line_number_url("https://github.com/o/r", &branch, "a.rs", "10", Some("25")),
+            "https://github.com/o/r/blob/main/a.rs#L10-L25"
+        );
+        assert_eq!(
+            GitLab.line_number_url("https://gitlab.com/g/p", &branch, "a.rs", "10", Some("25")),
+            "https://gitlab.com/g/p/-/blob/main/a.rs#L10-25"
+        );
+        assert_eq!(
+            Bitbucket.line_number_url(
+                "https://bitbucket.org/t/r",
+                &branch,
+                "a.rs",
+                "10",
+                Some("25")
+            ),
+            "https://bitbucket.org/t/r/src/main/a.rs#lines-10:25"
+        );
+        assert_eq!(
+            AzureDevOps.line_anchor("10", Some("25")),
+            "&line=10&lineEnd=25&lineStartColumn=1&lineEndColumn=1&lineStyle=plain&_a=contents"
+        );
+    }
+
+    #[test]
+    fn test_commit_refs() {
+        let sha = GitRef::Commit("998a1b3");
+        assert_eq!(
+            GitHub.line_number_url("https://github.com/o/r", &sha, "src/main.rs", "10", None),
+            "https://github.com/o/r/blob/998a1b3/src/main.rs#L10"
+        );
+        assert_eq!(
+            Gitea.line_number_url("https://codeberg.org/u/r", &sha, "src/main.rs", "10", None),
+            "https://codeberg.org/u/r/src/commit/998a1b3/src/main.rs#L10"
+        );
+        assert_eq!(
+            BitbucketServer.tree_url("https://bitbucket.company.com/scm/proj/repo", &sha, "src"),
+            "https://bitbucket.company.com/projects/PROJ/repos/repo/browse/src?at=998a1b3"
+        );
+        assert_eq!(
+            AzureDevOps.tree_url("https://dev.azure.com/org/project/_git/repo", &sha, "src"),
+            "https://dev.azure.com/org/project/_git/repo?path=/src&version=GC998a1b3"
+        );
+    }
+
+    #[test]
+    fn test_issue_urls() {
+        assert_eq!(
+            GitHub.issue_url("https://github.com/o/r", "42").unwrap(),
+            "https://github.com/o/r/issues/42"
+        );
+        assert_eq!(
+            GitLab.issues_url("https://gitlab.com/g/p").unwrap(),
+            "https://gitlab.com/g/p/-/issues"
+        );
+        assert_eq!(
+            AzureDevOps
+                .issue_url("https://dev.azure.com/org/project/_git/repo", "42")
+                .unwrap(),
+            "https://dev.azure.com/org/project/_workitems/edit/42"
+        );
+        assert!(BitbucketServer
+            .issues_url("https://bitbucket.company.com/scm/proj/repo")
+            .is_err());
+    }
+
+    #[test]
+    fn test_new_issue_urls() {
+        assert_eq!(
+            GitHub
+                .new_issue_url(
+                    "https://github.com/o/r",
+                    Some("It broke"),
+                    Some("Steps: 1 & 2")
+                )
+                .unwrap(),
+            "https://github.com/o/r/issues/new?title=It%20broke&body=Steps%3A%201%20%26%202"
+        );
+        assert_eq!(
+            GitLab
+                .new_issue_url("https://gitlab.com/g/p", Some("It broke"), None)
+                .unwrap(),
+            "https://gitlab.com/g/p/-/issues/new?issue[title]=It%20broke"
+        );
+        assert_eq!(
+            Gitea
+                .new_issue_url("https://codeberg.org/u/r", None, None)
+                .unwrap(),
+            "https://codeberg.org/u/r/issues/new"
+        );
+    }
+
+    #[test]
+    fn test_ci_urls() {
+        assert_eq!(
+            GitHub
+                .ci_url("https://github.com/o/r", "main", "998a1b3")
+                .unwrap(),
+            "https://github.com/o/r/commit/998a1b3/checks"
+        );
+        assert_eq!(
+            GitLab
+                .ci_url("https://gitlab.com/g/p", "feat/ci", "998a1b3")
+                .unwrap(),
+            "https://gitlab.com/g/p/-/pipelines?ref=feat%2Fci"
+        );
+        assert!(SourceHut
+            .ci_url("https://git.sr.ht/~u/r", "main", "998a1b3")
+            .is_err());
+    }
+
+    #[test]
+    fn test_release_and_tag_urls() {
+        assert_eq!(
+            GitHub.tag_url("https://github.com/o/r", "v1.2.3").unwrap(),
+            "https://github.com/o/r/releases/tag/v1.2.3"
+        );
+        assert_eq!(
+            GitLab.tag_url("https://gitlab.com/g/p", "v1.2.3").unwrap(),
+            "https://gitlab.com/g/p/-/tags/v1.2.3"
+        );
+        assert_eq!(
+            GitLab.releases_url("https://gitlab.com/g/p").unwrap(),
+            "https://gitlab.com/g/p/-/releases"
+        );
+        assert_eq!(
+            SourceHut.releases_url("https://git.sr.ht/~u/r").unwrap(),
+            "https://git.sr.ht/~u/r/refs"
+        );
+        assert!(AzureDevOps
+            .releases_url("https://dev.azure.com/org/project/_git/repo")
+            .is_err());
+    }
+
+    #[test]
+    fn test_wiki_urls() {
+        assert_eq!(
+            GitHub.wiki_url("https://github.com/o/r", None).unwrap(),
+            "https://github.com/o/r/wiki"
+        );
+        assert_eq!(
+            GitHub
+                .wiki_url("https://github.com/o/r", Some("Getting started"))
+                .unwrap(),
+            "https://github.com/o/r/wiki/Getting-started"
+        );
+        assert_eq!(
+            GitLab.wiki_url("https://gitlab.com/g/p", None).unwrap(),
+            "https://gitlab.com/g/p/-/wikis/home"
+        );
+        assert_eq!(
+            GitLab
+                .wiki_url("https://gitlab.com/g/p", Some("dev/Release process #2"))
+                .unwrap(),
+            "https://gitlab.com/g/p/-/wikis/dev/Release-process-%232"
+        );
+        assert_eq!(
+            Gitea
+                .wiki_url("https://codeberg.org/o/r", Some("FAQ"))
+                .unwrap(),
+            "https://codeberg.org/o/r/wiki/FAQ"
+        );
+        assert!(SourceHut.wiki_url("https://git.sr.ht/~u/r", None).is_err());
+    }
+
+    #[test]
+    fn test_raw_urls() {
+        let main = GitRef::Branch("feat/x");
+        let commit = GitRef::Commit("abc123");
+        assert_eq!(
+            GitHub
+                .raw_url("https://github.com/o/r", &main, "data/a b.json")
+                .unwrap(),
+            "https://raw.githubusercontent.com/o/r/feat/x/data/a%20b.json"
+        );
+        assert_eq!(
+            GitHub
+                .raw_url("https://github.example.com/o/r", &commit, "a.json")
+                .unwrap(),
+            "https://github.example.com/o/r/raw/abc123/a.json"
+        );
+        assert_eq!(
+            GitLab
+                .raw_url("https://gitlab.com/g/p", &main, "a.json")
+                .unwrap(),
+            "https://gitlab.com/g/p/-/raw/feat/x/a.json"
+        );
+        assert_eq!(
+            Gitea
+                .raw_url("https://codeberg.org/o/r", &commit, "a.json")
+                .unwrap(),
+            "https://codeberg.org/o/r/raw/commit/abc123/a.json"
+        );
+        assert_eq!(
+            Bitbucket
+                .raw_url("https://bitbucket.org/o/r", &main, "a.json")
+                .unwrap(),
+            "https://bitbucket.org/o/r/raw/feat%2Fx/a.json"
+        );
+        assert_eq!(
+            SourceHut
+                .raw_url("https://git.sr.ht/~u/r", &main, "a.json")
+                .unwrap(),
+            "https://git.sr.ht/~u/r/blob/feat/x/a.json"
+        );
+        assert!(AzureDevOps
+            .raw_url("https://dev.azure.com/org/project/_git/repo", &main, "a")
+            .is_err());
+    }
+
+    #[test]
+    fn test_sourcegraph_urls() {
+        let sourcegraph = Sourcegraph::new("https://sourcegraph.corp.com/", Box::new(GitHub));
+        let repo_url = "https://github.com/o/r";
+        assert_eq!(
+            sourcegraph.repo_url(repo_url),
+            "https://sourcegraph.corp.com/github.com/o/r"
+        );
+        assert_eq!(
+            sourcegraph.line_number_url(
+                repo_url,
+                &GitRef::Commit("abc123"),
+                "src/a b.rs",
+                "7",
+                None
+            ),
+            "https://sourcegraph.corp.com/github.com/o/r@abc123/-/blob/src/a%20b.rs?L7"
+        );
+        assert_eq!(
+            sourcegraph.tree_url(repo_url, &GitRef::Branch("feat/x"), ""),
+            "https://sourcegraph.corp.com/github.com/o/r@feat/x"
+        );
+        assert_eq!(
+            sourcegraph
+                .blame_url(repo_url, &GitRef::Branch("main"), "src/a.rs")
+                .unwrap(),
+            "https://sourcegraph.corp.com/github.com/o/r@main/-/blame/src/a.rs"
+        );
+        assert_eq!(
+            sourcegraph
+                .history_url(repo_url, &GitRef::Branch("main"), "src")
+                .unwrap(),
+            "https://sourcegraph.corp.com/github.com/o/r@main/-/commits/src"
+        );
+        assert_eq!(sourcegraph.name(), "sourcegraph");
+        assert_eq!(
+            sourcegraph.pr_url(repo_url, "3").unwrap(),
+            "https://github.com/o/r/pull/3"
+        );
+    }
+
+    #[test]
+    fn test_edit_urls() {
+        let main = GitRef::Branch("main");
+        assert_eq!(
+            GitHub
+                .edit_url("https://github.com/o/r", &main, "src/a.rs", Some("3"))
+                .unwrap(),
+            "https://github.dev/o/r/blob/main/src/a.rs#L3"
+        );
+        assert_eq!(
+            GitHub
+                .edit_url("https://github.example.com/o/r", &main, "src/a.rs", None)
+                .unwrap(),
+            "https://github.example.com/o/r/edit/main/src/a.rs"
+        );
+        assert_eq!(
+            GitLab
+                .edit_url(
+                    "https://gitlab.com/group/sub/p",
+                    &main,
+                    "src/a.rs",
+                    Some("3")
+                )
+                .unwrap(),
+            "https://gitlab.com/-/ide/project/group/sub/p/edit/main/-/src/a.rs"
+        );
+        assert_eq!(
+            Gitea
+                .edit_url("https://codeberg.org/o/r", &main, "src/a.rs", None)
+                .unwrap(),
+            "https://codeberg.org/o/r/_edit/main/src/a.rs"
+        );
+        assert!(Gitea
+            .edit_url(
+                "https://codeberg.org/o/r",
+                &GitRef::Commit("abc"),
+                "a",
+                None
+            )
+            .is_err());
+        assert!(SourceHut
+            .edit_url("https://git.sr.ht/~u/r", &main, "a", None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_settings_urls() {
+        assert_eq!(
+            GitHub.settings_url("https://github.com/o/r", None).unwrap(),
+            "https://github.com/o/r/settings"
+        );
+        assert_eq!(
+            GitHub
+                .settings_url("https://github.com/o/r", Some(SettingsSection::Secrets))
+                .unwrap(),
+            "https://github.com/o/r/settings/secrets/actions"
+        );
+        assert_eq!(
+            GitLab
+                .settings_url("https://gitlab.com/g/p", Some(SettingsSection::Branches))
+                .unwrap(),
+            "https://gitlab.com/g/p/-/settings/repository#js-protected-branches-settings"
+        );
+        assert_eq!(
+            Bitbucket
+                .settings_url("https://bitbucket.org/o/r", Some(SettingsSection::Webhooks))
+                .unwrap(),
+            "https://bitbucket.org/o/r/admin/webhooks"
+        );
+        assert_eq!(
+            Gitea
+                .settings_url(
+                    "https://codeberg.org/o/r",
+                    Some(SettingsSection::Collaborators)
+                )
+                .unwrap(),
+            "https://codeberg.org/o/r/settings/collaboration"
+        );
+        assert!(SourceHut
+            .settings_url("https://git.sr.ht/~u/r", None)
+            .is_err());
+        assert_eq!(
+            SettingsSection::from_name("deploy-keys").unwrap(),
+            SettingsSection::DeployKeys
+        );
+        assert!(SettingsSection::from_name("keys").is_err());
+    }
+
+    #[test]
+    fn test_project_page_urls() {
+        let github_urls: Vec<String> = ProjectPage::ALL
+            .iter()
+            .map(|page| {
+                GitHub
+                    .project_page_url("https://github.com/o/r", *page)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            github_urls,
+            [
+                "https://github.com/o/r/discussions",
+                "https://github.com/o/r/projects",
+                "https://github.com/o/r/security",
+                "https://github.com/o/r/pulse",
+                "https://github.com/o/r/graphs/contributors",
+                "https://github.com/o/r/network",
+            ]
+        );
+        assert_eq!(
+            GitLab
+                .project_page_url("https://gitlab.com/g/p", ProjectPage::Graph)
+                .unwrap(),
+            "https://gitlab.com/g/p/-/network/HEAD"
+        );
+        assert_eq!(
+            GitLab
+                .project_page_url("https://gitlab.com/g/p", ProjectPage::Security)
+                .unwrap(),
+            "https://gitlab.com/g/p/-/security/dashboard"
+        );
+        assert_eq!(
+            Gitea
+                .project_page_url("https://codeberg.org/o/r", ProjectPage::Insights)
+                .unwrap(),
+            "https://codeberg.org/o/r/activity"
+        );
+        assert_eq!(
+            AzureDevOps
+                .project_page_url(
+                    "https://dev.azure.com/org/project/_git/repo",
+                    ProjectPage::Projects
+                )
+                .unwrap(),
+            "https://dev.azure.com/org/project/_boards"
+        );
+        assert_eq!(
+            GitLab
+                .project_page_url("https://gitlab.com/g/p", ProjectPage::Discussions)
+                .unwrap_err()
+                .to_string(),
+            "gitlab does not support discussions"
+        );
+        assert!(Bitbucket
+            .project_page_url("https://bitbucket.org/o/r", ProjectPage::Projects)
+            .is_err());
+    }
+
+    #[test]
+    fn test_blame_urls() {
+        let branch = GitRef::Branch("main");
+        assert_eq!(
+            GitHub
+                .blame_url("https://github.com/o/r", &branch, "src/foo.rs")
+                .unwrap(),
+            "https://github.com/o/r/blame/main/src/foo.rs"
+        );
+        assert_eq!(
+            GitLab
+                .blame_url("https://gitlab.com/g/p", &branch, "src/foo.rs")
+                .unwrap(),
+            "https://gitlab.com/g/p/-/blame/main/src/foo.rs"
+        );
+        assert_eq!(
+            Gitea
+                .blame_url("https://codeberg.org/u/r", &branch, "src/foo.rs")
+                .unwrap(),
+            "https://codeberg.org/u/r/blame/branch/main/src/foo.rs"
+        );
+        assert_eq!(
+            Gitea
+                .blame_url(
+                    "https://codeberg.org/u/r",
+                    &GitRef::Branch("fix/50% off#2"),
+                    "src/foo.rs"
+                )
+                .unwrap(),
+            "https://codeberg.org/u/r/blame/branch/fix/50%25%20off%232/src/foo.rs"
+        );
+    }
+
+    #[test]
+    fn test_history_urls() {
+        let branch = GitRef::Branch("main");
+        assert_eq!(
+            GitHub
+                .history_url("https://github.com/o/r", &branch, "src/foo.rs")
+                .unwrap(),
+            "https://github.com/o/r/commits/main/src/foo.rs"
+        );
+        assert_eq!(
+            GitLab
+                .history_url("https://gitlab.com/g/p", &branch, "src/foo.rs")
+                .unwrap(),
+            "https://gitlab.com/g/p/-/commits/main/src/foo.rs"
+        );
+        assert_eq!(
+            AzureDevOps
+                .history_url("https://dev.azure.com/o/p/_git/r", &branch, "src/foo.rs")
+                .unwrap(),
+            "https://dev.azure.com/o/p/_git/r?path=/src/foo.rs&version=GBmain&_a=history"
+        );
+        assert_eq!(
+            Gitea
+                .history_url(
+                    "https://codeberg.org/u/r",
+                    &GitRef::Branch("fix/50% off#2"),
+                    "src/foo.rs"
+                )
+                .unwrap(),
+            "https://codeberg.org/u/r/commits/branch/fix/50%25%20off%232/src/foo.rs"
+        );
+    }
+
+    #[test]
+    fn test_compare_urls() {
+        assert_eq!(
+            GitHub
+                .compare_url("https://github.com/o/r", "main", "feature")
+                .unwrap(),
+            "https://github.com/o/r/compare/main...feature"
+        );
+        assert_eq!(
+            GitLab
+                .compare_url("https://gitlab.com/g/p", "main", "feature")
+                .unwrap(),
+            "https://gitlab.com/g/p/-/compare/main...feature"
+        );
+        assert_eq!(
+            Bitbucket
+                .compare_url("https://bitbucket.org/t/r", "main", "feature")
+                .unwrap(),
+            "https://bitbucket.org/t/r/branches/compare/feature%0Dmain"
+        );
+    }
+
+    #[test]
+    fn test_archive_urls() {
+        assert_eq!(
+            GitLab
+                .archive_url(
+                    "https://gitlab.com/g/project",
+                    "refs/heads/feat/x",
+                    ArchiveFormat::TarGz
+                )
+                .unwrap(),
+            "https://gitlab.com/g/project/-/archive/feat/x/project-feat-x.tar.gz"
+        );
+        assert_eq!(
+            Bitbucket
+                .archive_url(
+                    "https://bitbucket.org/t/r",
+                    "refs/tags/v1",
+                    ArchiveFormat::Zip
+                )
+                .unwrap(),
+            "https://bitbucket.org/t/r/get/v1.zip"
+        );
+        assert_eq!(
+            Gitea
+                .archive_url("https://codeberg.org/o/r", "abc123", ArchiveFormat::Zip)
+                .unwrap(),
+            "https://codeberg.org/o/r/archive/abc123.zip"
+        );
+        assert!(SourceHut
+            .archive_url(
+                "https://git.sr.ht/~sir/r",
+                "refs/heads/main",
+                ArchiveFormat::Zip
+            )
+            .is_err());
+        assert_eq!(
+            ArchiveFormat::from_name("tar.gz").unwrap(),
+            ArchiveFormat::TarGz
+        );
+    }
+
+    #[test]
+    fn test_user_and_org_urls() {
+        assert_eq!(
+            GitHub
+                .user_url("https://github.com/o/r", "octocat")
+                .unwrap(),
+            "https://github.com/octocat"
+        );
+        assert_eq!(
+            GitLab.org_url("https://gitlab.com/g/s/p", "g/s").unwrap(),
+            "https://gitlab.com/g/s"
+        );
+        assert_eq!(
+            Bitbucket.org_url("https://bitbucket.org/t/r", "t").unwrap(),
+            "https://bitbucket.org/t/"
+        );
+        assert!(Bitbucket
+            .user_url("https://bitbucket.org/t/r", "someone")
+            .is_err());
+        assert_eq!(
+            SourceHut
+                .org_url("https://git.sr.ht/~sir/repo", "~sir")
+                .unwrap(),
+            "https://git.sr.ht/~sir"
+        );
+    }
+
+    #[test]
+    fn test_code_search_urls() {
+        let search = CodeSearch {
+            pattern: "fn main",
+            branch: None,
+            path: Some("src"),
+        };
+        assert_eq!(
+            GitHub
+                .code_search_url("https://github.com/o/r", &search)
+                .unwrap(),
+            "https://github.com/search?q=repo%3Ao%2Fr%20path%3Asrc%20fn%20main&type=code"
+        );
+        let on_branch = CodeSearch {
+            branch: Some("dev"),
+            ..search
+        };
+        assert!(GitHub
+            .code_search_url("https://github.com/o/r", &on_branch)
+            .is_err());
+        assert_eq!(
+            GitLab
+                .code_search_url("https://gitlab.com/g/p", &on_branch)
+                .unwrap(),
+            "https://gitlab.com/g/p/-/search?search=fn%20main%20path%3Asrc&scope=blobs&repository_ref=dev"
+        );
+        assert_eq!(
+            Sourcegraph::new("https://sourcegraph.com", Box::new(GitHub))
+                .code_search_url("https://github.com/o/r.s", &on_branch)
+                .unwrap(),
+            "https://sourcegraph.com/search?q=repo%3A%5Egithub%5C.com%2Fo%2Fr%5C.s%24%40dev%20file%3A%5Esrc%20fn%20main&patternType=literal"
+        );
+        assert!(Bitbucket
+            .code_search_url("https://bitbucket.org/t/r", &search)
+            .is_err());
+    }
+
+    #[test]
+    fn test_new_pr_urls() {
+        let new_pr = NewPullRequest {
+            base: "main",
+            head: "feat/x",
+            head_owner: None,
+            title: Some("Add x"),
+            body: Some("Because & why"),
+            draft: false,
+        };
+        assert_eq!(
+            GitHub
+                .new_pr_url("https://github.com/org/repo", &new_pr)
+                .unwrap(),
+            "https://github.com/org/repo/compare/main...feat/x?quick_pull=1&title=Add%20x&body=Because%20%26%20why"
+        );
+        assert_eq!(
+            GitLab
+                .new_pr_url("https://gitlab.com/group/project", &new_pr)
+                .unwrap(),
+            "https://gitlab.com/group/project/-/merge_requests/new?merge_request[source_branch]=feat%2Fx&merge_request[target_branch]=main&merge_request[title]=Add%20x&merge_request[description]=Because%20%26%20why"
+        );
+        assert_eq!(
+            Bitbucket
+                .new_pr_url("https://bitbucket.org/team/repo", &new_pr)
+                .unwrap(),
+            "https://bitbucket.org/team/repo/pull-requests/new?source=feat%2Fx&dest=main"
+        );
+        let from_fork = NewPullRequest {
+            head_owner: Some("me"),
+            title: None,
+            body: None,
+            ..new_pr
+        };
+        assert_eq!(
+            GitHub
+                .new_pr_url("https://github.com/org/repo", &from_fork)
+                .unwrap(),
+            "https://github.com/org/repo/compare/main...me:feat/x?quick_pull=1"
+        );
+        let draft = NewPullRequest {
+            title: None,
+            draft: true,
+            ..new_pr
+        };
+        assert_eq!(
+            GitHub
+                .new_pr_url("https://github.com/org/repo", &draft)
+                .unwrap(),
+            "https://github.com/org/repo/compare/main...feat/x?quick_pull=1&draft=1&body=Because%20%26%20why"
+        );
+        assert_eq!(
+            GitLab
+                .new_pr_url("https://gitlab.com/group/project", &draft)
+                .unwrap(),
+            "https://gitlab.com/group/project/-/merge_requests/new?merge_request[source_branch]=feat%2Fx&merge_request[target_branch]=main&merge_request[title]=Draft%3A%20feat%2Fx&merge_request[description]=Because%20%26%20why"
+        );
+        assert_eq!(
+            Gitea
+                .new_pr_url(
+                    "https://gitea.com/org/repo",
+                    &NewPullRequest {
+                        body: None,
+                        ..draft
+                    }
+                )
+                .unwrap(),
+            "https://gitea.com/org/repo/compare/main...feat/x?expand=1&title=WIP%3A%20feat%2Fx"
+        );
+        let untitled = NewPullRequest {
+            title: None,
+            body: None,
+            ..new_pr
+        };
+        assert_eq!(
+            SourceHut
+                .new_pr_url("https://git.sr.ht/~user/repo", &untitled)
+                .map_err(|err| err.to_string()),
+            SourceHut
+                .compare_url("https://git.sr.ht/~user/repo", "main", "feat/x")
+                .map_err(|err| err.to_string())
+        );
+    }
+
+    #[test]
+    fn test_branch_prs_urls() {
+        assert_eq!(
+            GitHub
+                .branch_prs_url("https://github.com/org/repo", "feat/x")
+                .unwrap(),
+            "https://github.com/org/repo/pulls?q=is%3Apr%20head%3Afeat%2Fx"
+        );
+        assert_eq!(
+            GitLab
+                .branch_prs_url("https://gitlab.com/group/project", "feat/x")
+                .unwrap(),
+            "https://gitlab.com/group/project/-/merge_requests?source_branch=feat%2Fx"
+        );
+        assert!(Gitea
+            .branch_prs_url("https://gitea.com/org/repo", "feat")
+            .is_err());
+        assert_eq!(
+            GitHub
+                .commit_prs_url("https://github.com/org/repo", "998a1b3")
+                .unwrap(),
+            "https://github.com/org/repo/pulls?q=is%3Apr%20998a1b3"
+        );
+    }
+
+    #[test]
+    fn test_git_suffix_stripped_from_https_remote() {
+        assert_eq!(
+            GitHub.commit_url("https://github.com/oren0e/gitopen.git", "998a1b3"),
+            "https://github.com/oren0e/gitopen/commit/998a1b3"
+        );
+    }
+
+    #[test]
+    fn test_sourcehut_tickets() {
+        let repo_url = "https://git.sr.ht/~u/r";
+        assert_eq!(
+            SourceHut.issues_url(repo_url).unwrap(),
+            "https://todo.sr.ht/~u/r"
+        );
+        assert_eq!(
+            SourceHut.issue_url(repo_url, "42").unwrap(),
+            "https://todo.sr.ht/~u/r/42"
+        );
+        assert_eq!(
+            SourceHut.new_issue_url(repo_url, None, None).unwrap(),
+            "https://todo.sr.ht/~u/r"
+        );
+        assert!(SourceHut
+            .new_issue_url(repo_url, Some("Crash"), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sourcehut_has_no_prs() {
+        assert!(SourceHut.pr_url("https://git.sr.ht/~u/r", "1").is_err());
+    }
+
+    #[test]
+    fn test_tricky_names_are_encoded() {
+        let branch = GitRef::Branch("feat/50%#1?");
+        let path = "docs/read me/ünï.md";
+        assert_eq!(
+            GitHub.blob_url("https://github.com/o/r", &branch, path),
+            "https://github.com/o/r/blob/feat/50%25%231%3F/docs/read%20me/%C3%BCn%C3%AF.md"
+        );
+        assert_eq!(
+            GitLab.tree_url("https://gitlab.com/g/p", &branch, "a&b"),
+            "https://gitlab.com/g/p/-/tree/feat/50%25%231%3F/a%26b"
+        );
+        assert_eq!(
+            Bitbucket.blob_url("https://bitbucket.org/t/r", &branch, "a b.rs"),
+            "https://bitbucket.org/t/r/src/feat%2F50%25%231%3F/a%20b.rs"
+        );
+        assert_eq!(
+            BitbucketServer.blob_url("https://git.corp.com/scm/key/repo", &branch, "a b.rs"),
+            "https://git.corp.com/projects/KEY/repos/repo/browse/a%20b.rs?at=refs/heads/feat/50%25%231%3F"
+        );
+        assert_eq!(
+            AzureDevOps.blob_url("https://dev.azure.com/o/p/_git/r", &branch, "a b.rs"),
+            "https://dev.azure.com/o/p/_git/r?path=/a%20b.rs&version=GBfeat%2F50%25%231%3F"
+        );
+        assert_eq!(
+            GitHub
+                .compare_url("https://github.com/o/r", "main", "feat/#1")
+                .unwrap(),
+            "https://github.com/o/r/compare/main...feat/%231"
+        );
+        assert_eq!(
+            GitHub
+                .tag_url("https://github.com/o/r", "v1.0+rc#1")
+                .unwrap(),
+            "https://github.com/o/r/releases/tag/v1.0%2Brc%231"
+        );
+    }
+
+    #[test]
+    fn test_templated_provider() {
+        let config = Config::parse(
+            r#"
+[hosts."git.corp.com"]
+commit = "https://{host}/cgit/{repo}/commit/?id={sha}"
+file = "https://{host}/cgit/{repo}/tree/{path}?h={branch}"
+line = "https://{host}/cgit/{repo}/tree/{path}?h={branch}#n{line}"
+"#,
+        )
+        .unwrap();
+        let repo_url = "https://git.corp.com/team/repo";
+        let provider = provider_for_config(repo_url, &config, &RepoConfig::default()).unwrap();
+        let branch = GitRef::Branch("feat/x");
+        assert_eq!(
+            provider.commit_url(repo_url, "998a1b3"),
+            "https://git.corp.com/cgit/team/repo/commit/?id=998a1b3"
+        );
+        assert_eq!(
+            provider.blob_url(repo_url, &branch, "src/main.rs"),
+            "https://git.corp.com/cgit/team/repo/tree/src/main.rs?h=feat/x"
+        );
+        assert_eq!(
+            provider.line_number_url(repo_url, &branch, "src/main.rs", "10", None),
+            "https://git.corp.com/cgit/team/repo/tree/src/main.rs?h=feat/x#n10"
+        );
+        // Pages without a template keep the detected provider's layout.
+        assert_eq!(
+            provider.tree_url(repo_url, &branch, "src"),
+            "https://git.corp.com/team/repo/tree/feat/x/src"
+        );
+        assert_eq!(
+            provider_for_config("https://github.com/o/r", &config, &RepoConfig::default())
+                .unwrap()
+                .commit_url("https://github.com/o/r", "1"),
+            "https://github.com/o/r/commit/1"
+        );
+    }
+
+    #[test]
+    fn test_codecommit_urls() {
+        let repo_url = "https://git-codecommit.us-east-1.amazonaws.com/v1/repos/my-repo";
+        let console =
+            "https://us-east-1.console.aws.amazon.com/codesuite/codecommit/repositories/my-repo";
+        assert_eq!(
+            CodeCommit.repo_url(repo_url),
+            format!("{}/browse?region=us-east-1", console)
+        );
+        assert_eq!(
+            CodeCommit.line_number_url(
+                repo_url,
+                &GitRef::Branch("feat/x"),
+                "src/a.py",
+                "3",
+                Some("5")
+            ),
+            format!(
+                "{}/browse/refs/heads/feat/x/--/src/a.py?region=us-east-1&lines=3-5",
+                console
+            )
+        );
+        assert_eq!(
+            CodeCommit.line_number_url(repo_url, &GitRef::Commit("abc123"), "a.py", "3", None),
+            format!(
+                "{}/browse/abc123/--/a.py?region=us-east-1&lines=3-3",
+                console
+            )
+        );
+        assert_eq!(
+            CodeCommit.tree_url(repo_url, &GitRef::Branch("main"), ""),
+            format!("{}/browse/refs/heads/main?region=us-east-1", console)
+        );
+        assert_eq!(
+            CodeCommit.commit_url(repo_url, "abc123"),
+            format!("{}/commit/abc123?region=us-east-1", console)
+        );
+        assert_eq!(
+            CodeCommit.pr_url(repo_url, "7").unwrap(),
+            format!("{}/pull-requests/7/details?region=us-east-1", console)
+        );
+    }
+
+    #[test]
+    fn test_cgit_urls() {
+        let repo_url = "https://git.kernel.org/pub/scm/git/git.git";
+        assert_eq!(Cgit.repo_url(repo_url), repo_url);
+        assert_eq!(
+            Cgit.line_number_url(
+                repo_url,
+                &GitRef::Branch("maint"),
+                "src/a b.c",
+                "3",
+                Some("5")
+            ),
+            "https://git.kernel.org/pub/scm/git/git.git/tree/src/a%20b.c?h=maint#n3"
+        );
+        assert_eq!(
+            Cgit.tree_url(repo_url, &GitRef::Commit("abc123"), ""),
+            "https://git.kernel.org/pub/scm/git/git.git/tree/?id=abc123"
+        );
+        assert_eq!(
+            Cgit.commit_url(repo_url, "abc123"),
+            "https://git.kernel.org/pub/scm/git/git.git/commit/?id=abc123"
+        );
+        assert_eq!(
+            Cgit.history_url(repo_url, &GitRef::Branch("master"), "Makefile")
+                .unwrap(),
+            "https://git.kernel.org/pub/scm/git/git.git/log/Makefile?h=master"
+        );
+        assert!(Cgit.pr_url(repo_url, "1").is_err());
+    }
+
+    #[test]
+    fn test_gitweb_urls() {
+        let repo_url = "https://git.corp.com/team/project";
+        assert_eq!(
+            Gitweb.repo_url(repo_url),
+            "https://git.corp.com/?p=team/project.git;a=summary"
+        );
+        assert_eq!(
+            Gitweb.line_number_url(repo_url, &GitRef::Branch("main"), "src/a;b.c", "3", None),
+            "https://git.corp.com/?p=team/project.git;a=blob;f=src/a%3Bb.c;hb=refs/heads/main#l3"
+        );
+        assert_eq!(
+            Gitweb.tree_url(repo_url, &GitRef::Commit("abc123"), ""),
+            "https://git.corp.com/?p=team/project.git;a=tree;hb=abc123"
+        );
+        assert_eq!(
+            Gitweb.commit_url("https://git.corp.com/team/project.git", "abc123"),
+            "https://git.corp.com/?p=team/project.git;a=commit;h=abc123"
+        );
+        assert!(Gitweb.pr_url(repo_url, "1").is_err());
+    }
+
+    #[test]
+    fn test_config_selects_provider_for_host() {
+        let config = Config::parse("[providers]\n\"git.corp.com\" = \"cgit\"\n").unwrap();
+        let repo_url = "https://git.corp.com/team/repo";
+        let provider = provider_for_config(repo_url, &config, &RepoConfig::default()).unwrap();
+        assert_eq!(provider.name(), "cgit");
+        let mut repo_config = RepoConfig::default();
+        repo_config
+            .apply_git_config("gitopen.provider gitweb\n")
+            .unwrap();
+        let provider = provider_for_config(repo_url, &config, &repo_config).unwrap();
+        assert_eq!(provider.name(), "gitweb");
+        let config = Config::parse("[providers]\n\"git.corp.com\" = \"forgejo\"\n").unwrap();
+        let provider = provider_for_config(repo_url, &config, &RepoConfig::default()).unwrap();
+        assert_eq!(
+            provider.line_number_url(repo_url, &GitRef::Branch("main"), "a.rs", "2", None),
+            "https://git.corp.com/team/repo/src/branch/main/a.rs#L2"
+        );
+        assert_eq!(
+            provider.compare_url(repo_url, "main", "feat").unwrap(),
+            "https://git.corp.com/team/repo/compare/main...feat"
+        );
+        let config = Config::parse("[plugins]\n\"git.corp.com\" = \"does-not-exist\"\n").unwrap();
+        let provider = provider_for_config(repo_url, &config, &RepoConfig::default()).unwrap();
+        assert_eq!(
+            provider.commit_url(repo_url, "1"),
+            "https://git.corp.com/team/repo/commit/1"
+        );
+        assert!(provider.pr_url(repo_url, "1").is_err());
+        let config = Config::parse("[providers]\n\"git.corp.com\" = \"fossil\"\n").unwrap();
+        assert!(provider_for_config(repo_url, &config, &RepoConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_repo_config_overrides_provider() {
+        let mut repo_config = RepoConfig::default();
+        repo_config
+            .apply_git_config(
+                "gitopen.provider gitlab\ngitopen.template.repo https://mirror/{repo}\n",
+            )
+            .unwrap();
+        let config = Config::parse(
+            "[hosts.\"git.corp.com\"]\nrepo = \"x\"\ncommit = \"https://{host}/c/{sha}\"\n",
+        )
+        .unwrap();
+        let repo_url = "https://git.corp.com/team/repo";
+        let provider = provider_for_config(repo_url, &config, &repo_config).unwrap();
+        assert_eq!(provider.name(), "gitlab");
+        assert_eq!(provider.repo_url(repo_url), "https://mirror/team/repo");
+        assert_eq!(
+            provider.commit_url(repo_url, "1"),
+            "https://git.corp.com/c/1"
+        );
+        assert_eq!(
+            provider.tree_url(repo_url, &GitRef::Branch("main"), "src"),
+            "https://git.corp.com/team/repo/-/tree/main/src"
+        );
+        assert!(provider_by_name("launchpad").is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn ssh_config_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".ssh").join("config"))
+}
+
+/// Matches an ssh `Host` pattern, where `*` matches any run of characters
+/// and `?` exactly one.
+fn matches_pattern(pattern: &str, host: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let host: Vec<char> = host.chars().collect();
+    let (mut p, mut h) = (0, 0);
+    let mut backtrack = None;
+    while h < host.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == host[h]) {
+            p += 1;
+            h += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, h));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            h = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// A `Host` line applies when any pattern matches and no negated `!pattern` does.
+fn host_line_matches(patterns: &[&str], host: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(negated) if matches_pattern(negated, host) => return false,
+            Some(_) => {}
+            None => matched |= matches_pattern(pattern, host),
+        }
+    }
+    matched
+}
+
+/// Finds the `HostName` that ssh would connect to for `alias`. Like ssh, the
+/// first value found in a matching `Host` block wins. `%h` stands for the alias.
+pub fn resolve_host_alias(config: &str, alias: &str) -> Option<String> {
+    let mut in_matching_block = true;
+    for line in config.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (keyword, value) = match line.find(|c: char| c.is_whitespace() || c == '=') {
+            Some(index) => (
+                &line[..index],
+                line[index..].trim_start_matches(|c: char| c.is_whitespace() || c == '='),
+            ),
+            None => continue,
+        };
+        if keyword.eq_ignore_ascii_case("host") {
+            let patterns: Vec<&str> = value.split_whitespace().collect();
+            in_matching_block = host_line_matches(&patterns, alias);
+        } else if keyword.eq_ignore_ascii_case("match") {
+            // `Match` conditions need ssh itself to evaluate.
+            in_matching_block = false;
+        } else if in_matching_block && keyword.eq_ignore_ascii_case("hostname") {
+            return Some(value.trim_matches('"').replace("%h", alias));
+        }
+    }
+    None
+}
+
+/// The hosts of the public forges, whose web pages are on the same name
+/// whatever ssh connects to for them.
+const FORGE_HOSTS: [&str; 7] = [
+    "github.com",
+    "gitlab.com",
+    "bitbucket.org",
+    "codeberg.org",
+    "gitea.com",
+    "git.sr.ht",
+    "dev.azure.com",
+];
+
+/// Hosts that only serve ssh, like the one GitHub documents for ssh over
+/// port 443. Their web pages are on the host that was aliased to them.
+const SSH_ONLY_HOSTS: [&str; 3] = [
+    "ssh.github.com",
+    "altssh.gitlab.com",
+    "altssh.bitbucket.org",
+];
+
+/// The host the web pages of `alias` are on, when ssh connects to
+/// `hostname` for it. A public forge's own name isn't an alias, and
+/// ssh-only endpoints serve no web pages.
+pub fn web_hostname<'a>(alias: &str, hostname: &'a str) -> Option<&'a str> {
+    let is_one_of =
+        |hosts: &[&str], host: &str| hosts.iter().any(|known| known.eq_ignore_ascii_case(host));
+    if hostname.eq_ignore_ascii_case(alias)
+        || is_one_of(&FORGE_HOSTS, alias)
+        || is_one_of(&SSH_ONLY_HOSTS, hostname)
+    {
+        return None;
+    }
+    Some(hostname)
+}
+
+/// Looks `alias` up in `~/.ssh/config`. Returns `None` when there is no
+/// config or it doesn't rename the host to one with web pages.
+pub fn lookup_hostname(alias: &str) -> Option<String> {
+    let config = fs::read_to_string(ssh_config_path()?).ok()?;
+    let hostname = resolve_host_alias(&config, alias)?;
+    web_hostname(alias, &hostname).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "
+# Work account
+Host github-work
+    HostName github.com
+    IdentityFile ~/.ssh/work
+
+Host gl-*  !gl-private
+  HostName=%h.gitlab.example.com
+
+Host *
+    HostName fallback.example.com
+";
+
+    #[test]
+    fn test_resolve_host_alias() {
+        assert_eq!(
+            resolve_host_alias(CONFIG, "github-work"),
+            Some("github.com".to_string())
+        );
+        assert_eq!(
+            resolve_host_alias(CONFIG, "gl-team"),
+            Some("gl-team.gitlab.example.com".to_string())
+        );
+        assert_eq!(
+            resolve_host_alias(CONFIG, "gl-private"),
+            Some("fallback.example.com".to_string())
+        );
+        assert_eq!(resolve_host_alias("Host foo\n  User git\n", "foo"), None);
+    }
+
+    #[test]
+    fn test_web_hostname() {
+        assert_eq!(
+            web_hostname("github-work", "github.com"),
+            Some("github.com")
+        );
+        assert_eq!(web_hostname("s", "gitlab.com"), Some("gitlab.com"));
+        // GitHub's documented config for ssh over port 443.
+        assert_eq!(web_hostname("github.com", "ssh.github.com"), None);
+        assert_eq!(web_hostname("gitlab", "altssh.gitlab.com"), None);
+        assert_eq!(web_hostname("github.com", "fallback.example.com"), None);
+        assert_eq!(web_hostname("git", "git"), None);
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*", "github.com"));
+        assert!(matches_pattern("git?ub.*", "github.com"));
+        assert!(matches_pattern("*.example.com", "a.b.example.com"));
+        assert!(!matches_pattern("*.example.com", "example.com"));
+        assert!(!matches_pattern("github", "github-work"));
+    }
+}
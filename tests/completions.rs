@@ -0,0 +1,25 @@
+//! Generates the completions of every shell `gitopen completions` offers
+//! from the real command line, where clap panics on arguments it can't
+//! resolve in every subcommand.
+
+use gitopen::completions::SHELLS;
+use std::process::Command;
+
+const GITOPEN: &str = env!("CARGO_BIN_EXE_gitopen");
+
+#[test]
+fn test_completions_of_every_shell() {
+    for shell in SHELLS {
+        let output = Command::new(GITOPEN)
+            .args(["completions", shell])
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{} completions failed: {}",
+            shell,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(String::from_utf8_lossy(&output.stdout).contains("config"));
+    }
+}
@@ -0,0 +1,188 @@
+//! Runs gitopen in linked worktrees made with `git worktree add`, which keep
+//! their own HEAD and point at the shared repository from a `.git` file.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const GITOPEN: &str = env!("CARGO_BIN_EXE_gitopen");
+
+/// A repository with a commit on `main` and a worktree for `feat` next to it,
+/// in a directory of its own that also serves as `HOME`.
+struct Sandbox {
+    root: PathBuf,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Sandbox {
+        let root = env::temp_dir().join(format!("gitopen-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("main").join("src")).unwrap();
+        let sandbox = Sandbox { root };
+        sandbox.git("main", &["init", "--quiet", "--initial-branch=main"]);
+        fs::write(sandbox.root.join("main/src/lib.rs"), "fn main() {}\n").unwrap();
+        sandbox.git("main", &["add", "."]);
+        sandbox.git(
+            "main",
+            &["commit", "--quiet", "--message", "Initial commit"],
+        );
+        sandbox.git(
+            "main",
+            &[
+                "remote",
+                "add",
+                "origin",
+                "git@github.com:oren0e/gitopen.git",
+            ],
+        );
+        sandbox.git(
+            "main",
+            &["worktree", "add", "--quiet", "-b", "feat", "../feat"],
+        );
+        sandbox
+    }
+
+    fn command(&self, program: &str, dir: &str, args: &[&str]) -> String {
+        let output = Command::new(program)
+            .args(args)
+            .current_dir(self.root.join(dir))
+            .env("HOME", &self.root)
+            .env("XDG_CONFIG_HOME", self.root.join(".config"))
+            .env("XDG_CACHE_HOME", self.root.join(".cache"))
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_AUTHOR_NAME", "gitopen")
+            .env("GIT_AUTHOR_EMAIL", "gitopen@example.com")
+            .env("GIT_COMMITTER_NAME", "gitopen")
+            .env("GIT_COMMITTER_EMAIL", "gitopen@example.com")
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{} {:?} failed:\n{}",
+            program,
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn git(&self, dir: &str, args: &[&str]) -> String {
+        self.command("git", dir, args)
+    }
+
+    fn gitopen(&self, dir: &str, args: &[&str]) -> String {
+        let mut print_args = vec!["--print"];
+        print_args.extend_from_slice(args);
+        self.command(GITOPEN, dir, &print_args)
+    }
+
+    fn path(&self, dir: &str) -> PathBuf {
+        self.root.join(dir)
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+#[test]
+fn test_worktree_links_its_own_branch() {
+    let sandbox = Sandbox::new("worktree-branch");
+    assert!(sandbox.path("feat").join(".git").is_file());
+    assert_eq!(
+        sandbox.gitopen("feat", &["src/lib.rs:1"]),
+        "https://github.com/oren0e/gitopen/blob/feat/src/lib.rs#L1\n"
+    );
+    assert_eq!(
+        sandbox.gitopen("feat/src", &["lib.rs"]),
+        "https://github.com/oren0e/gitopen/blob/feat/src/lib.rs\n"
+    );
+    // The main worktree keeps its own HEAD, through the shared URL cache too.
+    assert_eq!(
+        sandbox.gitopen("main", &["src/lib.rs:1"]),
+        "https://github.com/oren0e/gitopen/blob/main/src/lib.rs#L1\n"
+    );
+}
+
+#[test]
+fn test_worktree_with_detached_head() {
+    let sandbox = Sandbox::new("worktree-detached");
+    sandbox.git("feat", &["checkout", "--quiet", "--detach"]);
+    let sha = sandbox.git("feat", &["rev-parse", "HEAD"]);
+    assert_eq!(
+        sandbox.gitopen("feat", &["src/lib.rs"]),
+        format!(
+            "https://github.com/oren0e/gitopen/blob/{}/src/lib.rs\n",
+            sha.trim()
+        )
+    );
+    assert_eq!(
+        sandbox.gitopen("main", &["src/lib.rs"]),
+        "https://github.com/oren0e/gitopen/blob/main/src/lib.rs\n"
+    );
+}
+
+#[test]
+fn test_worktree_branch_remote() {
+    let sandbox = Sandbox::new("worktree-remote");
+    sandbox.git(
+        "main",
+        &["remote", "add", "fork", "git@github.com:me/gitopen.git"],
+    );
+    sandbox.git("feat", &["config", "branch.feat.remote", "fork"]);
+    assert_eq!(
+        sandbox.gitopen("feat", &[]),
+        "https://github.com/me/gitopen\n"
+    );
+    assert_eq!(
+        sandbox.gitopen("main", &[]),
+        "https://github.com/oren0e/gitopen\n"
+    );
+}
+
+#[test]
+fn test_worktree_config() {
+    let sandbox = Sandbox::new("worktree-config");
+    sandbox.git("main", &["config", "extensions.worktreeConfig", "true"]);
+    assert_eq!(
+        sandbox.gitopen("feat", &[]),
+        "https://github.com/oren0e/gitopen\n"
+    );
+    sandbox.git(
+        "feat",
+        &[
+            "config",
+            "--worktree",
+            "url.git@gitlab.com:.insteadOf",
+            "git@github.com:",
+        ],
+    );
+    // The cached URL goes stale with the worktree's own config.
+    assert_eq!(
+        sandbox.gitopen("feat", &[]),
+        "https://gitlab.com/oren0e/gitopen\n"
+    );
+    assert_eq!(
+        sandbox.gitopen("main", &[]),
+        "https://github.com/oren0e/gitopen\n"
+    );
+}
+
+#[test]
+fn test_worktree_from_outside_with_dash_c() {
+    let sandbox = Sandbox::new("worktree-dash-c");
+    let feat = sandbox.path("feat");
+    assert_eq!(
+        sandbox.gitopen(".", &["-C", path_str(&feat), "src/lib.rs"]),
+        "https://github.com/oren0e/gitopen/blob/feat/src/lib.rs\n"
+    );
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str().unwrap()
+}